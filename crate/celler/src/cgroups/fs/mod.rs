@@ -406,6 +406,41 @@ impl CgroupManager for Manager {
         Ok(())
     }
 
+    /// 读取 freezer 的实际状态
+    ///
+    /// cgroup v1 直接读取 `freezer.state`（可能为 FREEZING）；
+    /// cgroup v2 的 `cgroup.freeze` 只反映请求值，冻结是否完成要看
+    /// `cgroup.events` 中的 `frozen` 字段。
+    fn freezer_state(&self) -> Result<FreezerState> {
+        let freezer_controller: &FreezerController = self
+            .cgroup
+            .controller_of()
+            .ok_or_else(|| anyhow!("freezer controller not found"))?;
+
+        if !freezer_controller.v2() {
+            return Ok(freezer_controller.state()?);
+        }
+
+        let events_path = freezer_controller.path().join("cgroup.events");
+        let events = std::fs::read_to_string(&events_path)
+            .with_context(|| format!("failed to read {}", events_path.display()))?;
+        let frozen = events
+            .lines()
+            .find_map(|l| l.strip_prefix("frozen "))
+            .map(|v| v.trim() == "1")
+            .ok_or_else(|| anyhow!("no frozen field in {}", events_path.display()))?;
+
+        if frozen {
+            return Ok(FreezerState::Frozen);
+        }
+
+        // 已请求冻结但尚未完成
+        match freezer_controller.state()? {
+            FreezerState::Frozen => Ok(FreezerState::Freezing),
+            state => Ok(state),
+        }
+    }
+
     fn destroy(&mut self) -> Result<()> {
         if let Err(err) = self.cgroup.delete() {
             warn!(
@@ -18,6 +18,14 @@ pub struct DevicesCgroupInfo {
     allowed_all: bool,
 }
 
+/// 是否启用了 `mock-cgroup` 特性：容器使用 mock 管理器，不创建 cgroup，资源限制不生效
+pub const MOCK_CGROUP: bool = cfg!(feature = "mock-cgroup");
+
+/// 宿主机是否挂载为 cgroup v2 统一层级（否则为 v1 或 hybrid 模式）
+pub fn is_cgroup2_unified_mode() -> bool {
+    cgroups::hierarchies::is_cgroup2_unified_mode()
+}
+
 pub trait CgroupManager {
     fn apply(&self, _pid: i32) -> Result<()> {
         Err(anyhow!("not supported!".to_string()))
@@ -35,6 +43,10 @@ pub trait CgroupManager {
         Err(anyhow!("not supported!"))
     }
 
+    fn freezer_state(&self) -> Result<FreezerState> {
+        Err(anyhow!("not supported!"))
+    }
+
     fn destroy(&mut self) -> Result<()> {
         Err(anyhow!("not supported!"))
     }
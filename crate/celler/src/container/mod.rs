@@ -40,7 +40,7 @@ use std::{
     path::{Path, PathBuf},
     str::FromStr,
     sync::{Arc, RwLock},
-    time::SystemTime,
+    time::{Duration, SystemTime},
 };
 
 use anyhow::{Context, Result, anyhow};
@@ -70,7 +70,9 @@ use runtime_spec::{ContainerState, State as OCIState};
 use slog::Logger;
 use tokio::fs::File;
 use types::*;
-pub use types::{ContainerStateFile, DEFAULT_DEVICES};
+pub use types::{
+    BaseContainer, Container, ContainerStateFile, DEFAULT_DEVICES, NAMESPACES, TYPETONAME,
+};
 
 #[cfg(all(not(test), not(feature = "mock-cgroup")))]
 use crate::cgroups::fs::Manager as FsManager;
@@ -79,7 +81,9 @@ use crate::cgroups::mock::Manager as FsManager;
 use crate::{
     capabilities,
     cgroups::{CgroupManager, DevicesCgroupInfo},
-    container::namespace::{get_namespaces, get_pid_namespace, join_namespaces, update_namespaces},
+    container::namespace::{
+        get_namespaces, get_pid_namespace, join_namespaces, set_namespace_paths, update_namespaces,
+    },
     log_child, mount,
     pipe::{
         pipestream::PipeStream,
@@ -545,6 +549,8 @@ impl BaseContainer for LinuxContainer {
 
         if p.init {
             self.init_process_pid = p.pid;
+            self.init_process_start_time =
+                get_process_start_time(p.pid).context("get container process's start time")?;
         }
 
         if p.init {
@@ -633,9 +639,6 @@ impl BaseContainer for LinuxContainer {
     /// - hooks 执行后才能删除 cgroup
     /// - 所有资源清理后才能删除状态文件
     async fn destroy(&mut self) -> Result<()> {
-        let spec = self.config.spec.as_ref().unwrap();
-        let st = self.oci_state()?;
-
         for process in self.processes.values() {
             match signal::kill(process.pid(), Some(Signal::SIGKILL)) {
                 Err(Errno::ESRCH) => {
@@ -652,20 +655,9 @@ impl BaseContainer for LinuxContainer {
             }
         }
 
-        // guest Poststop hook
-        // * should be executed after the container is deleted but before the delete
-        //   operation returns
-        // * the executable file is in agent namespace
-        // * should also be executed in agent namespace.
-        if let Some(hooks) = spec.hooks().as_ref() {
-            info!(self.logger, "guest Poststop hook");
-            let mut hook_states = HookStates::new();
-            hook_states.execute_hooks(
-                hooks.poststop().clone().unwrap_or_default().as_slice(),
-                Some(st),
-            )?;
-        }
+        self.run_poststop_hooks()?;
 
+        let spec = self.config.spec.as_ref().unwrap();
         self.status.transition(ContainerState::Stopped);
         mount::umount2(
             spec.root()
@@ -727,11 +719,11 @@ impl BaseContainer for LinuxContainer {
         let fd = fcntl::open(fifo.as_str(), OFlag::O_WRONLY, Mode::from_bits_truncate(0))?;
         let data: &[u8] = &[0];
         unistd::write(fd, data)?;
+        let _ = unistd::close(fd);
         info!(self.logger, "container started");
-        self.init_process_start_time = SystemTime::now()
-            .duration_since(SystemTime::UNIX_EPOCH)
-            .unwrap()
-            .as_secs();
+        // init 进程已解除阻塞，FIFO 不再需要；与 runc 一致，
+        // FIFO 是否存在也可用来判断容器是否仍处于 Created 状态。
+        let _ = fs::remove_file(&fifo).map_err(|e| warn!(self.logger, "remove exec fifo {:?}", e));
 
         self.status.transition(ContainerState::Running);
 
@@ -806,7 +798,8 @@ fn do_exec(args: &[String]) -> ! {
 impl Container for LinuxContainer {
     /// 暂停容器（冻结所有进程）
     ///
-    /// 使用 freezer cgroup 将容器内所有进程设置为 FROZEN 状态。
+    /// 使用 freezer cgroup 将容器内所有进程设置为 FROZEN 状态，
+    /// 并等待 freezer 确认冻结完成，超时则解冻并返回错误。
     /// 进程不会被杀死，而是暂停执行，可以稍后恢复。
     ///
     /// # 状态要求
@@ -825,7 +818,13 @@ impl Container for LinuxContainer {
             ));
         }
 
-        self.cgroup_manager.as_ref().freeze(FreezerState::Frozen)?;
+        let cgm = self.cgroup_manager.as_ref();
+        cgm.freeze(FreezerState::Frozen)?;
+        if let Err(e) = wait_freezer_state(cgm, FreezerState::Frozen) {
+            // 冻结未完成时解冻，避免容器停留在部分冻结的状态
+            let _ = cgm.freeze(FreezerState::Thawed);
+            return Err(e);
+        }
 
         self.status.transition(ContainerState::Paused);
 
@@ -834,7 +833,7 @@ impl Container for LinuxContainer {
 
     /// 恢复容器（解冻所有进程）
     ///
-    /// 将 freezer cgroup 状态设置为 THAWED，恢复容器内所有进程的执行。
+    /// 将 freezer cgroup 状态设置为 THAWED 并等待解冻完成，恢复容器内所有进程的执行。
     ///
     /// # 状态要求
     /// 只能恢复处于 Paused 状态的容器。
@@ -844,7 +843,9 @@ impl Container for LinuxContainer {
             return Err(anyhow!("container status is: {:?}, not paused", status));
         }
 
-        self.cgroup_manager.as_ref().freeze(FreezerState::Thawed)?;
+        let cgm = self.cgroup_manager.as_ref();
+        cgm.freeze(FreezerState::Thawed)?;
+        wait_freezer_state(cgm, FreezerState::Thawed)?;
 
         self.status.transition(ContainerState::Running);
 
@@ -889,35 +890,7 @@ impl LinuxContainer {
         )
         .context(format!("Cannot change owner of container {} root", id))?;
 
-        let spec = config.spec.as_ref().unwrap();
-        let linux_cgroups_path = spec
-            .linux()
-            .as_ref()
-            .unwrap()
-            .cgroups_path()
-            .as_ref()
-            .map_or(String::new(), |cgrp| cgrp.display().to_string());
-        let cpath = if config.use_systemd_cgroup {
-            if linux_cgroups_path.len() == 2 {
-                format!("system.slice:kata_agent:{}", id.as_str())
-            } else {
-                linux_cgroups_path.clone()
-            }
-        } else if linux_cgroups_path.is_empty() {
-            format!("/{}", id.as_str())
-        } else {
-            // if we have a systemd cgroup path we need to convert it to a fs cgroup path
-            linux_cgroups_path.replace(':', "/")
-        };
-
-        let cgroup_manager: Box<dyn CgroupManager + Send + Sync> = if config.use_systemd_cgroup {
-            todo!("systemd cgroup manager is not supported yet")
-        } else {
-            Box::new(
-                FsManager::new(cpath.as_str(), spec, devcg_info)
-                    .context("Create cgroupfs manager")?,
-            )
-        };
+        let cgroup_manager = new_cgroup_manager(id.as_str(), &config, devcg_info)?;
         info!(logger, "new cgroup_manager {:?}", &cgroup_manager);
 
         Ok(LinuxContainer {
@@ -931,10 +904,63 @@ impl LinuxContainer {
             processes: HashMap::new(),
             created: SystemTime::now(),
             init_process_pid: -1,
-            init_process_start_time: SystemTime::now()
-                .duration_since(SystemTime::UNIX_EPOCH)
-                .unwrap()
-                .as_secs(),
+            init_process_start_time: 0,
+            logger: logger.new(o!("module" => "rustjail", "subsystem" => "container", "cid" => id)),
+            #[cfg(feature = "standard-oci-runtime")]
+            console_socket: Path::new("").to_path_buf(),
+        })
+    }
+
+    /// 从磁盘恢复已创建的容器
+    ///
+    /// 读取 `{base}/{id}` 下由 `save_state` 写入的 state.json 和 config.json，
+    /// 重建 `LinuxContainer`，供 start、exec 等在新进程中执行的命令使用。
+    ///
+    /// 与 `new` 不同，这里既不重新校验 spec，也不创建容器目录；
+    /// 进程列表为空，只恢复 init 进程的 PID 和状态。
+    /// spec 中的 namespace 路径会被替换为 state.json 记录的 `namespace_paths`，
+    /// 之后以 `init == false` 的 `Process` 调用 `start_container` 即可在容器内执行进程。
+    pub fn load<T: Into<String> + Display + Clone>(
+        id: T,
+        base: T,
+        devcg_info: Option<Arc<RwLock<DevicesCgroupInfo>>>,
+        logger: &Logger,
+    ) -> Result<Self> {
+        let base = base.into();
+        let id = id.into();
+        let root = format!("{}/{}", base.as_str(), id.as_str());
+
+        let state = load_container_state(&base, &id)?;
+
+        let config_path = format!("{}/{}", root, ContainerStateFile::CONFIG_FILENAME);
+        let content = fs::read_to_string(&config_path)
+            .with_context(|| format!("read config file: {}", config_path))?;
+        let mut config: Config = serde_json::from_str(&content)
+            .with_context(|| format!("parse config file: {}", config_path))?;
+
+        // exec 进程需要加入 init 进程的 namespace，以 state.json 中记录的路径为准
+        if let Some(spec) = config.spec.as_mut() {
+            set_namespace_paths(spec, &state.namespace_paths)?;
+        }
+
+        let cgroup_manager = new_cgroup_manager(id.as_str(), &config, devcg_info)?;
+        let status = state.container_state()?;
+
+        Ok(LinuxContainer {
+            id: id.clone(),
+            root,
+            cgroup_manager,
+            status: ContainerStatus {
+                pre_status: status,
+                cur_status: status,
+            },
+            uid_map_path: String::from(""),
+            gid_map_path: "".to_string(),
+            config,
+            processes: HashMap::new(),
+            created: SystemTime::UNIX_EPOCH + Duration::from_secs(state.created),
+            init_process_pid: state.init_process_pid,
+            init_process_start_time: state.init_process_start_time,
             logger: logger.new(o!("module" => "rustjail", "subsystem" => "container", "cid" => id)),
             #[cfg(feature = "standard-oci-runtime")]
             console_socket: Path::new("").to_path_buf(),
@@ -954,8 +980,11 @@ impl LinuxContainer {
     /// - 状态和时间戳
     /// - Namespace 路径（用于 exec 命令）
     ///
+    /// 同时写入 config.json，使 `load` 能够重建容器。
+    /// 监督进程记录的字段（supervisor_pid、退出信息）和 bundle 从已有的 state.json 中保留。
+    ///
     /// # 文件位置
-    /// `{self.root}/state.json`
+    /// `{self.root}/state.json`、`{self.root}/config.json`
     ///
     /// # 使用时机
     /// - 容器启动成功后（run/start）
@@ -973,12 +1002,20 @@ impl LinuxContainer {
             .map(|r| r.path().display().to_string())
             .unwrap_or_default();
 
-        let bundle = {
-            let root_path = fs::canonicalize(&rootfs).unwrap_or_else(|_| PathBuf::from(&rootfs));
-            root_path
-                .parent()
-                .map(|p| p.to_string_lossy().to_string())
-                .unwrap_or_else(|| rootfs.clone())
+        let state_path = format!("{}/{}", self.root, ContainerStateFile::STATE_FILENAME);
+        let prev = read_state_file(&state_path).ok();
+
+        // 首次保存时由 rootfs 推导 bundle，之后沿用已记录的值
+        let bundle = match prev.as_ref() {
+            Some(p) if !p.bundle.is_empty() => p.bundle.clone(),
+            _ => {
+                let root_path =
+                    fs::canonicalize(&rootfs).unwrap_or_else(|_| PathBuf::from(&rootfs));
+                root_path
+                    .parent()
+                    .map(|p| p.to_string_lossy().to_string())
+                    .unwrap_or_else(|| rootfs.clone())
+            }
         };
 
         let state_file = ContainerStateFile {
@@ -994,17 +1031,102 @@ impl LinuxContainer {
                 .map(|d| d.as_secs())
                 .unwrap_or(0),
             namespace_paths: self.get_namespace_paths(),
+            supervisor_pid: prev.as_ref().and_then(|p| p.supervisor_pid),
+            exit_code: prev.as_ref().and_then(|p| p.exit_code),
+            exit_signal: prev.as_ref().and_then(|p| p.exit_signal),
+            exited: prev.as_ref().and_then(|p| p.exited),
         };
 
-        let state_path = format!("{}/{}", self.root, ContainerStateFile::STATE_FILENAME);
-        let json = serde_json::to_string_pretty(&state_file).context("serialize state to JSON")?;
-        fs::write(&state_path, json)
-            .with_context(|| format!("write state file: {}", state_path))?;
+        let config_path = format!("{}/{}", self.root, ContainerStateFile::CONFIG_FILENAME);
+        let json = serde_json::to_string(&self.config).context("serialize config to JSON")?;
+        fs::write(&config_path, json)
+            .with_context(|| format!("write config file: {}", config_path))?;
+
+        write_state_file(&state_path, &state_file)?;
 
         info!(self.logger, "container state saved"; "path" => &state_path);
         Ok(())
     }
 
+    /// 向容器进程发送信号
+    ///
+    /// # 参数
+    /// - `sig`: 要发送的信号
+    /// - `all`: 为 true 时向 `pids` 返回的所有进程发送，否则只发送给 init 进程
+    ///
+    /// # 说明
+    /// 发送前会比较 init 进程的启动时间，PID 已被复用时返回错误，避免误杀宿主机进程。
+    /// 与 runc 一致，`all` 模式下先冻结 cgroup 再逐个发送信号，防止进程在此期间 fork；
+    /// 容器原本未暂停时发送完成后解冻。无法列出容器进程时只向 init 进程发送并返回错误。
+    pub fn signal(&self, sig: Signal, all: bool) -> Result<()> {
+        if !is_init_process_alive(self.init_process_pid, self.init_process_start_time) {
+            return Err(anyhow!(
+                "container {} is not running (init pid {})",
+                self.id,
+                self.init_process_pid
+            ));
+        }
+
+        if !all {
+            signal::kill(Pid::from_raw(self.init_process_pid), Some(sig))
+                .with_context(|| format!("kill init process {}", self.init_process_pid))?;
+            return Ok(());
+        }
+
+        let cgm = self.cgroup_manager.as_ref();
+        let paused = self.status() == ContainerState::Paused;
+        if !paused {
+            let _ = cgm
+                .freeze(FreezerState::Frozen)
+                .map_err(|e| warn!(self.logger, "freeze before signal all: {:?}", e));
+        }
+
+        // 无法列出容器进程时仍向 init 进程发送信号，但返回错误，
+        // 不能让调用方以为其他进程也收到了信号
+        let pids = self.pids();
+        let targets = match &pids {
+            Ok(pids) => pids.clone(),
+            Err(_) => vec![self.init_process_pid],
+        };
+        for pid in targets {
+            match signal::kill(Pid::from_raw(pid), Some(sig)) {
+                Ok(_) | Err(Errno::ESRCH) => {}
+                Err(e) => warn!(self.logger, "kill process {} error: {:?}", pid, e),
+            }
+        }
+
+        if !paused {
+            let _ = cgm
+                .freeze(FreezerState::Thawed)
+                .map_err(|e| warn!(self.logger, "thaw after signal all: {:?}", e));
+        }
+
+        pids.map(|_| ())
+            .context("only the init process was signaled")
+    }
+
+    /// 获取容器内所有进程的 PID（宿主机视角）
+    ///
+    /// 优先使用 `CgroupManager::get_pids`；cgroup 不可用时（如 mock-cgroup）
+    /// 退而查找与 init 进程处于同一 pid namespace 的进程。结果总是包含 init 进程。
+    /// 两者都无法区分容器进程时（mock-cgroup 且容器共享宿主机 pid namespace）返回错误。
+    pub fn pids(&self) -> Result<Vec<i32>> {
+        let mut pids = self
+            .cgroup_manager
+            .as_ref()
+            .get_pids()
+            .context("get cgroup pids")?;
+        if pids.is_empty() {
+            pids = pid_namespace_members(self.init_process_pid)?;
+        }
+        if !pids.contains(&self.init_process_pid) {
+            pids.push(self.init_process_pid);
+        }
+        pids.sort_unstable();
+
+        Ok(pids)
+    }
+
     /// 获取容器的 namespace 路径
     ///
     /// 返回 /proc/{pid}/ns/ 下各 namespace 的路径映射。
@@ -1042,6 +1164,118 @@ impl LinuxContainer {
     pub async fn destroy_container(&mut self) -> Result<()> {
         BaseContainer::destroy(self).await
     }
+
+    /// 执行 spec 中的 poststop 钩子，钩子从 stdin 读取容器的 OCI 状态
+    ///
+    /// `destroy` 会调用它；不经过 `destroy` 删除容器的调用方（如 runc 兼容的
+    /// `delete`，rootfs 由调用方管理，不能卸载）需要自行调用。
+    pub fn run_poststop_hooks(&self) -> Result<()> {
+        let spec = self
+            .config
+            .spec
+            .as_ref()
+            .ok_or_else(|| anyhow!("spec not found"))?;
+
+        // guest Poststop hook
+        // * should be executed after the container is deleted but before the delete
+        //   operation returns
+        // * the executable file is in agent namespace
+        // * should also be executed in agent namespace.
+        if let Some(hooks) = spec.hooks().as_ref() {
+            info!(self.logger, "guest Poststop hook");
+            let mut hook_states = HookStates::new();
+            hook_states.execute_hooks(
+                hooks.poststop().clone().unwrap_or_default().as_slice(),
+                Some(self.oci_state()?),
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// 根据容器配置创建 cgroup 管理器
+///
+/// 计算容器的 cgroup 路径：未指定 `cgroupsPath` 时使用 `/{id}`，
+/// systemd 格式的路径会被转换为 cgroupfs 路径。
+fn new_cgroup_manager(
+    id: &str,
+    config: &Config,
+    devcg_info: Option<Arc<RwLock<DevicesCgroupInfo>>>,
+) -> Result<Box<dyn CgroupManager + Send + Sync>> {
+    let spec = config
+        .spec
+        .as_ref()
+        .ok_or_else(|| anyhow!("spec not found"))?;
+    let linux_cgroups_path = spec
+        .linux()
+        .as_ref()
+        .ok_or_else(|| anyhow!(MissingLinux))?
+        .cgroups_path()
+        .as_ref()
+        .map_or(String::new(), |cgrp| cgrp.display().to_string());
+    let cpath = if config.use_systemd_cgroup {
+        if linux_cgroups_path.len() == 2 {
+            format!("system.slice:kata_agent:{}", id)
+        } else {
+            linux_cgroups_path.clone()
+        }
+    } else if linux_cgroups_path.is_empty() {
+        format!("/{}", id)
+    } else {
+        // if we have a systemd cgroup path we need to convert it to a fs cgroup path
+        linux_cgroups_path.replace(':', "/")
+    };
+
+    let cgroup_manager: Box<dyn CgroupManager + Send + Sync> = if config.use_systemd_cgroup {
+        return Err(anyhow!("systemd cgroup manager is not supported yet"));
+    } else {
+        Box::new(
+            FsManager::new(cpath.as_str(), spec, devcg_info).context("Create cgroupfs manager")?,
+        )
+    };
+
+    Ok(cgroup_manager)
+}
+
+/// 等待 freezer 达到目标状态的最大轮询次数
+const FREEZER_RETRIES: u32 = 100;
+
+/// 两次轮询 freezer 状态之间的间隔
+const FREEZER_INTERVAL: Duration = Duration::from_millis(10);
+
+/// 等待 freezer 实际进入目标状态
+///
+/// 写入 freezer 只是发起请求：cgroup v1 可能停留在 FREEZING（如有进程处于
+/// 不可中断睡眠），cgroup v2 要等 `cgroup.events` 报告 `frozen 1`。
+/// 每次轮询未达到目标时重新写入一次，与 runc 的做法一致。
+///
+/// mock 管理器不冻结进程，也没有可确认的 freezer 状态，直接返回；
+/// CLI 在 mock-cgroup 构建下会拒绝暂停和恢复容器。
+fn wait_freezer_state(cgm: &(dyn CgroupManager + Send + Sync), target: FreezerState) -> Result<()> {
+    if cgm.as_any()?.is::<crate::cgroups::mock::Manager>() {
+        return Ok(());
+    }
+
+    let mut current = cgm.freezer_state()?;
+    for _ in 0..FREEZER_RETRIES {
+        if current == target {
+            return Ok(());
+        }
+        cgm.freeze(target)?;
+        std::thread::sleep(FREEZER_INTERVAL);
+        current = cgm.freezer_state()?;
+    }
+
+    if current == target {
+        return Ok(());
+    }
+
+    Err(anyhow!(
+        "freezer did not reach {:?} state, current state: {:?}",
+        target,
+        current
+    ))
 }
 
 // ============================================================================
@@ -1051,7 +1285,7 @@ impl LinuxContainer {
 /// 从状态目录加载容器状态
 ///
 /// # 参数
-/// - `state_base`: 状态基础目录（如 `/tmp/runcell/states`）
+/// - `state_base`: 状态基础目录（如 `/run/runcell/states`）
 /// - `container_id`: 容器 ID
 ///
 /// # 返回
@@ -1059,7 +1293,7 @@ impl LinuxContainer {
 ///
 /// # 示例
 /// ```rust,no_run
-/// let state = load_container_state("/tmp/runcell/states", "my-container")?;
+/// let state = load_container_state("/run/runcell/states", "my-container")?;
 /// println!("Container PID: {}", state.init_process_pid);
 /// ```
 pub fn load_container_state(state_base: &str, container_id: &str) -> Result<ContainerStateFile> {
@@ -1069,11 +1303,41 @@ pub fn load_container_state(state_base: &str, container_id: &str) -> Result<Cont
         container_id,
         ContainerStateFile::STATE_FILENAME
     );
-    let content = fs::read_to_string(&state_path)
+    read_state_file(&state_path)
+}
+
+/// 将容器状态写回状态目录
+///
+/// 先写临时文件再 rename，保证监督进程与其他命令并发读写时
+/// 不会读到写了一半的 state.json。
+///
+/// # 参数
+/// - `state_base`: 状态基础目录（如 `/run/runcell/states`）
+/// - `state`: 容器状态，写入 `{state_base}/{state.id}/state.json`
+pub fn save_container_state(state_base: &str, state: &ContainerStateFile) -> Result<()> {
+    let state_path = format!(
+        "{}/{}/{}",
+        state_base,
+        state.id,
+        ContainerStateFile::STATE_FILENAME
+    );
+    write_state_file(&state_path, state)
+}
+
+fn read_state_file(state_path: &str) -> Result<ContainerStateFile> {
+    let content = fs::read_to_string(state_path)
         .with_context(|| format!("read state file: {}", state_path))?;
     serde_json::from_str(&content).with_context(|| format!("parse state file: {}", state_path))
 }
 
+fn write_state_file(state_path: &str, state: &ContainerStateFile) -> Result<()> {
+    let json = serde_json::to_string_pretty(state).context("serialize state to JSON")?;
+    let tmp_path = format!("{}.tmp", state_path);
+    fs::write(&tmp_path, json).with_context(|| format!("write state file: {}", tmp_path))?;
+    fs::rename(&tmp_path, state_path)
+        .with_context(|| format!("rename {} to {}", tmp_path, state_path))
+}
+
 /// 检查进程是否仍在运行
 ///
 /// 通过检查 /proc/{pid} 目录是否存在来判断
@@ -1081,6 +1345,89 @@ pub fn is_process_running(pid: i32) -> bool {
     pid > 0 && Path::new(&format!("/proc/{}", pid)).exists()
 }
 
+/// 读取进程的启动时间
+///
+/// 返回 `/proc/{pid}/stat` 的第 22 列 starttime（系统启动后的时钟滴答数）。
+/// PID 会被复用，但 (PID, starttime) 可以唯一标识一个进程。
+pub fn get_process_start_time(pid: i32) -> Result<u64> {
+    let (_, start_time) = read_proc_stat(pid)?;
+    Ok(start_time)
+}
+
+/// 检查容器 init 进程是否仍然存活
+///
+/// 除了 `/proc/{pid}` 存在外，还要求进程启动时间与记录值一致且不是僵尸进程。
+/// `start_time` 为 0 时（旧版本写入的状态文件）只检查进程是否存在。
+pub fn is_init_process_alive(pid: i32, start_time: u64) -> bool {
+    if pid <= 0 {
+        return false;
+    }
+
+    match read_proc_stat(pid) {
+        Ok((state, t)) => state != 'Z' && (start_time == 0 || t == start_time),
+        Err(_) => false,
+    }
+}
+
+/// 列出与 `pid` 处于同一 pid namespace 的所有进程
+///
+/// `pid` 与当前进程共享 pid namespace 时（容器未隔离 pid namespace），
+/// 无法据此区分容器进程，返回错误。
+fn pid_namespace_members(pid: i32) -> Result<Vec<i32>> {
+    let target = fs::read_link(format!("/proc/{}/ns/pid", pid))
+        .with_context(|| format!("read pid namespace of {}", pid))?;
+    let own = fs::read_link("/proc/self/ns/pid").context("read own pid namespace")?;
+    if target == own {
+        return Err(anyhow!(
+            "process {} shares the host pid namespace and no cgroup is available to list its processes",
+            pid
+        ));
+    }
+
+    let mut pids = Vec::new();
+    for entry in fs::read_dir("/proc").context("read /proc")? {
+        let Some(p) = entry?
+            .file_name()
+            .to_str()
+            .and_then(|name| name.parse::<i32>().ok())
+        else {
+            continue;
+        };
+        // 进程可能在遍历期间退出
+        if fs::read_link(format!("/proc/{}/ns/pid", p)).is_ok_and(|ns| ns == target) {
+            pids.push(p);
+        }
+    }
+
+    Ok(pids)
+}
+
+/// 解析 `/proc/{pid}/stat`，返回进程状态字符和 starttime
+fn read_proc_stat(pid: i32) -> Result<(char, u64)> {
+    let path = format!("/proc/{}/stat", pid);
+    let content = fs::read_to_string(&path).with_context(|| format!("read {}", path))?;
+
+    // comm 字段可能包含空格和括号，从最后一个 ')' 之后开始解析
+    let rest = content
+        .rfind(')')
+        .map(|i| &content[i + 1..])
+        .ok_or_else(|| anyhow!("invalid stat format: {}", path))?;
+    let fields: Vec<&str> = rest.split_whitespace().collect();
+
+    // fields[0] 为第 3 列 state，starttime 为第 22 列
+    let state = fields
+        .first()
+        .and_then(|s| s.chars().next())
+        .ok_or_else(|| anyhow!("missing state field: {}", path))?;
+    let start_time = fields
+        .get(19)
+        .ok_or_else(|| anyhow!("missing starttime field: {}", path))?
+        .parse::<u64>()
+        .with_context(|| format!("parse starttime: {}", path))?;
+
+    Ok((state, start_time))
+}
+
 fn setid(uid: Uid, gid: Gid) -> Result<()> {
     // set uid/gid
     capctl::prctl::set_keepcaps(true)
@@ -1938,6 +2285,16 @@ mod tests {
         container.and_then(op)
     }
 
+    #[test]
+    fn test_new_cgroup_manager_systemd_unsupported() {
+        let mut config = create_dummy_opts();
+        config.use_systemd_cgroup = true;
+
+        let ret = new_cgroup_manager("some_id", &config, None);
+        assert!(ret.is_err());
+        assert!(format!("{:?}", ret.err().unwrap()).contains("systemd"));
+    }
+
     #[test]
     fn test_linuxcontainer_pause_bad_status() {
         let ret = new_linux_container_and_then(|mut c: LinuxContainer| {
@@ -1990,6 +2347,38 @@ mod tests {
         assert!(ret.is_ok(), "Expecting Ok, Got {:?}", ret);
     }
 
+    #[test]
+    fn test_linuxcontainer_pause_resume_real_freezer() {
+        skip_if_not_root!();
+
+        let cpath = format!("/runcell-freezer-test-{}", std::process::id());
+        let cgm = crate::cgroups::fs::Manager::new(&cpath, &Spec::default(), None).unwrap();
+        let mut child = std::process::Command::new("sleep")
+            .arg("30")
+            .spawn()
+            .unwrap();
+        cgm.apply(child.id() as i32).unwrap();
+
+        let ret = new_linux_container_and_then(|mut c: LinuxContainer| {
+            c.cgroup_manager = Box::new(cgm);
+            c.status.transition(ContainerState::Running);
+
+            c.pause()?;
+            assert_eq!(c.status(), ContainerState::Paused);
+            assert_eq!(c.cgroup_manager.freezer_state()?, FreezerState::Frozen);
+
+            c.resume()?;
+            assert_eq!(c.status(), ContainerState::Running);
+            assert_eq!(c.cgroup_manager.freezer_state()?, FreezerState::Thawed);
+
+            child.kill()?;
+            child.wait()?;
+            c.cgroup_manager.destroy()
+        });
+
+        assert!(ret.is_ok(), "Expecting Ok, Got {:?}", ret);
+    }
+
     #[test]
     fn test_linuxcontainer_state() {
         let ret = new_linux_container_and_then(|c: LinuxContainer| c.state());
@@ -2028,6 +2417,108 @@ mod tests {
         assert!(ret.is_ok(), "Expecting Ok, Got {:?}", ret);
     }
 
+    #[test]
+    fn test_linuxcontainer_load() {
+        let (c, dir) = new_linux_container();
+        let mut c = c.unwrap();
+        c.init_process_pid = 1234;
+        c.status.transition(ContainerState::Running);
+        c.save_state().unwrap();
+
+        let base = dir.path().join("rootfs");
+        let loaded = LinuxContainer::load("some_id", base.to_str().unwrap(), None, &sl());
+        assert!(loaded.is_ok(), "Expecting Ok, Got {:?}", loaded);
+
+        let loaded = loaded.unwrap();
+        assert_eq!(loaded.init_process_pid, 1234);
+        assert_eq!(loaded.status(), ContainerState::Running);
+        assert_eq!(loaded.config.spec, c.config.spec);
+    }
+
+    #[test]
+    fn test_linuxcontainer_load_namespace_paths() {
+        let (c, dir) = new_linux_container();
+        let mut c = c.unwrap();
+        let pid = unistd::getpid().as_raw();
+        c.init_process_pid = pid;
+        c.save_state().unwrap();
+
+        let base = dir.path().join("rootfs");
+        let loaded = LinuxContainer::load("some_id", base.to_str().unwrap(), None, &sl()).unwrap();
+        let spec = loaded.config.spec.as_ref().unwrap();
+        let namespaces = spec.linux().as_ref().unwrap().namespaces().clone().unwrap();
+
+        let net = namespaces
+            .iter()
+            .find(|ns| ns.typ() == oci::LinuxNamespaceType::Network)
+            .unwrap();
+        assert_eq!(
+            net.path().as_ref().unwrap(),
+            &PathBuf::from(format!("/proc/{}/ns/net", pid))
+        );
+    }
+
+    #[test]
+    fn test_linuxcontainer_load_not_found() {
+        let (_c, dir) = new_linux_container();
+        let base = dir.path().join("rootfs");
+        let ret = LinuxContainer::load("no_such_id", base.to_str().unwrap(), None, &sl());
+        assert!(ret.is_err(), "Expecting Err, Got {:?}", ret);
+    }
+
+    #[test]
+    fn test_save_state_keeps_exit_status() {
+        let (c, dir) = new_linux_container();
+        let c = c.unwrap();
+        c.save_state().unwrap();
+
+        let base = dir.path().join("rootfs");
+        let base = base.to_str().unwrap();
+        let mut state = load_container_state(base, "some_id").unwrap();
+        state.supervisor_pid = Some(4321);
+        state.set_exited(137, Some(9));
+        save_container_state(base, &state).unwrap();
+
+        // 后续的 save_state 不应覆盖监督进程记录的退出信息
+        c.save_state().unwrap();
+        let state = load_container_state(base, "some_id").unwrap();
+        assert_eq!(state.supervisor_pid, Some(4321));
+        assert_eq!(state.exit_code, Some(137));
+        assert_eq!(state.exit_signal, Some(9));
+        assert!(state.exited.is_some());
+        assert!(!state.is_running());
+    }
+
+    #[test]
+    fn test_is_init_process_alive() {
+        let pid = unistd::getpid().as_raw();
+        let start_time = get_process_start_time(pid).unwrap();
+        assert!(start_time > 0);
+
+        assert!(is_init_process_alive(pid, start_time));
+        assert!(is_init_process_alive(pid, 0));
+        // 启动时间不一致，说明 PID 已被复用
+        assert!(!is_init_process_alive(pid, start_time + 1));
+        assert!(!is_init_process_alive(-1, 0));
+    }
+
+    #[test]
+    fn test_pid_namespace_members() {
+        // 与当前进程共享 pid namespace 时无法区分容器进程
+        let pid = unistd::getpid().as_raw();
+        assert!(pid_namespace_members(pid).is_err());
+        assert!(pid_namespace_members(-1).is_err());
+    }
+
+    #[test]
+    fn test_linuxcontainer_signal_not_running() {
+        let ret = new_linux_container_and_then(|c: LinuxContainer| {
+            // init 进程尚未启动，不能发送信号
+            c.signal(Signal::SIGTERM, false)
+        });
+        assert!(ret.is_err(), "Expecting Err, Got {:?}", ret);
+    }
+
     #[test]
     fn test_linuxcontainer_config() {
         let ret = new_linux_container_and_then(|c: LinuxContainer| Ok(c));
@@ -18,7 +18,7 @@
 //! 6. 执行 prestart hooks
 //! 7. 子进程执行容器命令
 
-use std::{os::fd::RawFd, path::PathBuf};
+use std::{collections::HashMap, os::fd::RawFd, path::PathBuf};
 
 use anyhow::{Result, anyhow};
 use kata_sys_utils::hooks::HookStates;
@@ -98,6 +98,39 @@ pub fn update_namespaces(logger: &Logger, spec: &mut Spec, init_pid: RawFd) -> R
     Ok(())
 }
 
+/// 使用已记录的 namespace 路径更新 OCI spec
+///
+/// `paths` 为 namespace 名称（如 `net`）到路径的映射，通常来自 state.json 中的
+/// `namespace_paths`。只更新 spec 中已配置的 namespace 类型，
+/// 使 exec 进程加入容器 init 进程所在的 namespace，而不是创建新的。
+///
+/// # 参数
+/// - `spec`: OCI 规范配置（可变引用）
+/// - `paths`: namespace 名称到路径的映射
+///
+/// # 返回
+/// - `Ok(())`: 成功更新 namespace 配置
+/// - `Err(...)`: spec 缺少 linux 字段
+pub fn set_namespace_paths(spec: &mut Spec, paths: &HashMap<String, String>) -> Result<()> {
+    let linux = spec
+        .linux_mut()
+        .as_mut()
+        .ok_or_else(|| anyhow!("Spec didn't contain linux field"))?;
+
+    if let Some(namespaces) = linux.namespaces_mut().as_mut() {
+        for namespace in namespaces.iter_mut() {
+            if let Some(path) = TYPETONAME
+                .get(&namespace.typ())
+                .and_then(|name| paths.get(*name))
+            {
+                namespace.set_path(Some(PathBuf::from(path)));
+            }
+        }
+    }
+
+    Ok(())
+}
+
 /// 获取 PID namespace 配置信息
 ///
 /// 从 OCI Linux 配置中提取 PID namespace 的相关信息，包括是否启用以及
@@ -15,9 +15,9 @@
 //!    └────────→ Stopped ←────┘
 //! ```
 
-use std::{collections::HashMap, path::PathBuf, sync::Arc};
+use std::{collections::HashMap, path::PathBuf, sync::Arc, time::SystemTime};
 
-use anyhow::Result;
+use anyhow::{Result, anyhow};
 use async_trait::async_trait;
 use libc::pid_t;
 use nix::sched::CloneFlags;
@@ -219,9 +219,10 @@ pub struct SyncPc {
 /// - `list` 命令：读取所有容器的 state.json 以显示状态
 /// - `delete` 命令：读取 PID 以便在删除前 kill 进程
 /// - `exec` 命令：读取 namespace 路径以进入容器
+/// - 监督进程（shim）：记录 init 进程的退出码、退出时间和信号
 ///
 /// # 文件位置
-/// `/tmp/runcell/states/{container_id}/state.json`
+/// `/run/runcell/states/{container_id}/state.json`
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ContainerStateFile {
     /// 容器唯一标识符
@@ -230,7 +231,9 @@ pub struct ContainerStateFile {
     /// Init 进程的 PID（容器主进程）
     pub init_process_pid: i32,
 
-    /// Init 进程启动时间戳（Unix 时间戳，秒）
+    /// Init 进程启动时间（`/proc/{pid}/stat` 第 22 列，系统启动后的时钟滴答数）
+    ///
+    /// 与 PID 一起唯一标识 init 进程，用于识别 PID 复用。
     pub init_process_start_time: u64,
 
     /// 容器当前状态 ("Created", "Running", "Paused", "Stopped")
@@ -249,11 +252,70 @@ pub struct ContainerStateFile {
     /// 用于 exec 命令进入容器
     #[serde(default, skip_serializing_if = "HashMap::is_empty")]
     pub namespace_paths: HashMap<String, String>,
+
+    /// 监督进程（shim）的 PID，前台运行时为 CLI 进程自身
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub supervisor_pid: Option<i32>,
+
+    /// Init 进程退出码（被信号终止时为 128 + 信号值）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exit_code: Option<i32>,
+
+    /// 终止 init 进程的信号值（正常退出时为空）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exit_signal: Option<i32>,
+
+    /// Init 进程退出时间（Unix 时间戳，秒）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exited: Option<u64>,
 }
 
 impl ContainerStateFile {
+    /// 持久化的容器配置文件名
+    ///
+    /// 保存 `CreateOpts`（含更新过 namespace 路径的 OCI spec），
+    /// 供 start、exec 等后续命令在新进程中重建容器。
+    pub const CONFIG_FILENAME: &'static str = "config.json";
     /// 状态文件名常量
     pub const STATE_FILENAME: &'static str = "state.json";
+
+    /// 将持久化的状态字符串解析为 `ContainerState`
+    pub fn container_state(&self) -> Result<ContainerState> {
+        match self.status.as_str() {
+            "Creating" => Ok(ContainerState::Creating),
+            "Created" => Ok(ContainerState::Created),
+            "Running" => Ok(ContainerState::Running),
+            "Paused" => Ok(ContainerState::Paused),
+            "Stopped" => Ok(ContainerState::Stopped),
+            s => Err(anyhow!("unknown container status: {}", s)),
+        }
+    }
+
+    /// 判断 init 进程是否仍然存活
+    ///
+    /// 已记录退出信息的容器一律视为已停止；否则同时比较 PID 和进程启动时间，
+    /// 避免 PID 被复用后把已退出的容器误判为 Running。
+    pub fn is_running(&self) -> bool {
+        self.exited.is_none()
+            && super::is_init_process_alive(self.init_process_pid, self.init_process_start_time)
+    }
+
+    /// 记录 init 进程的退出信息，并将状态置为 Stopped
+    ///
+    /// # 参数
+    /// - `exit_code`: 退出码（被信号终止时为 128 + 信号值）
+    /// - `signal`: 终止进程的信号值
+    pub fn set_exited(&mut self, exit_code: i32, signal: Option<i32>) {
+        self.status = format!("{:?}", ContainerState::Stopped);
+        self.exit_code = Some(exit_code);
+        self.exit_signal = signal;
+        self.exited = Some(
+            SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0),
+        );
+    }
 }
 
 // ============================================================================
@@ -0,0 +1,362 @@
+//! 容器日志（json-file 格式）
+//!
+//! 容器的每一行输出记录为一个 JSON 对象，格式与 docker 的 json-file 日志驱动一致：
+//! ```text
+//! {"log":"hello\n","stream":"stdout","time":"2024-01-01T08:00:00.123456789Z"}
+//! ```
+//! 日志文件超过 `max_size` 时轮转：`container.log` → `container.log.1` → ... →
+//! `container.log.{max_file - 1}`，最旧的文件被删除；`max_file` 为 1 时直接清空当前文件。
+
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, FixedOffset, SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+
+/// 容器状态目录中的日志文件名
+pub const LOG_FILE_NAME: &str = "container.log";
+
+/// 单条日志的最大长度，超过时不等换行直接写出（与 docker 一致）
+const MAX_LINE_SIZE: usize = 16 * 1024;
+
+/// 日志轮转配置
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LogConfig {
+    /// 单个日志文件的最大字节数，`None` 表示不轮转
+    pub max_size: Option<u64>,
+    /// 最多保留的日志文件数（包括当前文件）
+    pub max_file: u32,
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        Self {
+            max_size: None,
+            max_file: 1,
+        }
+    }
+}
+
+/// 一条日志记录
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LogEntry {
+    /// 输出内容，完整的行以 `\n` 结尾
+    pub log: String,
+    /// 输出流：`stdout` 或 `stderr`
+    pub stream: String,
+    /// RFC3339 格式的时间戳（纳秒精度，UTC）
+    pub time: String,
+}
+
+impl LogEntry {
+    fn new(stream: &str, data: &[u8]) -> Self {
+        Self {
+            log: String::from_utf8_lossy(data).to_string(),
+            stream: stream.to_string(),
+            time: Utc::now().to_rfc3339_opts(SecondsFormat::Nanos, true),
+        }
+    }
+
+    /// 解析记录的时间戳
+    pub fn timestamp(&self) -> Option<DateTime<FixedOffset>> {
+        DateTime::parse_from_rfc3339(&self.time).ok()
+    }
+}
+
+struct LogFile {
+    file: File,
+    size: u64,
+}
+
+/// json-file 日志写入器
+///
+/// 多个输出流共享同一个日志文件，每条记录作为一次完整的写入追加到文件末尾。
+pub struct JsonFileLogger {
+    path: PathBuf,
+    config: LogConfig,
+    file: Mutex<LogFile>,
+}
+
+impl JsonFileLogger {
+    /// 打开（或创建）日志文件，新记录追加到已有内容之后
+    pub fn open(path: &Path, config: LogConfig) -> Result<Arc<Self>> {
+        let file = open_log_file(path)?;
+        let size = file.metadata()?.len();
+
+        Ok(Arc::new(Self {
+            path: path.to_path_buf(),
+            config,
+            file: Mutex::new(LogFile { file, size }),
+        }))
+    }
+
+    /// 创建某个输出流的写入端
+    ///
+    /// # 参数
+    /// - `stream`: 输出流名称，`stdout` 或 `stderr`
+    pub fn writer(self: &Arc<Self>, stream: &str) -> LogWriter {
+        LogWriter {
+            logger: self.clone(),
+            stream: stream.to_string(),
+            buf: Vec::new(),
+        }
+    }
+
+    fn append(&self, entry: &LogEntry) -> Result<()> {
+        let mut line = serde_json::to_vec(entry)?;
+        line.push(b'\n');
+
+        let mut file = self.file.lock().unwrap();
+        if let Some(max_size) = self.config.max_size
+            && file.size > 0
+            && file.size + line.len() as u64 > max_size
+        {
+            self.rotate(&mut file)?;
+        }
+
+        file.file.write_all(&line)?;
+        file.size += line.len() as u64;
+
+        Ok(())
+    }
+
+    fn rotate(&self, file: &mut LogFile) -> Result<()> {
+        if self.config.max_file <= 1 {
+            // 以追加模式打开，清空后的写入从头开始
+            file.file.set_len(0).context("清空日志文件失败")?;
+            file.size = 0;
+            return Ok(());
+        }
+
+        for i in (1..self.config.max_file - 1).rev() {
+            match fs::rename(rotated_path(&self.path, i), rotated_path(&self.path, i + 1)) {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => {
+                    return Err(anyhow::Error::new(e).context("轮转日志文件失败"));
+                }
+                _ => {}
+            }
+        }
+        fs::rename(&self.path, rotated_path(&self.path, 1)).context("轮转日志文件失败")?;
+
+        file.file = open_log_file(&self.path)?;
+        file.size = 0;
+
+        Ok(())
+    }
+}
+
+fn open_log_file(path: &Path) -> Result<File> {
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("无法打开日志文件: {}", path.display()))
+}
+
+/// 第 `index` 个轮转出的日志文件，如 `container.log.1`
+fn rotated_path(path: &Path, index: u32) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{}", index));
+    PathBuf::from(name)
+}
+
+/// 单个输出流的日志写入端
+///
+/// 按行切分写入的数据，每行生成一条记录；不以换行结尾的数据暂存到下次写入，
+/// 写入端被释放时写出剩余部分。
+pub struct LogWriter {
+    logger: Arc<JsonFileLogger>,
+    stream: String,
+    buf: Vec<u8>,
+}
+
+impl LogWriter {
+    fn emit(&mut self, len: usize) -> io::Result<()> {
+        let data: Vec<u8> = self.buf.drain(..len).collect();
+        self.logger
+            .append(&LogEntry::new(&self.stream, &data))
+            .map_err(io::Error::other)
+    }
+}
+
+impl Write for LogWriter {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        self.buf.extend_from_slice(data);
+
+        while let Some(pos) = self.buf.iter().position(|b| *b == b'\n') {
+            self.emit(pos + 1)?;
+        }
+        while self.buf.len() >= MAX_LINE_SIZE {
+            self.emit(MAX_LINE_SIZE)?;
+        }
+
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Drop for LogWriter {
+    fn drop(&mut self) {
+        if !self.buf.is_empty() {
+            let _ = self.emit(self.buf.len());
+        }
+    }
+}
+
+/// 按时间顺序列出现有的日志文件（最旧的轮转文件在前，当前文件在最后）
+pub fn log_files(path: &Path) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = (1..)
+        .map(|i| rotated_path(path, i))
+        .take_while(|p| p.exists())
+        .collect();
+    files.reverse();
+
+    if path.exists() {
+        files.push(path.to_path_buf());
+    }
+
+    files
+}
+
+/// 解析日志内容中的记录
+///
+/// 跳过无法解析的行（如正在写入、尚不完整的最后一行）。
+pub fn parse_entries(content: &str) -> Vec<LogEntry> {
+    content
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect()
+}
+
+/// 按时间顺序读取所有日志文件中的记录
+pub fn read_entries(path: &Path) -> Result<Vec<LogEntry>> {
+    let mut entries = Vec::new();
+    for file in log_files(path) {
+        let content = fs::read_to_string(&file)
+            .with_context(|| format!("无法读取日志文件: {}", file.display()))?;
+        entries.extend(parse_entries(&content));
+    }
+
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::tempdir;
+
+    use super::*;
+
+    #[test]
+    fn test_log_writer_lines() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join(LOG_FILE_NAME);
+        let logger = JsonFileLogger::open(&path, LogConfig::default()).unwrap();
+
+        let mut stdout = logger.writer("stdout");
+        let mut stderr = logger.writer("stderr");
+        stdout.write_all(b"hello\nwor").unwrap();
+        stderr.write_all(b"oops\n").unwrap();
+        stdout.write_all(b"ld\n").unwrap();
+        stdout.write_all(b"no newline").unwrap();
+        drop(stdout);
+
+        let entries = read_entries(&path).unwrap();
+        let logs: Vec<(&str, &str)> = entries
+            .iter()
+            .map(|e| (e.stream.as_str(), e.log.as_str()))
+            .collect();
+        assert_eq!(
+            logs,
+            vec![
+                ("stdout", "hello\n"),
+                ("stderr", "oops\n"),
+                ("stdout", "world\n"),
+                ("stdout", "no newline"),
+            ]
+        );
+        assert!(entries.iter().all(|e| e.timestamp().is_some()));
+    }
+
+    #[test]
+    fn test_log_writer_long_line() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join(LOG_FILE_NAME);
+        let logger = JsonFileLogger::open(&path, LogConfig::default()).unwrap();
+
+        let mut writer = logger.writer("stdout");
+        writer.write_all(&vec![b'x'; MAX_LINE_SIZE + 10]).unwrap();
+        drop(writer);
+
+        let entries = read_entries(&path).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].log.len(), MAX_LINE_SIZE);
+        assert_eq!(entries[1].log.len(), 10);
+    }
+
+    #[test]
+    fn test_log_rotation() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join(LOG_FILE_NAME);
+        let config = LogConfig {
+            max_size: Some(200),
+            max_file: 3,
+        };
+        let logger = JsonFileLogger::open(&path, config).unwrap();
+
+        let mut writer = logger.writer("stdout");
+        for i in 0..20 {
+            writeln!(writer, "line {}", i).unwrap();
+        }
+
+        let files = log_files(&path);
+        assert_eq!(
+            files,
+            vec![rotated_path(&path, 2), rotated_path(&path, 1), path.clone()]
+        );
+        assert!(!rotated_path(&path, 3).exists());
+        for file in &files {
+            assert!(fs::metadata(file).unwrap().len() <= 200);
+        }
+
+        // 保留的记录按时间顺序排列，最新的一行在最后
+        let entries = read_entries(&path).unwrap();
+        assert!(entries.len() < 20);
+        assert_eq!(entries.last().unwrap().log, "line 19\n");
+        let numbers: Vec<u32> = entries
+            .iter()
+            .map(|e| e.log.trim().trim_start_matches("line ").parse().unwrap())
+            .collect();
+        assert!(numbers.windows(2).all(|w| w[1] == w[0] + 1));
+    }
+
+    #[test]
+    fn test_log_rotation_single_file() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join(LOG_FILE_NAME);
+        let config = LogConfig {
+            max_size: Some(200),
+            max_file: 1,
+        };
+        let logger = JsonFileLogger::open(&path, config).unwrap();
+
+        let mut writer = logger.writer("stdout");
+        for i in 0..20 {
+            writeln!(writer, "line {}", i).unwrap();
+        }
+
+        assert_eq!(log_files(&path), vec![path.clone()]);
+        assert!(fs::metadata(&path).unwrap().len() <= 200);
+        assert_eq!(
+            read_entries(&path).unwrap().last().unwrap().log,
+            "line 19\n"
+        );
+    }
+}
@@ -24,6 +24,7 @@ pub mod capabilities;
 pub mod cgroups;
 pub mod console;
 pub mod container;
+pub mod container_log;
 pub mod mount;
 pub mod pipe;
 pub mod process;
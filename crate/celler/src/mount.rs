@@ -113,6 +113,16 @@ lazy_static! {
     };
 }
 
+/// 支持的挂载选项（`OPTIONS` 中的标志和 `PROPAGATION` 中的传播类型），按名称排序
+///
+/// 不在其中的选项会原样作为文件系统的 data 参数传给 mount(2)。
+pub fn supported_mount_options() -> Vec<&'static str> {
+    let mut options: Vec<&'static str> =
+        OPTIONS.keys().chain(PROPAGATION.keys()).copied().collect();
+    options.sort_unstable();
+    options
+}
+
 #[inline(always)]
 #[cfg(not(test))]
 pub fn mount<
@@ -1194,6 +1204,16 @@ mod tests {
 
     use super::*;
 
+    #[test]
+    fn test_supported_mount_options() {
+        let options = supported_mount_options();
+        assert_eq!(options.len(), OPTIONS.len() + PROPAGATION.len());
+        assert!(options.windows(2).all(|w| w[0] < w[1]));
+        for o in ["ro", "nosuid", "rbind", "rprivate", "shared"] {
+            assert!(options.contains(&o), "missing {}", o);
+        }
+    }
+
     #[test]
     #[serial(chdir)]
     fn test_init_rootfs() {
@@ -198,6 +198,53 @@ impl Process {
         Ok(p)
     }
 
+    /// 让非 TTY 进程直接继承调用者的标准输入输出
+    ///
+    /// `new` 默认为非 TTY 进程创建管道，由调用者负责转发。当调用者希望像
+    /// `runc create` 那样把自己的 stdio 直接交给容器时，关闭这些管道，
+    /// 改为使用当前进程 0/1/2 的副本。
+    pub fn inherit_stdio(&mut self) -> Result<()> {
+        self.set_stdio(
+            std::io::stdin().as_raw_fd(),
+            std::io::stdout().as_raw_fd(),
+            std::io::stderr().as_raw_fd(),
+        )
+    }
+
+    /// 使用指定的文件描述符作为非 TTY 进程的标准输入输出
+    ///
+    /// 关闭 `new` 创建的管道，改为使用传入 fd 的副本。必须复制：`start`
+    /// 会接管并关闭传入的文件描述符，调用者仍负责关闭自己的 fd。
+    /// TTY 进程的标准输入输出由 `start` 创建的伪终端提供，此时不做任何处理。
+    pub fn set_stdio(&mut self, stdin: RawFd, stdout: RawFd, stderr: RawFd) -> Result<()> {
+        if self.tty {
+            return Ok(());
+        }
+
+        // standard-oci-runtime 下 new 已直接使用 0/1/2，没有额外的管道
+        if self.parent_stdout.is_some() {
+            close_process_stream!(self, parent_stdin, ParentStdin);
+            for fd in [
+                self.stdin.take(),
+                self.stdout.take(),
+                self.stderr.take(),
+                self.parent_stdout.take(),
+                self.parent_stderr.take(),
+            ]
+            .into_iter()
+            .flatten()
+            {
+                let _ = unistd::close(fd);
+            }
+        }
+
+        self.stdin = Some(fcntl(stdin, FcntlArg::F_DUPFD_CLOEXEC(0))?);
+        self.stdout = Some(fcntl(stdout, FcntlArg::F_DUPFD_CLOEXEC(0))?);
+        self.stderr = Some(fcntl(stderr, FcntlArg::F_DUPFD_CLOEXEC(0))?);
+
+        Ok(())
+    }
+
     pub fn notify_term_close(&mut self) {
         let notify = self.term_exit_notifier.clone();
         notify.notify_waiters();
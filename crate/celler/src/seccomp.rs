@@ -5,6 +5,65 @@ use libseccomp::*;
 use oci::{LinuxSeccomp, LinuxSeccompArg};
 use oci_spec::runtime as oci;
 
+/// `init_seccomp` 支持的动作
+///
+/// 不包含 `SCMP_ACT_NOTIFY`：没有接收通知的 listener，命中规则的系统调用会一直阻塞。
+pub const SUPPORTED_ACTIONS: &[&str] = &[
+    "SCMP_ACT_ALLOW",
+    "SCMP_ACT_ERRNO",
+    "SCMP_ACT_KILL",
+    "SCMP_ACT_KILL_PROCESS",
+    "SCMP_ACT_KILL_THREAD",
+    "SCMP_ACT_LOG",
+    "SCMP_ACT_TRACE",
+    "SCMP_ACT_TRAP",
+];
+
+/// 系统调用参数支持的比较运算符
+pub const SUPPORTED_OPERATORS: &[&str] = &[
+    "SCMP_CMP_EQ",
+    "SCMP_CMP_GE",
+    "SCMP_CMP_GT",
+    "SCMP_CMP_LE",
+    "SCMP_CMP_LT",
+    "SCMP_CMP_MASKED_EQ",
+    "SCMP_CMP_NE",
+];
+
+/// 支持的架构
+pub const SUPPORTED_ARCHS: &[&str] = &[
+    "SCMP_ARCH_AARCH64",
+    "SCMP_ARCH_ARM",
+    "SCMP_ARCH_MIPS",
+    "SCMP_ARCH_MIPS64",
+    "SCMP_ARCH_MIPS64N32",
+    "SCMP_ARCH_MIPSEL",
+    "SCMP_ARCH_MIPSEL64",
+    "SCMP_ARCH_MIPSEL64N32",
+    "SCMP_ARCH_PPC",
+    "SCMP_ARCH_PPC64",
+    "SCMP_ARCH_PPC64LE",
+    "SCMP_ARCH_RISCV64",
+    "SCMP_ARCH_S390",
+    "SCMP_ARCH_S390X",
+    "SCMP_ARCH_X32",
+    "SCMP_ARCH_X86",
+    "SCMP_ARCH_X86_64",
+];
+
+/// 支持的过滤器标志，与 `get_filter_attr_from_flag` 一致
+pub const SUPPORTED_FLAGS: &[&str] = &[
+    "SECCOMP_FILTER_FLAG_LOG",
+    "SECCOMP_FILTER_FLAG_SPEC_ALLOW",
+    "SECCOMP_FILTER_FLAG_TSYNC",
+];
+
+/// 运行时链接的 libseccomp 版本（如 `2.5.4`）
+pub fn libseccomp_version() -> Result<String> {
+    let v = ScmpVersion::current()?;
+    Ok(format!("{}.{}.{}", v.major, v.minor, v.micro))
+}
+
 fn get_filter_attr_from_flag(flag: &str) -> Result<ScmpFilterAttr> {
     match flag {
         "SECCOMP_FILTER_FLAG_TSYNC" => Ok(ScmpFilterAttr::CtlTsync),
@@ -219,6 +278,27 @@ mod tests {
         assert_eq!(get_filter_attr_from_flag("ERROR").is_err(), true);
     }
 
+    #[test]
+    fn test_supported_tables() {
+        for action in SUPPORTED_ACTIONS {
+            assert!(
+                ScmpAction::from_str(action, Some(EPERM)).is_ok(),
+                "{}",
+                action
+            );
+        }
+        for op in SUPPORTED_OPERATORS {
+            assert!(ScmpCompareOp::from_str(op).is_ok(), "{}", op);
+        }
+        for arch in SUPPORTED_ARCHS {
+            assert!(ScmpArch::from_str(arch).is_ok(), "{}", arch);
+            assert!(arch.parse::<oci::Arch>().is_ok(), "{}", arch);
+        }
+        for flag in SUPPORTED_FLAGS {
+            assert!(get_filter_attr_from_flag(flag).is_ok(), "{}", flag);
+        }
+    }
+
     #[test]
     fn test_get_unknown_syscalls() {
         let scmp: oci::LinuxSeccomp = serde_json::from_str(TEST_DATA).unwrap();
@@ -0,0 +1,780 @@
+//! 连接到后台容器（`ctr attach`）
+//!
+//! `ctr run -d` 启动和 `ctr create` 创建的容器由 shim 进程持有其伪终端 master（`-t`）
+//! 或标准输入输出管道，并在容器状态目录中监听 `attach.sock`。`ctr attach` 连接该 socket：
+//! 容器输出原样转发给所有已连接的客户端，客户端的输入和窗口大小以帧的形式发给 shim。
+//! 输入分离键序列（默认 Ctrl-P Ctrl-Q）后客户端断开连接，容器继续运行。
+//! 无论是否有客户端连接，容器输出都会写入 json-file 日志（见 `ctr logs`）。
+//! 前台运行的容器由 CLI 通过 `AttachIo::forward_local` 在当前终端与容器之间转发，
+//! 输出同样写入日志。
+//!
+//! # 帧格式（客户端 → shim）
+//! ```text
+//! +---------+--------------+-----------+
+//! | 类型 u8 | 长度 u32 大端 |   数据    |
+//! +---------+--------------+-----------+
+//! 类型 0：标准输入数据
+//! 类型 1：窗口大小，数据为 rows u16 + cols u16（大端）
+//! ```
+//! shim → 客户端方向没有分帧，直接是容器的输出。
+
+use std::{
+    fs::{self, File, Permissions},
+    io::{self, Read, Write},
+    net::Shutdown,
+    os::unix::{
+        fs::PermissionsExt,
+        io::{AsRawFd, FromRawFd, RawFd},
+        net::{UnixListener, UnixStream},
+    },
+    path::{Path, PathBuf},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+    },
+    thread::JoinHandle,
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, Result, anyhow};
+use celler::{
+    container_log::{JsonFileLogger, LogWriter},
+    process::Process,
+};
+use nix::{
+    fcntl::{self, FcntlArg},
+    unistd,
+};
+use slog::Logger;
+use tokio::signal::unix::{SignalKind, signal};
+
+use crate::{
+    config::state_base,
+    container_cmd::{load_running_container, wait_exit_code},
+    shim, terminal,
+};
+
+/// 状态目录中 attach socket 的文件名
+const ATTACH_SOCKET: &str = "attach.sock";
+
+/// 标准输入数据帧
+const FRAME_STDIN: u8 = 0;
+
+/// 窗口大小帧
+const FRAME_RESIZE: u8 = 1;
+
+/// 单个帧的最大长度，防止异常客户端让 shim 分配过多内存
+const MAX_FRAME_SIZE: usize = 64 * 1024;
+
+/// 向客户端写输出的超时时间，超时的客户端被断开，避免阻塞容器输出
+const CLIENT_WRITE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// 客户端发给 shim 的帧
+#[derive(Debug, Clone, PartialEq)]
+enum Frame {
+    /// 写入容器标准输入的数据
+    Stdin(Vec<u8>),
+    /// 客户端终端的窗口大小
+    Resize { rows: u16, cols: u16 },
+}
+
+/// 容器的 attach socket 路径
+pub fn socket_path(id: &str) -> PathBuf {
+    Path::new(state_base()).join(id).join(ATTACH_SOCKET)
+}
+
+fn write_frame(w: &mut impl Write, frame: &Frame) -> io::Result<()> {
+    let mut buf = Vec::new();
+    match frame {
+        Frame::Stdin(data) => {
+            buf.push(FRAME_STDIN);
+            buf.extend_from_slice(&(data.len() as u32).to_be_bytes());
+            buf.extend_from_slice(data);
+        }
+        Frame::Resize { rows, cols } => {
+            buf.push(FRAME_RESIZE);
+            buf.extend_from_slice(&4u32.to_be_bytes());
+            buf.extend_from_slice(&rows.to_be_bytes());
+            buf.extend_from_slice(&cols.to_be_bytes());
+        }
+    }
+    w.write_all(&buf)
+}
+
+/// 读取一个帧
+///
+/// # 返回
+/// 对端关闭连接时返回 `None`
+fn read_frame(r: &mut impl Read) -> io::Result<Option<Frame>> {
+    let mut header = [0u8; 5];
+    match r.read_exact(&mut header) {
+        Ok(()) => {}
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e),
+    }
+
+    let len = u32::from_be_bytes([header[1], header[2], header[3], header[4]]) as usize;
+    if len > MAX_FRAME_SIZE {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("帧长度 {} 超过上限 {}", len, MAX_FRAME_SIZE),
+        ));
+    }
+
+    let mut payload = vec![0u8; len];
+    r.read_exact(&mut payload)?;
+
+    match header[0] {
+        FRAME_STDIN => Ok(Some(Frame::Stdin(payload))),
+        FRAME_RESIZE if len == 4 => Ok(Some(Frame::Resize {
+            rows: u16::from_be_bytes([payload[0], payload[1]]),
+            cols: u16::from_be_bytes([payload[2], payload[3]]),
+        })),
+        kind => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("无效的帧: 类型 {}，长度 {}", kind, len),
+        )),
+    }
+}
+
+/// 解析分离键序列
+///
+/// 格式与 docker 的 `--detach-keys` 一致：逗号分隔的按键，每个按键为单个字符或
+/// `ctrl-<值>`，`<值>` 可以是字母或 `@`、`[`、`\`、`]`、`^`、`_` 之一。
+/// 空字符串表示禁用分离键。
+///
+/// # 返回
+/// 按键对应的字节序列，如 `ctrl-p,ctrl-q` 为 `[0x10, 0x11]`
+pub fn parse_detach_keys(s: &str) -> Result<Vec<u8>> {
+    if s.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    s.split(',')
+        .map(|key| {
+            let key = key.trim();
+            let invalid = || anyhow!("无效的分离键 '{}'", key);
+
+            if let Some(value) = key.to_ascii_lowercase().strip_prefix("ctrl-") {
+                let &[c] = value.as_bytes() else {
+                    return Err(invalid());
+                };
+                return match c {
+                    b'a'..=b'z' => Ok(c - b'a' + 1),
+                    b'@' => Ok(0),
+                    b'[' => Ok(27),
+                    b'\\' => Ok(28),
+                    b']' => Ok(29),
+                    b'^' => Ok(30),
+                    b'_' => Ok(31),
+                    _ => Err(invalid()),
+                };
+            }
+
+            match key.as_bytes() {
+                &[c] if c.is_ascii() => Ok(c),
+                _ => Err(invalid()),
+            }
+        })
+        .collect()
+}
+
+/// 在标准输入中识别分离键序列
+///
+/// 与分离键前缀匹配的字节先暂存，序列中断时再原样转发给容器，
+/// 因此单独输入 Ctrl-P 仍能被容器内的程序收到。
+/// 序列中断时按 KMP 算法回退到已暂存字节中最长的可匹配前缀，而不是从头开始，
+/// 分离键为 `ctrl-a,ctrl-a,d` 时输入 `ctrl-a ctrl-a ctrl-a d` 也能识别。
+struct DetachMatcher {
+    keys: Vec<u8>,
+    /// `fallback[i]` 为 `keys[..=i]` 中既是真前缀又是后缀的最长长度
+    fallback: Vec<usize>,
+    matched: usize,
+}
+
+impl DetachMatcher {
+    fn new(keys: Vec<u8>) -> Self {
+        let mut fallback = vec![0; keys.len()];
+        let mut k = 0;
+        for i in 1..keys.len() {
+            while k > 0 && keys[i] != keys[k] {
+                k = fallback[k - 1];
+            }
+            if keys[i] == keys[k] {
+                k += 1;
+            }
+            fallback[i] = k;
+        }
+
+        Self {
+            keys,
+            fallback,
+            matched: 0,
+        }
+    }
+
+    /// 处理一段输入
+    ///
+    /// # 返回
+    /// 需要转发给容器的数据，以及是否输入了完整的分离键序列
+    fn feed(&mut self, input: &[u8]) -> (Vec<u8>, bool) {
+        let mut out = Vec::with_capacity(input.len());
+        if self.keys.is_empty() {
+            out.extend_from_slice(input);
+            return (out, false);
+        }
+
+        for &b in input {
+            // 暂存的字节即 keys[..matched]，回退后只保留其中与新前缀匹配的部分
+            while self.matched > 0 && b != self.keys[self.matched] {
+                let next = self.fallback[self.matched - 1];
+                out.extend_from_slice(&self.keys[..self.matched - next]);
+                self.matched = next;
+            }
+            if b == self.keys[self.matched] {
+                self.matched += 1;
+                if self.matched == self.keys.len() {
+                    return (out, true);
+                }
+            } else {
+                out.push(b);
+            }
+        }
+
+        (out, false)
+    }
+}
+
+/// shim 转发给 attach 客户端的容器标准输入输出
+///
+/// 所有 fd 都是 shim 持有的副本：`LinuxContainer` 在 shim 中创建容器后即被释放，
+/// 其中的 `Process` 会关闭自己持有的管道。
+pub struct AttachIo {
+    /// 容器的标准输入（伪终端 master 或 stdin 管道写端），未使用 `-i` 时为 `None`
+    input: Option<File>,
+    /// 容器的输出（伪终端 master，或 stdout、stderr 管道读端）及其在日志中的流名称
+    outputs: Vec<(File, &'static str)>,
+    /// 伪终端 master，用于同步窗口大小
+    master: Option<File>,
+}
+
+impl AttachIo {
+    /// 从已启动的容器进程复制标准输入输出 fd
+    ///
+    /// # 参数
+    /// - `process`: 已启动的容器 init 进程
+    /// - `stdin`: 是否接受客户端的输入（对应 `-i`）。非 TTY 进程不接受输入时，
+    ///   stdin 管道随 `Process` 一起关闭，容器进程读到 EOF
+    pub fn from_process(process: &Process, stdin: bool) -> Result<Self> {
+        let dup = |fd: RawFd| -> Result<File> {
+            let fd = fcntl::fcntl(fd, FcntlArg::F_DUPFD_CLOEXEC(0))
+                .context("复制容器标准输入输出 fd 失败")?;
+            Ok(unsafe { File::from_raw_fd(fd) })
+        };
+
+        if let Some(master) = process.term_master {
+            return Ok(Self {
+                input: if stdin { Some(dup(master)?) } else { None },
+                outputs: vec![(dup(master)?, "stdout")],
+                master: Some(dup(master)?),
+            });
+        }
+
+        let input = match process.parent_stdin {
+            Some(fd) if stdin => Some(dup(fd)?),
+            _ => None,
+        };
+        let outputs = [
+            (process.parent_stdout, "stdout"),
+            (process.parent_stderr, "stderr"),
+        ]
+        .into_iter()
+        .filter_map(|(fd, stream)| fd.map(|fd| dup(fd).map(|file| (file, stream))))
+        .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self {
+            input,
+            outputs,
+            master: None,
+        })
+    }
+
+    /// 前台运行时在容器与当前进程的标准输入输出之间转发数据
+    ///
+    /// 启动后台线程：每路输出写入当前进程的 stdout 或 stderr，同时写入容器日志；
+    /// 接受输入时另有 stdin → 容器，当前进程的标准输入结束后关闭输入端。
+    ///
+    /// # 参数
+    /// - `log`: 容器的 json-file 日志
+    ///
+    /// # 返回
+    /// 输出线程的句柄，调用方在容器进程退出后 join，确保输出全部写完
+    pub fn forward_local(
+        self,
+        log: Arc<JsonFileLogger>,
+        logger: &Logger,
+    ) -> Result<Vec<JoinHandle<()>>> {
+        if let Some(mut input) = self.input {
+            std::thread::spawn(move || {
+                let _ = io::copy(&mut io::stdin(), &mut input);
+            });
+        }
+
+        self.outputs
+            .into_iter()
+            .map(|(mut output, stream)| {
+                // 直接写 fd 1/2，绕过 std::io::stdout 的行缓冲，否则不带换行的提示符不会立即显示
+                let local = match stream {
+                    "stderr" => io::stderr().as_raw_fd(),
+                    _ => io::stdout().as_raw_fd(),
+                };
+                let local = unistd::dup(local).context("复制标准输出失败")?;
+                let mut local = unsafe { File::from_raw_fd(local) };
+                let log = log.writer(stream);
+                let logger = logger.clone();
+                Ok(std::thread::spawn(move || {
+                    tee_output(&mut output, log, &logger, |data| {
+                        let _ = local.write_all(data);
+                    });
+                }))
+            })
+            .collect()
+    }
+}
+
+/// 读取容器的一路输出直到结束，写入日志后交给 `sink`
+///
+/// 日志写入失败时只记录一次警告，输出继续交给 `sink`。
+fn tee_output(output: &mut File, log: LogWriter, logger: &Logger, mut sink: impl FnMut(&[u8])) {
+    let mut log = Some(log);
+    let mut buf = [0u8; 8192];
+    loop {
+        match output.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => {
+                if let Some(writer) = log.as_mut()
+                    && let Err(e) = writer.write_all(&buf[..n])
+                {
+                    slog::warn!(logger, "写入容器日志失败"; "error" => format!("{:?}", e));
+                    log = None;
+                }
+                sink(&buf[..n]);
+            }
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            // 伪终端 slave 端全部关闭后 master 读到 EIO
+            Err(_) => break,
+        }
+    }
+
+    // 写出不以换行结尾的最后一段输出
+    drop(log);
+}
+
+/// shim 中的 attach 服务
+///
+/// 容器输出始终被读取并写入日志，没有客户端连接时也不会因缓冲区写满而阻塞容器进程。
+pub struct AttachServer {
+    outputs: Vec<JoinHandle<()>>,
+}
+
+impl AttachServer {
+    /// 在 `path` 上监听 attach 连接，并开始转发容器的输出
+    ///
+    /// # 参数
+    /// - `log`: 容器的 json-file 日志，所有输出同时写入其中
+    pub fn serve(
+        path: &Path,
+        io: AttachIo,
+        log: Arc<JsonFileLogger>,
+        logger: &Logger,
+    ) -> Result<Self> {
+        let _ = fs::remove_file(path);
+        let listener = UnixListener::bind(path)
+            .with_context(|| format!("无法监听 attach socket: {}", path.display()))?;
+        fs::set_permissions(path, Permissions::from_mode(0o600))
+            .with_context(|| format!("无法设置 attach socket 权限: {}", path.display()))?;
+
+        let clients: Arc<Mutex<Vec<UnixStream>>> = Arc::default();
+        let remaining = Arc::new(AtomicUsize::new(io.outputs.len()));
+
+        let outputs = io
+            .outputs
+            .into_iter()
+            .map(|(mut output, stream)| {
+                let clients = clients.clone();
+                let remaining = remaining.clone();
+                let log = log.writer(stream);
+                let logger = logger.clone();
+                std::thread::spawn(move || {
+                    tee_output(&mut output, log, &logger, |data| {
+                        clients
+                            .lock()
+                            .unwrap()
+                            .retain_mut(|c| c.write_all(data).is_ok());
+                    });
+
+                    // 所有输出都已结束，断开客户端让其读到 EOF
+                    if remaining.fetch_sub(1, Ordering::SeqCst) == 1 {
+                        for client in clients.lock().unwrap().drain(..) {
+                            let _ = client.shutdown(Shutdown::Both);
+                        }
+                    }
+                })
+            })
+            .collect();
+
+        let input = io.input.map(Arc::new);
+        let master = io.master.map(Arc::new);
+        let logger = logger.clone();
+        std::thread::spawn(move || {
+            for conn in listener.incoming() {
+                let Ok(conn) = conn else {
+                    continue;
+                };
+                let Ok(writer) = conn.try_clone() else {
+                    continue;
+                };
+                let _ = writer.set_write_timeout(Some(CLIENT_WRITE_TIMEOUT));
+                clients.lock().unwrap().push(writer);
+
+                slog::debug!(logger, "attach 客户端已连接");
+
+                let input = input.clone();
+                let master = master.clone();
+                let logger = logger.clone();
+                std::thread::spawn(move || {
+                    handle_client(conn, input.as_deref(), master.as_deref(), &logger)
+                });
+            }
+        });
+
+        Ok(Self { outputs })
+    }
+
+    /// 容器进程退出后调用：等待剩余输出转发给客户端，最多等待 `timeout`
+    pub fn finish(self, timeout: Duration) {
+        let deadline = Instant::now() + timeout;
+        while self.outputs.iter().any(|h| !h.is_finished()) && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    }
+}
+
+/// 处理单个客户端发来的帧，直到其断开连接
+fn handle_client(
+    mut conn: UnixStream,
+    mut input: Option<&File>,
+    master: Option<&File>,
+    logger: &Logger,
+) {
+    loop {
+        match read_frame(&mut conn) {
+            Ok(Some(Frame::Stdin(data))) => {
+                if let Some(writer) = input.as_mut()
+                    && let Err(e) = writer.write_all(&data)
+                {
+                    slog::debug!(logger, "写入容器标准输入失败"; "error" => format!("{:?}", e));
+                    input = None;
+                }
+            }
+            Ok(Some(Frame::Resize { rows, cols })) => {
+                if let Some(master) = master {
+                    let ws = libc::winsize {
+                        ws_row: rows,
+                        ws_col: cols,
+                        ws_xpixel: 0,
+                        ws_ypixel: 0,
+                    };
+                    if let Err(e) = terminal::set_window_size(master.as_raw_fd(), &ws) {
+                        slog::debug!(logger, "同步终端窗口大小失败"; "error" => format!("{:?}", e));
+                    }
+                }
+            }
+            Ok(None) => break,
+            Err(e) => {
+                slog::debug!(logger, "读取 attach 客户端数据失败"; "error" => format!("{:?}", e));
+                break;
+            }
+        }
+    }
+
+    slog::debug!(logger, "attach 客户端已断开");
+}
+
+/// 连接到后台运行的容器
+///
+/// 容器使用伪终端且转发标准输入时，当前终端切换为 raw 模式并同步窗口大小。
+/// 输入分离键序列后断开连接，容器继续运行；容器退出时以其退出码结束。
+///
+/// # 参数
+/// - `id`: 容器 ID
+/// - `detach_keys`: 分离键序列，格式见 [`parse_detach_keys`]
+/// - `no_stdin`: 只接收输出，不转发标准输入
+pub async fn attach_container(
+    id: &str,
+    detach_keys: &str,
+    no_stdin: bool,
+    logger: &Logger,
+) -> Result<()> {
+    let keys = parse_detach_keys(detach_keys)?;
+
+    slog::info!(logger, "连接到容器"; "id" => id);
+
+    let container = load_running_container(id, logger)?;
+    let tty = container
+        .config
+        .spec
+        .as_ref()
+        .and_then(|spec| spec.process().as_ref())
+        .and_then(|process| process.terminal())
+        .unwrap_or(false);
+
+    let path = socket_path(id);
+    let stream = UnixStream::connect(&path).with_context(|| {
+        format!(
+            "无法连接到容器 '{}'（只有 ctr run -d 启动的容器支持 attach）",
+            id
+        )
+    })?;
+    let writer = Arc::new(Mutex::new(
+        stream.try_clone().context("复制 attach 连接失败")?,
+    ));
+
+    let raw = if tty && !no_stdin {
+        terminal::RawTerminal::enable(io::stdin().as_raw_fd())?
+    } else {
+        None
+    };
+
+    // 伪终端的窗口大小跟随当前终端
+    let resize = if tty {
+        if let Err(e) = send_window_size(&writer) {
+            slog::debug!(logger, "同步终端窗口大小失败"; "error" => format!("{:?}", e));
+        }
+        let mut winch = signal(SignalKind::window_change()).context("监听 SIGWINCH 失败")?;
+        let writer = writer.clone();
+        Some(tokio::spawn(async move {
+            while winch.recv().await.is_some() {
+                let _ = send_window_size(&writer);
+            }
+        }))
+    } else {
+        None
+    };
+
+    let detached = Arc::new(AtomicBool::new(false));
+    if !no_stdin {
+        let writer = writer.clone();
+        let detached = detached.clone();
+        std::thread::spawn(move || {
+            let mut matcher = DetachMatcher::new(keys);
+            let mut buf = [0u8; 4096];
+            loop {
+                let n = match io::stdin().read(&mut buf) {
+                    Ok(0) | Err(_) => break,
+                    Ok(n) => n,
+                };
+                let (data, detach) = matcher.feed(&buf[..n]);
+                let mut conn = writer.lock().unwrap();
+                if !data.is_empty() && write_frame(&mut *conn, &Frame::Stdin(data)).is_err() {
+                    break;
+                }
+                if detach {
+                    detached.store(true, Ordering::SeqCst);
+                    // 关闭连接，输出转发随之结束
+                    let _ = conn.shutdown(Shutdown::Both);
+                    break;
+                }
+            }
+        });
+    }
+
+    // 直接写 fd 1，绕过 std::io::stdout 的行缓冲
+    let stdout = unistd::dup(io::stdout().as_raw_fd()).context("复制标准输出失败")?;
+    let mut reader = stream;
+    tokio::task::spawn_blocking(move || {
+        let mut stdout = unsafe { File::from_raw_fd(stdout) };
+        let _ = io::copy(&mut reader, &mut stdout);
+    })
+    .await
+    .context("转发容器输出的任务异常结束")?;
+
+    if let Some(resize) = resize {
+        resize.abort();
+    }
+    drop(raw);
+
+    if detached.load(Ordering::SeqCst) {
+        slog::info!(logger, "已从容器分离"; "id" => id);
+        return Ok(());
+    }
+
+    // 输出结束说明容器已退出，等待 shim 记录退出码
+    match wait_exit_code(id, false).await? {
+        Some(0) => Ok(()),
+        Some(code) => Err(shim::ExitError(code).into()),
+        None => {
+            slog::warn!(logger, "无法获取容器的退出码"; "id" => id);
+            Ok(())
+        }
+    }
+}
+
+/// 将当前终端的窗口大小发送给 shim
+fn send_window_size(writer: &Mutex<UnixStream>) -> Result<()> {
+    let ws = terminal::window_size(io::stdin().as_raw_fd())?;
+    let frame = Frame::Resize {
+        rows: ws.ws_row,
+        cols: ws.ws_col,
+    };
+    write_frame(&mut *writer.lock().unwrap(), &frame).context("发送窗口大小失败")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frame_roundtrip() {
+        let frames = [
+            Frame::Stdin(b"echo hello\n".to_vec()),
+            Frame::Resize { rows: 24, cols: 80 },
+            Frame::Stdin(Vec::new()),
+        ];
+
+        let mut buf = Vec::new();
+        for frame in &frames {
+            write_frame(&mut buf, frame).unwrap();
+        }
+
+        let mut reader = buf.as_slice();
+        for frame in &frames {
+            assert_eq!(read_frame(&mut reader).unwrap().as_ref(), Some(frame));
+        }
+        assert_eq!(read_frame(&mut reader).unwrap(), None);
+    }
+
+    #[test]
+    fn test_read_frame_invalid() {
+        // 未知类型
+        let mut reader: &[u8] = &[9, 0, 0, 0, 0];
+        assert!(read_frame(&mut reader).is_err());
+
+        // 超长帧
+        let mut reader: &[u8] = &[FRAME_STDIN, 0xff, 0xff, 0xff, 0xff];
+        assert!(read_frame(&mut reader).is_err());
+
+        // 数据不完整
+        let mut reader: &[u8] = &[FRAME_STDIN, 0, 0, 0, 4, b'a'];
+        assert!(read_frame(&mut reader).is_err());
+    }
+
+    #[test]
+    fn test_parse_detach_keys() {
+        assert_eq!(
+            parse_detach_keys("ctrl-p,ctrl-q").unwrap(),
+            vec![0x10, 0x11]
+        );
+        assert_eq!(parse_detach_keys("ctrl-A, x").unwrap(), vec![0x01, b'x']);
+        assert_eq!(
+            parse_detach_keys("ctrl-@,ctrl-[,ctrl-\\,ctrl-],ctrl-^,ctrl-_").unwrap(),
+            vec![0, 27, 28, 29, 30, 31]
+        );
+        assert!(parse_detach_keys("").unwrap().is_empty());
+        assert!(parse_detach_keys("ctrl-1").is_err());
+        assert!(parse_detach_keys("ctrl-pq").is_err());
+        assert!(parse_detach_keys("ab").is_err());
+        assert!(parse_detach_keys("ctrl-p,").is_err());
+    }
+
+    #[test]
+    fn test_detach_matcher() {
+        let mut matcher = DetachMatcher::new(vec![0x10, 0x11]);
+        assert_eq!(matcher.feed(b"ls\n"), (b"ls\n".to_vec(), false));
+
+        // 分离键跨越多次读取
+        assert_eq!(matcher.feed(b"a\x10"), (b"a".to_vec(), false));
+        assert_eq!(matcher.feed(b"\x11rest"), (Vec::new(), true));
+
+        // 序列中断时原样转发已暂存的前缀
+        let mut matcher = DetachMatcher::new(vec![0x10, 0x11]);
+        assert_eq!(matcher.feed(b"\x10b"), (b"\x10b".to_vec(), false));
+        assert_eq!(matcher.feed(b"\x10\x10\x11"), (b"\x10".to_vec(), true));
+
+        // 序列中断时回退到最长的可匹配前缀
+        let keys = parse_detach_keys("ctrl-a,ctrl-a,d").unwrap();
+        let mut matcher = DetachMatcher::new(keys.clone());
+        assert_eq!(matcher.feed(b"\x01\x01\x01d"), (b"\x01".to_vec(), true));
+        let mut matcher = DetachMatcher::new(keys);
+        assert_eq!(matcher.feed(b"\x01\x01\x01"), (b"\x01".to_vec(), false));
+        assert_eq!(matcher.feed(b"x"), (b"\x01\x01x".to_vec(), false));
+
+        // 禁用分离键
+        let mut matcher = DetachMatcher::new(Vec::new());
+        assert_eq!(matcher.feed(b"\x10\x11"), (b"\x10\x11".to_vec(), false));
+    }
+
+    #[test]
+    fn test_attach_server() {
+        let logger = slog::Logger::root(slog::Discard, slog::o!());
+        let path = std::env::temp_dir().join(format!("runcell-attach-{}.sock", std::process::id()));
+        let log_path = path.with_extension("log");
+        let log = JsonFileLogger::open(&log_path, Default::default()).unwrap();
+
+        // 模拟非 TTY 容器的 stdin / stdout 管道
+        let (stdin_r, stdin_w) = unistd::pipe().unwrap();
+        let (stdout_r, stdout_w) = unistd::pipe().unwrap();
+        let io = AttachIo {
+            input: Some(unsafe { File::from_raw_fd(stdin_w) }),
+            outputs: vec![(unsafe { File::from_raw_fd(stdout_r) }, "stdout")],
+            master: None,
+        };
+        let server = AttachServer::serve(&path, io, log, &logger).unwrap();
+        assert_eq!(
+            fs::metadata(&path).unwrap().permissions().mode() & 0o777,
+            0o600
+        );
+
+        let mut client = UnixStream::connect(&path).unwrap();
+        write_frame(&mut client, &Frame::Stdin(b"ping".to_vec())).unwrap();
+        let mut container_stdin = unsafe { File::from_raw_fd(stdin_r) };
+        let mut buf = [0u8; 4];
+        container_stdin.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"ping");
+
+        // 等待服务端登记客户端后再产生输出
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let mut container_stdout = unsafe { File::from_raw_fd(stdout_w) };
+        let mut received = Vec::new();
+        client
+            .set_read_timeout(Some(Duration::from_millis(100)))
+            .unwrap();
+        while received.is_empty() && Instant::now() < deadline {
+            container_stdout.write_all(b"pong\n").unwrap();
+            let mut buf = [0u8; 64];
+            if let Ok(n) = client.read(&mut buf) {
+                received.extend_from_slice(&buf[..n]);
+            }
+        }
+        assert!(received.starts_with(b"pong"));
+
+        // 输出结束后客户端读到 EOF
+        drop(container_stdout);
+        server.finish(Duration::from_secs(5));
+        client.set_read_timeout(None).unwrap();
+        let mut rest = Vec::new();
+        client.read_to_end(&mut rest).unwrap();
+
+        // 输出同时写入了日志
+        let entries = celler::container_log::read_entries(&log_path).unwrap();
+        assert!(!entries.is_empty());
+        assert!(
+            entries
+                .iter()
+                .all(|e| e.stream == "stdout" && e.log == "pong\n")
+        );
+
+        let _ = fs::remove_file(&path);
+        let _ = fs::remove_file(&log_path);
+    }
+}
@@ -0,0 +1,372 @@
+//! 运行时目录配置
+//!
+//! 运行时根目录保存容器状态（`{root}/states`）和 bundle（`{root}/bundles`），
+//! 按以下优先级确定：
+//! 1. 全局参数 `--root`
+//! 2. 环境变量 `RUNCELL_ROOT`
+//! 3. 配置文件中的 `root`
+//! 4. 默认值：root 用户为 `/run/runcell`，其他用户为 `$XDG_RUNTIME_DIR/runcell`
+//!
+//! 非 root 用户未设置 `XDG_RUNTIME_DIR` 时没有安全的默认位置（`/tmp` 下的目录可被其他用户抢先创建），
+//! 必须通过前三种方式之一指定。运行时根目录和镜像目录必须属于当前用户、不是符号链接，
+//! 且组和其他用户不可写；由 runcell 创建的目录权限为 0700。
+//!
+//! 镜像和容器 rootfs 需要跨重启保留，单独存放在镜像目录中：配置文件中的 `image_root`，
+//! 默认 root 用户为 `/var/lib/runcell`，其他用户为 `$XDG_DATA_HOME/runcell`。
+//!
+//! 配置文件为 TOML 格式，root 用户读取 `/etc/runcell/config.toml`，
+//! 其他用户读取 `$XDG_CONFIG_HOME/runcell/config.toml`，文件不存在时使用默认值：
+//! ```toml
+//! root = "/run/runcell"
+//! image_root = "/var/lib/runcell"
+//! ```
+
+use std::{
+    fs,
+    os::unix::fs::{DirBuilderExt, MetadataExt, PermissionsExt},
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
+
+use anyhow::{Context, Result, anyhow};
+use serde::Deserialize;
+
+/// root 用户的默认运行时根目录
+const DEFAULT_ROOT: &str = "/run/runcell";
+
+/// root 用户的配置文件
+const SYSTEM_CONFIG_FILE: &str = "/etc/runcell/config.toml";
+
+/// 当前进程使用的目录，由 `init` 设置
+static DIRS: OnceLock<Dirs> = OnceLock::new();
+
+/// 配置文件的内容
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigFile {
+    /// 运行时根目录
+    root: Option<String>,
+    /// 镜像目录
+    image_root: Option<String>,
+}
+
+/// runcell 使用的目录
+#[derive(Debug, Clone, PartialEq)]
+struct Dirs {
+    root: String,
+    state_base: String,
+    bundle_base: String,
+    image_root: PathBuf,
+}
+
+impl Dirs {
+    fn new(root: &Path, image_root: &Path) -> Self {
+        let root = std::path::absolute(root).unwrap_or_else(|_| root.to_path_buf());
+        let image_root =
+            std::path::absolute(image_root).unwrap_or_else(|_| image_root.to_path_buf());
+        Self {
+            state_base: root.join("states").display().to_string(),
+            bundle_base: root.join("bundles").display().to_string(),
+            root: root.display().to_string(),
+            image_root,
+        }
+    }
+}
+
+/// 确定本次运行使用的目录，需在执行任何命令之前调用
+///
+/// # 参数
+/// - `root`: 全局参数 `--root` 的值
+pub fn init(root: Option<&str>) -> Result<()> {
+    if root.is_some_and(str::is_empty) {
+        return Err(anyhow::anyhow!("--root 不能为空"));
+    }
+
+    let uid = nix::unistd::geteuid().as_raw();
+    let file = match config_file_path(uid, &env_var) {
+        Some(path) => load_config_file(&path)?,
+        None => ConfigFile::default(),
+    };
+
+    let dirs = resolve(root, &file, uid, &env_var)?;
+    DIRS.set(dirs)
+        .map_err(|_| anyhow::anyhow!("运行时目录已设置"))
+}
+
+/// 创建运行时根目录和镜像目录
+///
+/// 新建的目录权限为 0700，避免其他用户查看容器信息；已存在的目录（如 containerd 创建的 0711 目录）
+/// 保留原有权限，只检查所有者和写权限，拒绝使用其他用户预先创建的目录或符号链接。
+pub fn create_dirs() -> Result<()> {
+    let dirs = dirs();
+    let uid = nix::unistd::geteuid().as_raw();
+    for dir in [Path::new(&dirs.root), dirs.image_root.as_path()] {
+        if fs::symlink_metadata(dir).is_err() {
+            fs::DirBuilder::new()
+                .recursive(true)
+                .mode(0o700)
+                .create(dir)
+                .with_context(|| format!("无法创建目录: {}", dir.display()))?;
+        }
+        check_private_dir(dir, uid)?;
+    }
+    Ok(())
+}
+
+/// 检查目录不是符号链接、属于 `uid`，且组和其他用户不可写
+fn check_private_dir(dir: &Path, uid: u32) -> Result<()> {
+    let meta = fs::symlink_metadata(dir)
+        .with_context(|| format!("无法读取目录信息: {}", dir.display()))?;
+    if !meta.file_type().is_dir() {
+        return Err(anyhow!("{} 不是目录（可能是符号链接）", dir.display()));
+    }
+    if meta.uid() != uid {
+        return Err(anyhow!(
+            "{} 属于 uid {}，而不是当前用户 {}",
+            dir.display(),
+            meta.uid(),
+            uid
+        ));
+    }
+    let mode = meta.permissions().mode() & 0o7777;
+    if mode & 0o022 != 0 {
+        return Err(anyhow!(
+            "{} 的权限为 {:o}，组和其他用户不能有写权限",
+            dir.display(),
+            mode
+        ));
+    }
+    Ok(())
+}
+
+/// 运行时根目录
+pub fn root() -> &'static str {
+    &dirs().root
+}
+
+/// 容器状态目录 `{root}/states`
+pub fn state_base() -> &'static str {
+    &dirs().state_base
+}
+
+/// bundle 目录 `{root}/bundles`
+pub fn bundle_base() -> &'static str {
+    &dirs().bundle_base
+}
+
+/// 镜像目录
+pub fn image_root() -> &'static Path {
+    &dirs().image_root
+}
+
+/// 本次运行使用的目录，`init` 之前调用时 panic
+fn dirs() -> &'static Dirs {
+    DIRS.get().expect("运行时目录未初始化")
+}
+
+fn env_var(key: &str) -> Option<String> {
+    std::env::var(key).ok().filter(|v| !v.is_empty())
+}
+
+/// 配置文件路径，非 root 用户未设置 `XDG_CONFIG_HOME` 和 `HOME` 时没有配置文件
+fn config_file_path(uid: u32, env: &dyn Fn(&str) -> Option<String>) -> Option<PathBuf> {
+    if uid == 0 {
+        return Some(PathBuf::from(SYSTEM_CONFIG_FILE));
+    }
+    xdg_dir(env, "XDG_CONFIG_HOME", ".config").map(|dir| dir.join("runcell").join("config.toml"))
+}
+
+/// 读取配置文件，文件不存在时返回默认配置
+fn load_config_file(path: &Path) -> Result<ConfigFile> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(ConfigFile::default()),
+        Err(e) => {
+            return Err(e).with_context(|| format!("无法读取配置文件: {}", path.display()));
+        }
+    };
+    toml::from_str(&content).with_context(|| format!("无法解析配置文件: {}", path.display()))
+}
+
+/// 按优先级确定目录
+fn resolve(
+    root: Option<&str>,
+    file: &ConfigFile,
+    uid: u32,
+    env: &dyn Fn(&str) -> Option<String>,
+) -> Result<Dirs> {
+    let root = match root
+        .map(PathBuf::from)
+        .or_else(|| env("RUNCELL_ROOT").map(PathBuf::from))
+        .or_else(|| file.root.as_ref().map(PathBuf::from))
+    {
+        Some(root) => root,
+        None => default_root(uid, env)?,
+    };
+
+    let image_root = match file.image_root.as_ref() {
+        Some(image_root) => PathBuf::from(image_root),
+        None => default_image_root(uid, env)?,
+    };
+
+    Ok(Dirs::new(&root, &image_root))
+}
+
+/// 默认的运行时根目录
+///
+/// 非 root 用户未设置 `XDG_RUNTIME_DIR` 时返回错误，不退回到可被其他用户抢先创建的临时目录。
+fn default_root(uid: u32, env: &dyn Fn(&str) -> Option<String>) -> Result<PathBuf> {
+    if uid == 0 {
+        return Ok(PathBuf::from(DEFAULT_ROOT));
+    }
+    env("XDG_RUNTIME_DIR")
+        .map(|dir| Path::new(&dir).join("runcell"))
+        .ok_or_else(|| {
+            anyhow!(
+                "未设置 XDG_RUNTIME_DIR，请通过 --root、RUNCELL_ROOT 或配置文件指定运行时根目录"
+            )
+        })
+}
+
+/// 默认的镜像目录
+fn default_image_root(uid: u32, env: &dyn Fn(&str) -> Option<String>) -> Result<PathBuf> {
+    if uid == 0 {
+        return Ok(PathBuf::from(storage::image::DEFAULT_IMAGE_ROOT));
+    }
+    xdg_dir(env, "XDG_DATA_HOME", ".local/share")
+        .map(|dir| dir.join("runcell"))
+        .ok_or_else(|| anyhow!("未设置 XDG_DATA_HOME 和 HOME，请在配置文件中指定 image_root"))
+}
+
+/// XDG 目录：环境变量未设置时为 `$HOME/{fallback}`，两者都未设置时返回 `None`
+fn xdg_dir(env: &dyn Fn(&str) -> Option<String>, key: &str, fallback: &str) -> Option<PathBuf> {
+    env(key)
+        .map(PathBuf::from)
+        .or_else(|| env("HOME").map(|home| Path::new(&home).join(fallback)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    fn env_of(vars: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        move |key| vars.get(key).cloned()
+    }
+
+    #[test]
+    fn test_resolve_defaults() {
+        let env = env_of(&[("XDG_RUNTIME_DIR", "/run/user/1000"), ("HOME", "/home/u")]);
+        let file = ConfigFile::default();
+
+        let dirs = resolve(None, &file, 0, &env).unwrap();
+        assert_eq!(dirs.root, "/run/runcell");
+        assert_eq!(dirs.state_base, "/run/runcell/states");
+        assert_eq!(dirs.bundle_base, "/run/runcell/bundles");
+        assert_eq!(dirs.image_root, PathBuf::from("/var/lib/runcell"));
+
+        let dirs = resolve(None, &file, 1000, &env).unwrap();
+        assert_eq!(dirs.root, "/run/user/1000/runcell");
+        assert_eq!(
+            dirs.image_root,
+            PathBuf::from("/home/u/.local/share/runcell")
+        );
+
+        // 非 root 用户未设置 XDG_RUNTIME_DIR 时必须显式指定运行时根目录
+        let env = env_of(&[("XDG_DATA_HOME", "/data")]);
+        assert!(resolve(None, &file, 1000, &env).is_err());
+        let dirs = resolve(Some("/srv/runcell"), &file, 1000, &env).unwrap();
+        assert_eq!(dirs.root, "/srv/runcell");
+        assert_eq!(dirs.image_root, PathBuf::from("/data/runcell"));
+
+        // 也无法确定镜像目录
+        let env = env_of(&[("XDG_RUNTIME_DIR", "/run/user/1000")]);
+        assert!(resolve(None, &file, 1000, &env).is_err());
+        assert!(config_file_path(1000, &env).is_none());
+    }
+
+    #[test]
+    fn test_resolve_precedence() {
+        let file = ConfigFile {
+            root: Some("/srv/runcell".to_string()),
+            image_root: Some("/srv/images".to_string()),
+        };
+
+        let dirs = resolve(None, &file, 0, &env_of(&[])).unwrap();
+        assert_eq!(dirs.root, "/srv/runcell");
+        assert_eq!(dirs.image_root, PathBuf::from("/srv/images"));
+
+        let env = env_of(&[("RUNCELL_ROOT", "/env/runcell")]);
+        assert_eq!(resolve(None, &file, 0, &env).unwrap().root, "/env/runcell");
+        assert_eq!(
+            resolve(Some("/flag/runcell"), &file, 0, &env).unwrap().root,
+            "/flag/runcell"
+        );
+    }
+
+    #[test]
+    fn test_check_private_dir() {
+        let uid = nix::unistd::geteuid().as_raw();
+        let dir = std::env::temp_dir().join(format!("runcell-private-{}", std::process::id()));
+        fs::DirBuilder::new().mode(0o700).create(&dir).unwrap();
+        check_private_dir(&dir, uid).unwrap();
+
+        // 其他用户的目录
+        assert!(check_private_dir(&dir, uid + 1).is_err());
+
+        // 其他用户可以进入或读取，但不能写入
+        for mode in [0o711, 0o755] {
+            fs::set_permissions(&dir, fs::Permissions::from_mode(mode)).unwrap();
+            check_private_dir(&dir, uid).unwrap();
+        }
+        for mode in [0o775, 0o707, 0o1777] {
+            fs::set_permissions(&dir, fs::Permissions::from_mode(mode)).unwrap();
+            assert!(check_private_dir(&dir, uid).is_err());
+        }
+
+        // 指向合法目录的符号链接
+        fs::set_permissions(&dir, fs::Permissions::from_mode(0o700)).unwrap();
+        let link = dir.with_extension("link");
+        std::os::unix::fs::symlink(&dir, &link).unwrap();
+        assert!(check_private_dir(&link, uid).is_err());
+
+        fs::remove_file(&link).unwrap();
+        fs::remove_dir(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_config_file() {
+        let env = env_of(&[("HOME", "/home/u")]);
+        assert_eq!(
+            config_file_path(0, &env),
+            Some(PathBuf::from("/etc/runcell/config.toml"))
+        );
+        assert_eq!(
+            config_file_path(1000, &env),
+            Some(PathBuf::from("/home/u/.config/runcell/config.toml"))
+        );
+
+        let dir = std::env::temp_dir().join(format!("runcell-config-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.toml");
+
+        // 文件不存在时使用默认配置
+        let file = load_config_file(&path).unwrap();
+        assert!(file.root.is_none() && file.image_root.is_none());
+
+        fs::write(&path, "root = \"/srv/runcell\"\n").unwrap();
+        let file = load_config_file(&path).unwrap();
+        assert_eq!(file.root.as_deref(), Some("/srv/runcell"));
+        assert!(file.image_root.is_none());
+
+        fs::write(&path, "rot = \"/srv/runcell\"\n").unwrap();
+        assert!(load_config_file(&path).is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -2,64 +2,87 @@
 
 use std::{
     fs,
+    os::unix::io::{AsRawFd, RawFd},
     path::Path,
     sync::{Arc, RwLock},
-    time::Duration,
+    thread::JoinHandle,
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
 use anyhow::{Context, Result};
 use celler::{
-    cgroups::DevicesCgroupInfo,
-    container::{LinuxContainer, is_process_running, load_container_state},
+    cgroups::{DevicesCgroupInfo, MOCK_CGROUP},
+    container::{
+        BaseContainer, Container, ContainerStateFile, LinuxContainer, is_init_process_alive,
+        is_process_running, load_container_state,
+    },
+    container_log::JsonFileLogger,
     process::Process,
     specconf::CreateOpts,
+    validator,
 };
-use nix::{
-    sys::{
-        signal::{self, Signal},
-        wait::{self, WaitStatus},
-    },
-    unistd::Pid,
-};
-use oci_spec::runtime::Spec;
+use nix::sys::signal::Signal;
+use oci_spec::{image::ImageConfiguration, runtime::Spec};
+use runtime_spec::ContainerState;
 use slog::Logger;
 
-use crate::ContainerCommands;
+use crate::{
+    ContainerCommands, ProcessOptions, ResourceOptions, SpecOptions, attach,
+    config::{self, bundle_base, state_base},
+    image_config, logs,
+    process_opts::apply_process_options,
+    ps,
+    resource_opts::{apply_resource_options, load_resources, merge_resources},
+    shim, signals, spec_opts, stats, terminal,
+};
 
-/// Bundle 基础目录
-const BUNDLE_BASE: &str = "/tmp/runcell/bundles";
+/// 发送 SIGKILL 后等待进程退出的最长时间
+pub(crate) const KILL_TIMEOUT: Duration = Duration::from_secs(5);
 
-/// Container 状态目录  
-const CONTAINER_STATE_BASE: &str = "/tmp/runcell/states";
+/// 轮询容器状态的间隔
+pub(crate) const POLL_INTERVAL: Duration = Duration::from_millis(100);
 
 /// 处理容器相关命令
 pub async fn handle_container_command(cmd: ContainerCommands, logger: &Logger) -> Result<()> {
     match cmd {
-        ContainerCommands::Create { id, rootfs, bundle } => {
-            create_container(&id, &rootfs, bundle.as_deref(), logger).await?;
+        ContainerCommands::Create {
+            id,
+            rootfs,
+            bundle,
+            log_opt,
+            command,
+        } => {
+            create_container(
+                &id,
+                rootfs.as_deref(),
+                bundle.as_deref(),
+                &command,
+                &log_opt,
+                logger,
+            )
+            .await?;
         }
         ContainerCommands::Run {
             id,
             image,
+            bundle,
             tty,
             interactive,
             detach,
+            log_opt,
+            opts,
             command,
         } => {
-            // 解析命令和参数
-            let (cmd_str, args) = if command.is_empty() {
-                ("/bin/sh".to_string(), Vec::new())
-            } else {
-                (command[0].clone(), command[1..].to_vec())
-            };
             run_container(
                 &id,
-                &image,
-                &cmd_str,
-                &args,
+                image.as_deref(),
+                bundle.as_deref(),
+                &command,
                 tty,
                 interactive,
                 detach,
+                &log_opt,
+                &opts,
                 logger,
             )
             .await?;
@@ -67,25 +90,90 @@ pub async fn handle_container_command(cmd: ContainerCommands, logger: &Logger) -
         ContainerCommands::Start { id } => {
             start_container(&id, logger).await?;
         }
+        ContainerCommands::Kill { id, signal, all } => {
+            kill_container(&id, &signal, all, logger)?;
+        }
+        ContainerCommands::Stop { id, timeout } => {
+            stop_container(&id, timeout, logger).await?;
+        }
+        ContainerCommands::Pause { id } => {
+            pause_container(&id, logger)?;
+        }
+        ContainerCommands::Resume { id } => {
+            resume_container(&id, logger)?;
+        }
+        ContainerCommands::Attach {
+            id,
+            detach_keys,
+            no_stdin,
+        } => {
+            attach::attach_container(&id, &detach_keys, no_stdin, logger).await?;
+        }
+        ContainerCommands::Logs {
+            id,
+            follow,
+            since,
+            tail,
+            timestamps,
+        } => {
+            logs::show_logs(&id, follow, since.as_deref(), tail, timestamps, logger).await?;
+        }
+        ContainerCommands::Wait { id, condition } => {
+            wait_container(&id, &condition, logger).await?;
+        }
         ContainerCommands::Delete { id } => {
             delete_container(&id, logger).await?;
         }
         ContainerCommands::List { format, all } => {
             list_containers(&format, all, logger).await?;
         }
+        ContainerCommands::Stats {
+            id,
+            all,
+            no_stream,
+            format,
+        } => {
+            stats::show_stats(&id, all, no_stream, &format, logger).await?;
+        }
+        ContainerCommands::Ps { id, format } => {
+            ps::list_processes(&id, &format, logger)?;
+        }
+        ContainerCommands::Inspect { id } => {
+            inspect_containers(&id, logger)?;
+        }
+        ContainerCommands::Update {
+            id,
+            resources,
+            opts,
+        } => {
+            update_container(&id, resources.as_deref(), &opts, logger)?;
+        }
         ContainerCommands::Exec {
             id,
             tty,
             interactive,
+            detach,
+            process,
+            opts,
             command,
         } => {
-            // 解析命令和参数
-            let (cmd_str, args) = if command.is_empty() {
-                ("/bin/sh".to_string(), Vec::new())
+            // 未指定命令且未通过 --process 提供时，默认执行 /bin/sh
+            let command = if command.is_empty() && process.is_none() {
+                vec!["/bin/sh".to_string()]
             } else {
-                (command[0].clone(), command[1..].to_vec())
+                command
             };
-            exec_in_container(&id, &cmd_str, &args, tty, interactive, logger).await?;
+            exec_in_container(
+                &id,
+                &command,
+                tty,
+                interactive,
+                detach,
+                process.as_deref(),
+                &opts,
+                logger,
+            )
+            .await?;
         }
     }
 
@@ -93,112 +181,147 @@ pub async fn handle_container_command(cmd: ContainerCommands, logger: &Logger) -
 }
 
 /// 创建容器
+///
+/// 生成 OCI spec 并交给 shim 进程创建 `LinuxContainer`，fork 出的 init 进程完成
+/// 环境设置后阻塞在 exec.fifo 上，容器处于 Created 状态，直到 `ctr start` 打开 FIFO。
+/// 容器的输出由 shim 写入日志并通过 attach socket 提供，`log_opts` 为 `--log-opt`
+/// 指定的轮转选项。
+///
+/// 指定 `bundle` 时使用其中的 config.json，`rootfs` 和 `command` 只在显式给出时覆盖；
+/// 否则以 `rootfs` 生成默认 spec。
 async fn create_container(
     id: &str,
-    rootfs: &str,
+    rootfs: Option<&str>,
     bundle: Option<&str>,
+    command: &[String],
+    log_opts: &[String],
     logger: &Logger,
 ) -> Result<()> {
-    slog::info!(logger, "创建容器"; "id" => id, "rootfs" => rootfs);
+    slog::info!(logger, "创建容器"; "id" => id, "rootfs" => rootfs, "bundle" => bundle);
 
-    // 确定 bundle 目录
-    let bundle_path = bundle
-        .map(|s| s.to_string())
-        .unwrap_or_else(|| format!("{}/{}", BUNDLE_BASE, id));
+    let log = logs::parse_log_opts(log_opts)?;
 
-    // 创建 bundle 目录
-    fs::create_dir_all(&bundle_path)
-        .with_context(|| format!("无法创建 bundle 目录: {}", bundle_path))?;
+    let args = (!command.is_empty()).then_some(command);
+    let spec = match bundle {
+        Some(bundle) => {
+            let mut spec = spec_opts::load_bundle_spec(bundle)?;
+            spec_opts::override_spec(&mut spec, rootfs, args, None)?;
+            spec
+        }
+        None => {
+            let rootfs = rootfs.ok_or_else(|| anyhow::anyhow!("必须指定 --rootfs 或 --bundle"))?;
+            let args = args.map(<[String]>::to_vec).unwrap_or_else(default_command);
+            let mut spec = spec_opts::default_spec(rootfs, &args, false);
+            spec_opts::share_host_network(&mut spec);
+            spec
+        }
+    };
+    validate_spec(id, &spec)?;
 
-    // 生成最小化 OCI spec
-    let spec = create_minimal_spec(rootfs, &["/bin/sh".to_string()], false)?;
+    let bundle_path = save_spec(id, &spec)?;
+    slog::info!(logger, "容器配置已生成"; "bundle" => &bundle_path);
 
-    // 保存 config.json
-    let config_path = format!("{}/config.json", bundle_path);
-    spec.save(&config_path)
-        .with_context(|| format!("无法保存 config.json 到 {}", config_path))?;
+    // 只执行 start：init 进程阻塞在 exec.fifo 上，由 shim 负责监督
+    let stdio = shim::ShimStdio::Attach { stdin: false, log };
+    let pid =
+        shim::spawn_shim(id, &bundle_path, false, stdio, logger).context("创建容器进程失败")?;
 
-    slog::info!(logger, "容器配置已生成"; "config" => &config_path);
+    slog::info!(logger, "容器已创建"; "id" => id, "pid" => pid);
 
     Ok(())
 }
 
 /// 运行容器（创建+启动）
+///
+/// 容器的输出写入日志（后台运行时由 shim 写入），`log_opts` 为 `--log-opt` 指定的轮转选项。
+/// 指定 `bundle` 时使用其中的 config.json，镜像、命令和 `-t` 只在显式给出时覆盖；
+/// 否则以镜像生成默认 spec，镜像带有配置时按其设置命令、环境变量、工作目录、用户和注解。
+/// `opts` 中的挂载、环境变量、资源限制等选项在此基础上生效，`--user` 按 rootfs 解析用户名。
+#[allow(clippy::too_many_arguments)]
 async fn run_container(
     id: &str,
-    image: &str,
-    command: &str,
-    args: &[String],
+    image: Option<&str>,
+    bundle: Option<&str>,
+    command: &[String],
     tty: bool,
     interactive: bool,
     detach: bool,
+    log_opts: &[String],
+    opts: &SpecOptions,
     logger: &Logger,
 ) -> Result<()> {
-    slog::info!(logger, "运行容器"; "id" => id, "image" => image, "command" => command,
-        "tty" => tty, "interactive" => interactive, "detach" => detach);
+    slog::info!(logger, "运行容器"; "id" => id, "image" => image, "bundle" => bundle,
+        "command" => format!("{:?}", command), "tty" => tty, "interactive" => interactive,
+        "detach" => detach);
+
+    let log = logs::parse_log_opts(log_opts)?;
+    // 没有 cgroup 时资源限制不会生效，在拉取镜像前报错
+    if !opts.resources.is_empty() {
+        ensure_cgroup("应用 --memory、--cpus、--pids-limit 等资源限制")?;
+    }
 
     // 1. 拉取镜像
-    slog::info!(logger, "正在拉取镜像...");
-    let rootfs = storage::image::pull_and_extract(image, id, logger).await?;
-    slog::info!(logger, "镜像拉取成功"; "rootfs" => &rootfs);
+    let (rootfs, oci_config) = match image {
+        Some(image) => {
+            slog::info!(logger, "正在拉取镜像...");
+            let pulled =
+                storage::image::pull_and_extract(image, config::image_root(), id, logger).await?;
+            slog::info!(logger, "镜像拉取成功"; "rootfs" => &pulled.rootfs);
+            (Some(pulled.rootfs), pulled.config)
+        }
+        None => (None, None),
+    };
 
-    // 2. 确定 bundle 目录
-    let bundle_path = format!("{}/{}", BUNDLE_BASE, id);
-    fs::create_dir_all(&bundle_path)?;
+    // 容器启动前失败时卸载已拉取的镜像，否则残留的 overlay 挂载和 image.json
+    // 会让相同 ID 的重试失败，并阻止 `image rm` 删除该镜像
+    let cleanup = |e: anyhow::Error| {
+        if image.is_some()
+            && let Err(err) = storage::image::cleanup_image(config::image_root(), id, logger)
+        {
+            slog::warn!(logger, "清理镜像失败"; "id" => id, "error" => format!("{:?}", err));
+        }
+        e
+    };
 
-    // 3. 生成 OCI spec（带TTY支持）
-    let mut cmd_args = vec![command.to_string()];
-    cmd_args.extend(args.iter().cloned());
+    // 2. 生成 OCI spec（bundle 配置或默认模板 + 镜像配置 + 命令行选项）
+    // 3. 保存 config.json
+    let (spec, bundle_path) = run_spec(rootfs, oci_config.as_ref(), bundle, command, tty, opts)
+        .and_then(|spec| {
+            validate_spec(id, &spec)?;
+            let bundle_path = save_spec(id, &spec)?;
+            Ok((spec, bundle_path))
+        })
+        .map_err(&cleanup)?;
 
-    let spec = create_minimal_spec(&rootfs, &cmd_args, tty)?;
+    slog::info!(logger, "OCI 配置已生成"; "bundle" => &bundle_path);
 
-    // 4. 保存 config.json
-    let config_path = format!("{}/config.json", bundle_path);
-    spec.save(&config_path)?;
+    // 4. 后台运行：由 shim 进程创建、启动并监督容器，之后可通过 ctr attach 连接
+    if detach {
+        let stdio = shim::ShimStdio::Attach {
+            stdin: interactive,
+            log,
+        };
+        let pid = shim::spawn_shim(id, &bundle_path, true, stdio, logger)
+            .context("启动容器失败")
+            .map_err(&cleanup)?;
+        slog::info!(logger, "容器正在后台运行"; "id" => id, "pid" => pid);
+        return Ok(());
+    }
 
-    slog::info!(logger, "OCI 配置已生成"; "config" => &config_path);
+    // 前台运行：当前进程作为监督进程，必须在创建容器前成为 subreaper
+    shim::set_child_subreaper().map_err(&cleanup)?;
 
     // 5. 创建容器实例
-    let create_opts = CreateOpts {
-        cgroup_name: id.to_string(),
-        use_systemd_cgroup: false,
-        no_pivot_root: false,
-        no_new_keyring: false,
-        spec: Some(spec.clone()),
-        rootless_euid: false,
-        rootless_cgroup: false,
-        container_name: id.to_string(),
-    };
-
-    let devcg_info = Some(Arc::new(RwLock::new(DevicesCgroupInfo::default())));
-
     slog::info!(logger, "正在创建容器实例...");
 
-    let mut container =
-        LinuxContainer::new(id, CONTAINER_STATE_BASE, devcg_info, create_opts, logger)?;
+    let mut container = new_container(id, &spec, logger).map_err(&cleanup)?;
 
     slog::info!(logger, "容器创建成功！"; "id" => id);
 
     // 6. 创建并启动进程
     slog::info!(logger, "正在创建容器进程...");
 
-    // 从 spec 中获取 process 配置
-    let oci_process = spec
-        .process()
-        .as_ref()
-        .ok_or_else(|| anyhow::anyhow!("OCI spec 中缺少 process 配置"))?
-        .clone();
-
-    // 创建 Process 实例
-    let process = Process::new(
-        logger,
-        &oci_process,
-        id,   // exec_id
-        true, // init process
-        0,    // pipe_size (0 = default)
-        None, // proc_io (None for simple case)
-    )
-    .context("创建 Process 失败")?;
+    let process = new_init_process(id, &spec, logger).map_err(&cleanup)?;
 
     slog::info!(logger, "正在启动容器...");
 
@@ -206,70 +329,409 @@ async fn run_container(
     container
         .run_container(process)
         .await
-        .context("启动容器失败")?;
+        .context("启动容器失败")
+        .map_err(&cleanup)?;
 
     slog::info!(logger, "容器启动成功！"; "id" => id);
 
     let pid = container.init_process_pid;
-
-    if detach {
-        slog::info!(logger, "容器正在后台运行"; "id" => id, "pid" => pid);
-    } else if interactive || tty {
-        // 交互模式：使用 nsenter 进入容器
+    shim::record_supervisor(id, std::process::id() as i32)?;
+
+    // 在 init 进程的 pty 或标准输入输出管道与当前终端之间转发数据，输出同时写入日志
+    let process = container
+        .processes
+        .get_mut(id)
+        .ok_or_else(|| anyhow::anyhow!("找不到容器 init 进程"))?;
+    let master = process.term_master;
+    let io = attach::AttachIo::from_process(process, interactive)?;
+    if master.is_some() {
         slog::info!(logger, "进入交互模式..."; "id" => id, "pid" => pid);
-
-        let mut nsenter_args = vec![
-            format!("--target={}", pid),
-            "--mount".to_string(),
-            "--uts".to_string(),
-            "--ipc".to_string(),
-            "--net".to_string(),
-            "--pid".to_string(),
-        ];
-
-        // 添加要执行的命令
-        nsenter_args.push("--".to_string());
-        nsenter_args.push(command.to_string());
-        nsenter_args.extend(args.iter().cloned());
-
-        let status = std::process::Command::new("nsenter")
-            .args(&nsenter_args)
-            .stdin(std::process::Stdio::inherit())
-            .stdout(std::process::Stdio::inherit())
-            .stderr(std::process::Stdio::inherit())
-            .status()
-            .context("执行 nsenter 失败")?;
-
-        if !status.success() {
-            slog::warn!(logger, "命令退出"; "code" => status.code());
-        }
     } else {
-        // 非交互模式：等待进程退出
-        slog::info!(logger, "等待容器进程退出..."; "id" => id, "pid" => pid);
+        // stdin 管道只保留 io 中的副本，当前终端的输入结束后容器进程读到 EOF
+        process.close_stdin().await;
+    }
+    let log = JsonFileLogger::open(&logs::log_path(id), log)?;
+    let foreground = Foreground::attach_logged(pid, master, io, log, interactive, logger)?;
 
-        match wait::waitpid(Pid::from_raw(pid), None) {
-            Ok(WaitStatus::Exited(_, code)) => {
-                slog::info!(logger, "容器进程退出"; "code" => code);
-            }
-            Ok(WaitStatus::Signaled(_, sig, _)) => {
-                slog::info!(logger, "容器进程被信号终止"; "signal" => format!("{:?}", sig));
-            }
-            Ok(status) => {
-                slog::info!(logger, "容器进程状态变化"; "status" => format!("{:?}", status));
-            }
-            Err(e) => {
-                slog::warn!(logger, "等待进程失败"; "error" => format!("{:?}", e));
-            }
+    // 等待容器进程退出
+    slog::info!(logger, "等待容器进程退出..."; "id" => id, "pid" => pid);
+
+    let status = shim::wait_init_process(id, pid, logger).await?;
+    foreground.finish();
+
+    match status.signal {
+        Some(sig) => {
+            slog::info!(logger, "容器进程被信号终止"; "signal" => sig);
+        }
+        None => {
+            slog::info!(logger, "容器进程退出"; "code" => status.code);
         }
     }
 
+    // 与 docker run 一致，CLI 以容器的退出码（被信号终止时为 128 + 信号值）退出
+    if status.code != 0 {
+        return Err(shim::ExitError(status.code).into());
+    }
+
     Ok(())
 }
 
 /// 启动已创建的容器
-async fn start_container(id: &str, logger: &Logger) -> Result<()> {
+///
+/// 从状态目录重建容器，打开 exec.fifo 的写端，解除 init 进程的阻塞，
+/// 并将 Running 状态写回 state.json。
+pub(crate) async fn start_container(id: &str, logger: &Logger) -> Result<()> {
     slog::info!(logger, "启动容器"; "id" => id);
-    slog::warn!(logger, "start 命令暂未完全实现");
+
+    let mut container = LinuxContainer::load(id, state_base(), None, logger)
+        .with_context(|| format!("容器 '{}' 不存在", id))?;
+
+    let status = container.status();
+    if status != ContainerState::Created {
+        return Err(anyhow::anyhow!(
+            "容器 '{}' 当前状态为 {:?}，只能启动处于 Created 状态的容器",
+            id,
+            status
+        ));
+    }
+
+    if !is_init_process_alive(
+        container.init_process_pid,
+        container.init_process_start_time,
+    ) {
+        return Err(anyhow::anyhow!(
+            "容器 '{}' 的 init 进程 (PID {}) 已不存在",
+            id,
+            container.init_process_pid
+        ));
+    }
+
+    container
+        .exec_container()
+        .await
+        .context("打开 exec.fifo 失败")?;
+
+    container.save_state().context("保存容器状态失败")?;
+
+    slog::info!(logger, "容器已启动"; "id" => id, "pid" => container.init_process_pid);
+
+    Ok(())
+}
+
+/// 向容器发送信号
+///
+/// `all` 为 true 时向容器 cgroup 内的所有进程发送，否则只发送给 init 进程。
+pub(crate) fn kill_container(id: &str, signal: &str, all: bool, logger: &Logger) -> Result<()> {
+    let sig = signals::parse_signal(signal)?;
+    slog::info!(logger, "向容器发送信号"; "id" => id, "signal" => sig.as_str(), "all" => all);
+
+    let container = LinuxContainer::load(id, state_base(), None, logger)
+        .with_context(|| format!("容器 '{}' 不存在", id))?;
+
+    container
+        .signal(sig, all)
+        .with_context(|| format!("向容器 '{}' 发送 {} 失败", id, sig))?;
+
+    Ok(())
+}
+
+/// 停止容器
+///
+/// 先向 init 进程发送 SIGTERM 并等待最多 `timeout` 秒，
+/// 容器仍未退出时向 cgroup 内所有进程发送 SIGKILL。
+async fn stop_container(id: &str, timeout: u64, logger: &Logger) -> Result<()> {
+    slog::info!(logger, "停止容器"; "id" => id, "timeout" => timeout);
+
+    let container = LinuxContainer::load(id, state_base(), None, logger)
+        .with_context(|| format!("容器 '{}' 不存在", id))?;
+    let pid = container.init_process_pid;
+    let start_time = container.init_process_start_time;
+
+    if !is_init_process_alive(pid, start_time) {
+        slog::info!(logger, "容器已停止"; "id" => id);
+        return Ok(());
+    }
+
+    container
+        .signal(Signal::SIGTERM, false)
+        .context("发送 SIGTERM 失败")?;
+
+    if wait_for_exit(pid, start_time, Duration::from_secs(timeout)).await {
+        slog::info!(logger, "容器已停止"; "id" => id);
+        return Ok(());
+    }
+
+    slog::warn!(logger, "容器未在超时时间内退出，发送 SIGKILL"; "id" => id, "timeout" => timeout);
+    if let Err(e) = container.signal(Signal::SIGKILL, true) {
+        // init 进程可能恰好在超时后退出
+        if is_init_process_alive(pid, start_time) {
+            return Err(e.context("发送 SIGKILL 失败"));
+        }
+    }
+
+    if !wait_for_exit(pid, start_time, KILL_TIMEOUT).await {
+        return Err(anyhow::anyhow!("容器 '{}' 在 SIGKILL 后仍未退出", id));
+    }
+
+    slog::info!(logger, "容器已停止"; "id" => id);
+
+    Ok(())
+}
+
+/// 等待 init 进程退出
+///
+/// 每隔 `POLL_INTERVAL` 检查一次，返回进程是否在 `timeout` 内退出。
+pub(crate) async fn wait_for_exit(pid: i32, start_time: u64, timeout: Duration) -> bool {
+    let deadline = tokio::time::Instant::now() + timeout;
+    while is_init_process_alive(pid, start_time) {
+        if tokio::time::Instant::now() >= deadline {
+            return false;
+        }
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+    true
+}
+
+/// 等待容器满足指定条件，并输出其退出码
+///
+/// - `not-running`：等待 init 进程退出且监督进程写入退出码
+/// - `removed`：等待容器被 `ctr rm` 删除，输出删除前记录到的退出码
+///
+/// 容器处于 Created 状态时会一直等待到它被启动并退出。
+async fn wait_container(id: &str, condition: &str, logger: &Logger) -> Result<()> {
+    slog::info!(logger, "等待容器"; "id" => id, "condition" => condition);
+
+    let wait_removed = condition == "removed";
+    match wait_exit_code(id, wait_removed).await? {
+        Some(code) => println!("{}", code),
+        None if wait_removed => {
+            slog::warn!(logger, "容器删除前未记录退出码"; "id" => id);
+        }
+        None => return Err(anyhow::anyhow!("无法获取容器 '{}' 的退出码", id)),
+    }
+
+    Ok(())
+}
+
+/// 等待容器退出（`removed` 为 true 时等待其被删除）
+///
+/// # 返回
+/// 监督进程记录的退出码；监督进程未记录就已退出，或容器在记录前被删除时返回 `None`
+pub(crate) async fn wait_exit_code(id: &str, removed: bool) -> Result<Option<i32>> {
+    let mut state =
+        load_container_state(state_base(), id).with_context(|| format!("容器 '{}' 不存在", id))?;
+    let state_dir = Path::new(state_base()).join(id);
+    let mut exit_code = None;
+
+    loop {
+        exit_code = state.exit_code.or(exit_code);
+
+        // init 进程退出后，退出码由监督进程随后写入；监督进程也已退出时不再等待
+        let supervisor_alive = state.supervisor_pid.is_some_and(is_process_running);
+        if !removed && !state.is_running() && (state.exit_code.is_some() || !supervisor_alive) {
+            break;
+        }
+
+        tokio::time::sleep(POLL_INTERVAL).await;
+
+        match load_container_state(state_base(), id) {
+            Ok(s) => state = s,
+            Err(_) if !state_dir.exists() => break,
+            Err(e) => return Err(e),
+        }
+    }
+
+    Ok(exit_code)
+}
+
+/// 暂停容器
+///
+/// 从状态目录重建容器，通过 freezer cgroup 冻结容器内所有进程，
+/// 确认 freezer 已进入 FROZEN 后将 Paused 状态写回 state.json。
+pub(crate) fn pause_container(id: &str, logger: &Logger) -> Result<()> {
+    ensure_cgroup("暂停容器")?;
+    slog::info!(logger, "暂停容器"; "id" => id);
+
+    let mut container = load_running_container(id, logger)?;
+    container
+        .pause()
+        .with_context(|| format!("暂停容器 '{}' 失败", id))?;
+    container.save_state().context("保存容器状态失败")?;
+
+    slog::info!(logger, "容器已暂停"; "id" => id);
+
+    Ok(())
+}
+
+/// 恢复已暂停的容器
+///
+/// 解冻容器内所有进程，确认 freezer 已进入 THAWED 后将 Running 状态写回 state.json。
+pub(crate) fn resume_container(id: &str, logger: &Logger) -> Result<()> {
+    ensure_cgroup("恢复容器")?;
+    slog::info!(logger, "恢复容器"; "id" => id);
+
+    let mut container = load_running_container(id, logger)?;
+    container
+        .resume()
+        .with_context(|| format!("恢复容器 '{}' 失败", id))?;
+    container.save_state().context("保存容器状态失败")?;
+
+    slog::info!(logger, "容器已恢复"; "id" => id);
+
+    Ok(())
+}
+
+/// 更新运行中容器的资源限制
+///
+/// 以容器当前 spec 中的 `linux.resources` 为基础，依次合并 `--resources` 文件和
+/// 命令行选项，通过 cgroup 管理器应用到容器，再写回状态目录中的 config.json，
+/// 之后重建容器（exec、stats 等）时使用的都是更新后的资源配置。
+fn update_container(
+    id: &str,
+    resources_file: Option<&str>,
+    opts: &ResourceOptions,
+    logger: &Logger,
+) -> Result<()> {
+    if resources_file.is_none() && opts.is_empty() {
+        return Err(anyhow::anyhow!("未指定需要更新的资源"));
+    }
+    ensure_cgroup("更新容器资源限制")?;
+
+    slog::info!(logger, "更新容器资源限制"; "id" => id);
+
+    let mut container = load_running_container(id, logger)?;
+
+    let mut resources = container
+        .config
+        .spec
+        .as_ref()
+        .and_then(|spec| spec.linux().as_ref())
+        .and_then(|linux| linux.resources().clone())
+        .unwrap_or_default();
+    if let Some(path) = resources_file {
+        merge_resources(&mut resources, load_resources(path)?);
+    }
+    apply_resource_options(&mut resources, opts)?;
+
+    slog::debug!(logger, "新的资源配置"; "resources" => format!("{:?}", resources));
+
+    container
+        .set_resources(resources)
+        .with_context(|| format!("更新容器 '{}' 的资源限制失败", id))?;
+    container.save_state().context("保存容器状态失败")?;
+
+    slog::info!(logger, "容器资源限制已更新"; "id" => id);
+
+    Ok(())
+}
+
+/// 显示容器的状态和保存的 OCI 配置
+///
+/// 输出 state.json 中的状态和 config.json 中保存的 OCI spec，
+/// `ctr update` 更新后的资源限制见 `spec.linux.resources`。
+/// 与 `ctr ls` 一样，init 进程已退出的容器状态显示为 Stopped。
+///
+/// # 参数
+/// - `ids`: 容器 ID 列表，按顺序输出为 JSON 数组
+fn inspect_containers(ids: &[String], logger: &Logger) -> Result<()> {
+    slog::info!(logger, "查看容器详情"; "ids" => format!("{:?}", ids));
+
+    let mut output = Vec::new();
+    for id in ids {
+        let state = load_container_state(state_base(), id)
+            .with_context(|| format!("容器 '{}' 不存在", id))?;
+        let config_path = Path::new(state_base())
+            .join(id)
+            .join(ContainerStateFile::CONFIG_FILENAME);
+        let content = fs::read_to_string(&config_path)
+            .with_context(|| format!("读取容器配置失败: {}", config_path.display()))?;
+        let config: CreateOpts = serde_json::from_str(&content)
+            .with_context(|| format!("解析容器配置失败: {}", config_path.display()))?;
+
+        let status = if state.is_running() {
+            state.status.clone()
+        } else {
+            "Stopped".to_string()
+        };
+        let mut value = serde_json::to_value(&state)?;
+        value["status"] = status.into();
+        value["spec"] = serde_json::to_value(&config.spec)?;
+        output.push(value);
+    }
+
+    println!("{}", serde_json::to_string_pretty(&output)?);
+    Ok(())
+}
+
+/// 确认容器有可用的 cgroup
+///
+/// mock-cgroup 构建不创建 cgroup，冻结、资源限制和统计都不会生效，
+/// 依赖 cgroup 的命令直接报错，而不是报告一个实际没有发生的结果。
+///
+/// # 参数
+/// - `action`: 错误信息中的操作名称
+pub(crate) fn ensure_cgroup(action: &str) -> Result<()> {
+    if MOCK_CGROUP {
+        return Err(anyhow::anyhow!(
+            "当前构建启用了 mock-cgroup，不创建容器 cgroup，无法{}",
+            action
+        ));
+    }
+    Ok(())
+}
+
+/// 从状态目录重建容器，并确认其 init 进程仍然存活
+pub(crate) fn load_running_container(id: &str, logger: &Logger) -> Result<LinuxContainer> {
+    let container = LinuxContainer::load(id, state_base(), None, logger)
+        .with_context(|| format!("容器 '{}' 不存在", id))?;
+
+    if !is_init_process_alive(
+        container.init_process_pid,
+        container.init_process_start_time,
+    ) {
+        return Err(anyhow::anyhow!("容器 '{}' 未在运行", id));
+    }
+
+    Ok(container)
+}
+
+/// 终止容器内的所有进程，执行 poststop 钩子并删除容器的 cgroup
+///
+/// 与 `runc delete --force` 一致：init 进程仍在运行时向 cgroup 内所有进程发送 SIGKILL，
+/// 暂停中的容器随后解冻，进程才能处理 SIGKILL。状态目录由调用方删除。
+pub(crate) async fn destroy_container(
+    container: &mut LinuxContainer,
+    logger: &Logger,
+) -> Result<()> {
+    let id = container.id.clone();
+    let pid = container.init_process_pid;
+    let start_time = container.init_process_start_time;
+
+    if is_init_process_alive(pid, start_time) {
+        slog::info!(logger, "正在终止容器进程"; "id" => &id, "pid" => pid);
+
+        let status = container.status();
+        if let Err(e) = container.signal(Signal::SIGKILL, true) {
+            // 无法列出容器进程时 init 进程仍会收到信号
+            slog::warn!(logger, "向容器发送 SIGKILL 失败"; "id" => &id,
+                "error" => format!("{:?}", e));
+        }
+        // 冻结中的进程在解冻后才会处理 SIGKILL
+        if status == ContainerState::Paused {
+            container.resume().context("恢复已暂停的容器失败")?;
+        }
+
+        if !wait_for_exit(pid, start_time, KILL_TIMEOUT).await {
+            return Err(anyhow::anyhow!("容器 '{}' 在 SIGKILL 后仍未退出", id));
+        }
+    }
+
+    if let Err(e) = container.run_poststop_hooks() {
+        slog::warn!(logger, "执行 poststop 钩子失败"; "error" => format!("{:?}", e));
+    }
+    if let Err(e) = container.cgroup_manager.destroy() {
+        slog::warn!(logger, "删除容器 cgroup 失败"; "error" => format!("{:?}", e));
+    }
 
     Ok(())
 }
@@ -277,93 +739,166 @@ async fn start_container(id: &str, logger: &Logger) -> Result<()> {
 /// 删除容器
 ///
 /// 执行以下步骤：
-/// 1. 读取 state.json 获取容器 PID
-/// 2. 如果进程仍在运行，发送 SIGKILL 信号
-/// 3. 清理 bundle 目录
-/// 4. 清理状态目录
-/// 5. 清理镜像
+/// 1. 加载容器，终止其所有进程，执行 poststop 钩子并删除 cgroup（见 `destroy_container`）
+/// 2. 清理 bundle 目录
+/// 3. 清理状态目录
+/// 4. 清理镜像
 async fn delete_container(id: &str, logger: &Logger) -> Result<()> {
     slog::info!(logger, "删除容器"; "id" => id);
 
-    // 1. 尝试读取状态并 kill 进程
-    match load_container_state(CONTAINER_STATE_BASE, id) {
-        Ok(state) => {
-            if state.init_process_pid > 0 {
-                if is_process_running(state.init_process_pid) {
-                    slog::info!(logger, "正在终止容器进程";
-                        "pid" => state.init_process_pid);
-
-                    // 发送 SIGKILL 信号
-                    match signal::kill(Pid::from_raw(state.init_process_pid), Signal::SIGKILL) {
-                        Ok(_) => {
-                            slog::info!(logger, "SIGKILL 信号已发送";
-                                "pid" => state.init_process_pid);
-                            // 等待进程退出
-                            tokio::time::sleep(Duration::from_millis(100)).await;
-                        }
-                        Err(e) => {
-                            slog::warn!(logger, "发送 SIGKILL 失败";
-                                "pid" => state.init_process_pid,
-                                "error" => format!("{:?}", e));
-                        }
-                    }
-                } else {
-                    slog::info!(logger, "容器进程已不存在";
-                        "pid" => state.init_process_pid);
-                }
-            }
-        }
+    // 1. 终止容器进程并清理 cgroup
+    match LinuxContainer::load(id, state_base(), None, logger) {
+        Ok(mut container) => destroy_container(&mut container, logger).await?,
         Err(e) => {
-            slog::debug!(logger, "无法读取容器状态（可能容器从未启动）";
+            slog::debug!(logger, "无法加载容器（可能容器从未启动）";
                 "error" => format!("{:?}", e));
         }
     }
 
     // 2. 清理 bundle
-    let bundle_path = format!("{}/{}", BUNDLE_BASE, id);
+    let bundle_path = format!("{}/{}", bundle_base(), id);
     if Path::new(&bundle_path).exists() {
         fs::remove_dir_all(&bundle_path)?;
         slog::info!(logger, "Bundle 已删除"; "path" => &bundle_path);
     }
 
     // 3. 清理容器状态
-    let state_path = format!("{}/{}", CONTAINER_STATE_BASE, id);
+    let state_path = format!("{}/{}", state_base(), id);
     if Path::new(&state_path).exists() {
         fs::remove_dir_all(&state_path)?;
         slog::info!(logger, "容器状态已删除"; "path" => &state_path);
     }
 
     // 4. 清理镜像
-    storage::image::cleanup_image(id, logger)?;
+    storage::image::cleanup_image(config::image_root(), id, logger)?;
 
     slog::info!(logger, "容器删除完成"; "id" => id);
 
     Ok(())
 }
 
-/// 创建最小化的 OCI Spec
+/// 生成 `ctr run` 使用的 spec
+///
+/// 指定 `bundle` 时以其中的 config.json 为基础，否则以默认模板和镜像配置生成，
+/// 最后应用命令行选项。
 ///
-/// 这是一个简化版本，用于快速测试容器创建流程
-fn create_minimal_spec(rootfs: &str, args: &[String], terminal: bool) -> Result<Spec> {
-    // 从文件加载默认 spec 或创建一个基础的
-    // 这里我们使用 oci_spec 的 builder 模式
+/// # 参数
+/// - `rootfs`: 拉取的镜像 rootfs，未指定 `--image` 时为 `None`
+/// - `oci_config`: 镜像配置
+fn run_spec(
+    rootfs: Option<String>,
+    oci_config: Option<&ImageConfiguration>,
+    bundle: Option<&str>,
+    command: &[String],
+    tty: bool,
+    opts: &SpecOptions,
+) -> Result<Spec> {
+    let args = (!command.is_empty()).then_some(command);
+    let mut spec = match bundle {
+        Some(bundle) => {
+            let mut spec = spec_opts::load_bundle_spec(bundle)?;
+            spec_opts::override_spec(&mut spec, rootfs.as_deref(), args, tty.then_some(true))?;
+            spec
+        }
+        None => {
+            let rootfs = rootfs.ok_or_else(|| anyhow::anyhow!("必须指定 --image 或 --bundle"))?;
+            let command = match oci_config {
+                Some(oci_config) => image_config::command(oci_config, args),
+                None => args.map(<[String]>::to_vec),
+            }
+            .unwrap_or_else(default_command);
+            let mut spec = spec_opts::default_spec(&rootfs, &command, tty);
+            spec_opts::share_host_network(&mut spec);
+            if let Some(oci_config) = oci_config {
+                image_config::apply_image_config(&mut spec, oci_config, Path::new(&rootfs))
+                    .context("无法应用镜像配置")?;
+            }
+            spec
+        }
+    };
+    spec_opts::apply_spec_options(&mut spec, opts).context("无效的容器配置选项")?;
+    Ok(spec)
+}
 
-    use oci_spec::runtime::{ProcessBuilder, RootBuilder, SpecBuilder};
+/// 未指定命令时容器默认执行的命令
+fn default_command() -> Vec<String> {
+    vec!["/bin/sh".to_string()]
+}
 
-    let process = ProcessBuilder::default()
-        .args(args.to_vec())
-        .terminal(terminal)  // 设置 TTY
-        .build()?;
+/// 将容器最终使用的 spec 保存到 `bundle_base()/{id}/config.json`
+///
+/// 用户的 bundle 目录只读不写，命令行覆盖后的配置保存在 runcell 自己的目录中，
+/// 由 shim 从这里加载，删除容器时一并清理。
+///
+/// # 返回
+/// 保存 config.json 的 bundle 目录
+fn save_spec(id: &str, spec: &Spec) -> Result<String> {
+    let bundle_path = format!("{}/{}", bundle_base(), id);
+    fs::create_dir_all(&bundle_path)
+        .with_context(|| format!("无法创建 bundle 目录: {}", bundle_path))?;
 
-    let root = RootBuilder::default().path(rootfs).build()?;
+    let config_path = format!("{}/config.json", bundle_path);
+    spec.save(&config_path)
+        .with_context(|| format!("无法保存 config.json 到 {}", config_path))?;
 
-    let spec = SpecBuilder::default()
-        .version("1.0.0")
-        .process(process)
-        .root(root)
-        .build()?;
+    Ok(bundle_path)
+}
 
-    Ok(spec)
+/// 创建容器的配置，状态目录位于 `state_base()/{id}`
+pub(crate) fn create_opts(id: &str, spec: &Spec) -> CreateOpts {
+    CreateOpts {
+        cgroup_name: id.to_string(),
+        use_systemd_cgroup: false,
+        no_pivot_root: false,
+        no_new_keyring: false,
+        spec: Some(spec.clone()),
+        rootless_euid: false,
+        rootless_cgroup: false,
+        container_name: id.to_string(),
+    }
+}
+
+/// 在交给 shim 之前按 celler 的规则校验 spec，尽早报告配置错误
+fn validate_spec(id: &str, spec: &Spec) -> Result<()> {
+    validator::validate(&create_opts(id, spec)).context("OCI 配置校验失败")
+}
+
+/// 根据 spec 创建 `LinuxContainer` 实例
+///
+/// 容器状态目录位于 `state_base()/{id}`。
+pub(crate) fn new_container(id: &str, spec: &Spec, logger: &Logger) -> Result<LinuxContainer> {
+    let devcg_info = Some(Arc::new(RwLock::new(DevicesCgroupInfo::default())));
+
+    LinuxContainer::new(id, state_base(), devcg_info, create_opts(id, spec), logger)
+}
+
+/// 根据 spec 中的 process 配置创建 init 进程
+pub(crate) fn new_init_process(id: &str, spec: &Spec, logger: &Logger) -> Result<Process> {
+    let oci_process = spec
+        .process()
+        .as_ref()
+        .ok_or_else(|| anyhow::anyhow!("OCI spec 中缺少 process 配置"))?;
+
+    Process::new(
+        logger,
+        oci_process,
+        id,   // exec_id
+        true, // init process
+        0,    // pipe_size (0 = default)
+        None, // proc_io (None for simple case)
+    )
+    .context("创建 Process 失败")
+}
+
+/// 生成 exec 进程的唯一标识
+///
+/// 由当前进程 PID 和纳秒时间戳组成，同一容器内不会重复。
+pub(crate) fn new_exec_id() -> String {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or(0);
+    format!("exec-{}-{:x}", std::process::id(), nanos)
 }
 
 /// 列出所有容器
@@ -373,7 +908,7 @@ fn create_minimal_spec(rootfs: &str, args: &[String], terminal: bool) -> Result<
 async fn list_containers(format: &str, show_all: bool, logger: &Logger) -> Result<()> {
     slog::info!(logger, "列出容器"; "format" => format, "all" => show_all);
 
-    let state_dir = Path::new(CONTAINER_STATE_BASE);
+    let state_dir = Path::new(state_base());
     if !state_dir.exists() {
         // 状态目录不存在，输出空列表
         if format == "json" {
@@ -395,11 +930,13 @@ async fn list_containers(format: &str, show_all: bool, logger: &Logger) -> Resul
         let container_id = entry.file_name().to_string_lossy().to_string();
 
         // 尝试读取状态文件
-        match load_container_state(CONTAINER_STATE_BASE, &container_id) {
+        match load_container_state(state_base(), &container_id) {
             Ok(state) => {
-                // 验证实际进程状态
-                let actual_status = if is_process_running(state.init_process_pid) {
-                    "Running"
+                // 验证实际进程状态：进程存活时以 state.json 记录的状态为准
+                // （Created/Running/Paused），否则视为已停止。
+                // is_running 同时比较进程启动时间，PID 被复用时不会误判。
+                let actual_status = if state.is_running() {
+                    state.status.as_str()
                 } else {
                     "Stopped"
                 };
@@ -420,6 +957,7 @@ async fn list_containers(format: &str, show_all: bool, logger: &Logger) -> Resul
                     actual_status.to_string(),
                     created,
                     state.rootfs,
+                    state.exit_code,
                 ));
             }
             Err(e) => {
@@ -434,13 +972,14 @@ async fn list_containers(format: &str, show_all: bool, logger: &Logger) -> Resul
     if format == "json" {
         let json_output: Vec<serde_json::Value> = containers
             .iter()
-            .map(|(id, pid, status, created, rootfs)| {
+            .map(|(id, pid, status, created, rootfs, exit_code)| {
                 serde_json::json!({
                     "id": id,
                     "pid": pid,
                     "status": status,
                     "created": created,
                     "rootfs": rootfs,
+                    "exit_code": exit_code,
                 })
             })
             .collect();
@@ -451,7 +990,7 @@ async fn list_containers(format: &str, show_all: bool, logger: &Logger) -> Resul
             "{:<20} {:<8} {:<10} {:<20} {}",
             "CONTAINER ID", "PID", "STATUS", "CREATED", "ROOTFS"
         );
-        for (id, pid, status, created, rootfs) in &containers {
+        for (id, pid, status, created, rootfs, _) in &containers {
             // 截断过长的 ID 和 rootfs
             let id_display = if id.len() > 18 { &id[..18] } else { id };
             let rootfs_display = if rootfs.len() > 40 {
@@ -473,68 +1012,204 @@ async fn list_containers(format: &str, show_all: bool, logger: &Logger) -> Resul
 
 /// 在运行中的容器内执行命令
 ///
-/// 通过进入容器的 namespace 来执行指定命令。
+/// 从状态目录重建容器，以 `init == false` 的 `Process` 调用 `start_container`：
+/// 新进程加入 state.json 中记录的 namespace 和容器的 cgroup，并经过与 init 进程
+/// 相同的 capabilities、seccomp、rlimits、用户、环境变量和工作目录设置。
+///
+/// # 参数
+/// - `command`: 要执行的命令，为空时使用 `process_file` 中的 args
+/// - `detach`: 启动后立即返回，不等待命令结束
+/// - `process_file`: 完整的 OCI Process JSON 文件，替代 init 进程的配置作为基础
+/// - `opts`: 用户、环境变量、工作目录和 capability 等覆盖选项
+#[allow(clippy::too_many_arguments)]
 async fn exec_in_container(
     id: &str,
-    command: &str,
-    args: &[String],
+    command: &[String],
     tty: bool,
     interactive: bool,
+    detach: bool,
+    process_file: Option<&str>,
+    opts: &ProcessOptions,
     logger: &Logger,
 ) -> Result<()> {
     slog::info!(logger, "在容器内执行命令";
-        "id" => id, "command" => command, "tty" => tty, "interactive" => interactive);
+        "id" => id, "command" => format!("{:?}", command), "tty" => tty,
+        "interactive" => interactive, "detach" => detach);
+
+    if detach && tty {
+        return Err(anyhow::anyhow!("exec 的 -d 不能与 -t 同时使用"));
+    }
 
     // 1. 读取容器状态
-    let state = load_container_state(CONTAINER_STATE_BASE, id)
+    let state = load_container_state(state_base(), id)
         .with_context(|| format!("容器 '{}' 不存在或未运行", id))?;
 
     // 2. 验证容器正在运行
-    if !is_process_running(state.init_process_pid) {
+    if !state.is_running() {
         return Err(anyhow::anyhow!(
             "容器 '{}' 未运行 (PID {} 不存在)",
             id,
             state.init_process_pid
         ));
     }
+    if state.container_state()? == ContainerState::Paused {
+        return Err(anyhow::anyhow!("容器 '{}' 已暂停，无法执行命令", id));
+    }
 
     slog::info!(logger, "找到运行中的容器";
         "pid" => state.init_process_pid);
 
-    // 3. 构建 nsenter 命令进入容器
-    // 使用 nsenter 是最简单可靠的方式进入容器 namespace
-    let pid = state.init_process_pid;
+    // 3. exec 进程同样是中间进程 fork 出的孙进程，需要成为 subreaper 才能等待它
+    if !detach {
+        shim::set_child_subreaper()?;
+    }
+
+    let mut container = LinuxContainer::load(id, state_base(), None, logger)
+        .with_context(|| format!("加载容器 '{}' 失败", id))?;
+
+    // 4. 以 --process 文件或 init 进程的配置为基础，再应用命令行选项
+    let mut oci_process = match process_file {
+        Some(path) => {
+            let content = fs::read_to_string(path)
+                .with_context(|| format!("无法读取 process 配置: {}", path))?;
+            serde_json::from_str::<oci_spec::runtime::Process>(&content)
+                .with_context(|| format!("无法解析 process 配置: {}", path))?
+        }
+        None => container
+            .config
+            .spec
+            .as_ref()
+            .and_then(|spec| spec.process().clone())
+            .ok_or_else(|| anyhow::anyhow!("OCI spec 中缺少 process 配置"))?,
+    };
+
+    if !command.is_empty() {
+        oci_process.set_args(Some(command.to_vec()));
+    }
+    if tty || process_file.is_none() {
+        oci_process.set_terminal(Some(tty));
+    }
+    apply_process_options(&mut oci_process, opts, Some(Path::new(&state.rootfs)))?;
+
+    let exec_id = new_exec_id();
+    let mut process = Process::new(logger, &oci_process, &exec_id, false, 0, None)
+        .context("创建 Process 失败")?;
+    if detach {
+        // 后台进程不占用当前终端
+        let null = fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open("/dev/null")?;
+        let fd = null.as_raw_fd();
+        process
+            .set_stdio(fd, fd, fd)
+            .context("设置标准输入输出失败")?;
+    } else {
+        process.inherit_stdio().context("继承标准输入输出失败")?;
+    }
+
+    // 5. 在容器内启动进程
+    container
+        .start_container(process)
+        .await
+        .context("在容器内启动进程失败")?;
+
+    let process = container
+        .processes
+        .get(&exec_id)
+        .ok_or_else(|| anyhow::anyhow!("找不到 exec 进程 '{}'", exec_id))?;
+    let pid = process.pid;
 
-    let mut nsenter_args = vec![
-        format!("--target={}", pid),
-        "--mount".to_string(),
-        "--uts".to_string(),
-        "--ipc".to_string(),
-        "--net".to_string(),
-        "--pid".to_string(),
-    ];
+    slog::info!(logger, "exec 进程已启动"; "exec_id" => &exec_id, "pid" => pid);
 
-    // 添加要执行的命令
-    nsenter_args.push("--".to_string());
-    nsenter_args.push(command.to_string());
-    nsenter_args.extend(args.iter().cloned());
+    if detach {
+        println!("{}", exec_id);
+        return Ok(());
+    }
 
-    slog::info!(logger, "执行 nsenter"; "args" => format!("{:?}", nsenter_args));
+    let foreground = Foreground::attach(pid, process.term_master, interactive, logger)?;
 
-    // 4. 执行 nsenter
-    let status = std::process::Command::new("nsenter")
-        .args(&nsenter_args)
-        .stdin(std::process::Stdio::inherit())
-        .stdout(std::process::Stdio::inherit())
-        .stderr(std::process::Stdio::inherit())
-        .status()
-        .context("执行 nsenter 失败")?;
+    // 6. 等待进程退出
+    let status = shim::wait_process(pid).await?;
+    foreground.finish();
 
-    if !status.success() {
-        return Err(anyhow::anyhow!("命令执行失败，退出码: {:?}", status.code()));
+    if status.code != 0 {
+        return Err(shim::ExitError(status.code).into());
     }
 
     slog::info!(logger, "命令执行完成");
 
     Ok(())
 }
+
+/// 前台运行的容器进程与当前终端之间的连接
+///
+/// - 将收到的信号转发给容器进程；
+/// - 使用伪终端时同步窗口大小，并在 master 与当前终端之间转发数据；
+/// - 交互模式（`-i`）下将当前终端切换为 raw 模式，结束时恢复。
+struct Foreground {
+    outputs: Vec<JoinHandle<()>>,
+    _raw: Option<terminal::RawTerminal>,
+    _signals: signals::SignalForwarder,
+}
+
+impl Foreground {
+    /// 连接 exec 进程：只转发伪终端，非 TTY 进程直接继承当前进程的标准输入输出
+    fn attach(pid: i32, master: Option<RawFd>, interactive: bool, logger: &Logger) -> Result<Self> {
+        let mut foreground = Self::new(pid, master, interactive, logger)?;
+        if let Some(master) = master {
+            foreground
+                .outputs
+                .push(terminal::proxy_terminal(master, interactive)?);
+        }
+        Ok(foreground)
+    }
+
+    /// 连接容器 init 进程：转发 `io` 中的全部输入输出，输出同时写入容器日志
+    fn attach_logged(
+        pid: i32,
+        master: Option<RawFd>,
+        io: attach::AttachIo,
+        log: Arc<JsonFileLogger>,
+        interactive: bool,
+        logger: &Logger,
+    ) -> Result<Self> {
+        let mut foreground = Self::new(pid, master, interactive, logger)?;
+        foreground.outputs = io.forward_local(log, logger)?;
+        Ok(foreground)
+    }
+
+    fn new(pid: i32, master: Option<RawFd>, interactive: bool, logger: &Logger) -> Result<Self> {
+        let signals = signals::forward_signals(pid, master, logger)?;
+
+        let Some(master) = master else {
+            return Ok(Self {
+                outputs: Vec::new(),
+                _raw: None,
+                _signals: signals,
+            });
+        };
+
+        if let Err(e) = terminal::resize_pty(master) {
+            slog::debug!(logger, "同步终端窗口大小失败"; "error" => format!("{:?}", e));
+        }
+        let raw = if interactive {
+            terminal::RawTerminal::enable(std::io::stdin().as_raw_fd())?
+        } else {
+            None
+        };
+
+        Ok(Self {
+            outputs: Vec::new(),
+            _raw: raw,
+            _signals: signals,
+        })
+    }
+
+    /// 容器进程退出后调用：等待输出转发完成，随后恢复终端设置并停止信号转发
+    fn finish(mut self) {
+        for output in self.outputs.drain(..) {
+            let _ = output.join();
+        }
+    }
+}
@@ -0,0 +1,175 @@
+//! 运行时特性（`runcell features`）
+//!
+//! 按 OCI runtime-spec 的 features.json 格式输出 celler 实际支持的 namespace、
+//! capability、挂载选项、seccomp、钩子和 cgroup 版本，上层工具据此判断
+//! 能否把某个 spec 交给 runcell 运行。
+
+use anyhow::Result;
+use celler::{
+    capabilities::get_all_caps,
+    cgroups::{MOCK_CGROUP, is_cgroup2_unified_mode},
+    container::{NAMESPACES, TYPETONAME},
+    mount::supported_mount_options,
+    selinux,
+};
+use serde_json::{Map, Value, json};
+
+/// 支持的 OCI 运行时规范最低版本
+const OCI_VERSION_MIN: &str = "1.0.0";
+
+/// 支持的 OCI 运行时规范最高版本
+const OCI_VERSION_MAX: &str = "1.2.0";
+
+/// celler 会执行的 OCI 钩子
+const HOOKS: &[&str] = &[
+    "prestart",
+    "createContainer",
+    "startContainer",
+    "poststart",
+    "poststop",
+];
+
+/// runcell 自定义注解的前缀
+const ANNOTATION_PREFIX: &str = "io.github.dragonos-community.runcell";
+
+/// 输出 features.json
+pub fn print_features() -> Result<()> {
+    println!("{}", serde_json::to_string_pretty(&features())?);
+    Ok(())
+}
+
+/// 生成 features.json 的内容
+fn features() -> Value {
+    let unified = is_cgroup2_unified_mode();
+    let cgroup_mode = if MOCK_CGROUP {
+        "mock"
+    } else if unified {
+        "v2"
+    } else {
+        "v1"
+    };
+
+    let mut annotations = Map::new();
+    annotations.insert(
+        format!("{}.version", ANNOTATION_PREFIX),
+        env!("CARGO_PKG_VERSION").into(),
+    );
+    annotations.insert(
+        format!("{}.cgroup.mode", ANNOTATION_PREFIX),
+        cgroup_mode.into(),
+    );
+    #[cfg(feature = "seccomp")]
+    if let Ok(version) = celler::seccomp::libseccomp_version() {
+        annotations.insert(
+            "io.github.seccomp.libseccomp.version".to_string(),
+            version.into(),
+        );
+    }
+
+    json!({
+        "ociVersionMin": OCI_VERSION_MIN,
+        "ociVersionMax": OCI_VERSION_MAX,
+        "hooks": HOOKS,
+        "mountOptions": supported_mount_options(),
+        "linux": {
+            "namespaces": namespaces(),
+            "capabilities": capabilities(),
+            // mock-cgroup 下不创建 cgroup，两个版本都不支持；否则取决于宿主机挂载的版本
+            "cgroup": {
+                "v1": !MOCK_CGROUP && !unified,
+                "v2": !MOCK_CGROUP && unified,
+                "systemd": false,
+                "systemdUser": false,
+                "rdma": false,
+            },
+            "seccomp": seccomp(),
+            "apparmor": { "enabled": false },
+            "selinux": { "enabled": selinux::is_enabled().unwrap_or(false) },
+            "intelRdt": { "enabled": false },
+            "mountExtensions": { "idmap": { "enabled": false } },
+        },
+        "annotations": annotations,
+    })
+}
+
+/// 支持的 namespace 类型（OCI 名称，如 `mount`、`network`），按名称排序
+fn namespaces() -> Vec<String> {
+    let mut namespaces: Vec<String> = TYPETONAME
+        .iter()
+        .filter(|(_, name)| NAMESPACES.contains_key(*name))
+        .filter_map(|(typ, _)| match serde_json::to_value(typ) {
+            Ok(Value::String(s)) => Some(s),
+            _ => None,
+        })
+        .collect();
+    namespaces.sort();
+    namespaces
+}
+
+/// 当前内核支持的全部 capability（如 `CAP_CHOWN`），按名称排序
+fn capabilities() -> Vec<String> {
+    let mut caps: Vec<String> = get_all_caps().iter().map(|c| c.to_string()).collect();
+    caps.sort();
+    caps
+}
+
+#[cfg(feature = "seccomp")]
+fn seccomp() -> Value {
+    use celler::seccomp::{
+        SUPPORTED_ACTIONS, SUPPORTED_ARCHS, SUPPORTED_FLAGS, SUPPORTED_OPERATORS,
+    };
+
+    json!({
+        "enabled": true,
+        "actions": SUPPORTED_ACTIONS,
+        "operators": SUPPORTED_OPERATORS,
+        "archs": SUPPORTED_ARCHS,
+        "knownFlags": SUPPORTED_FLAGS,
+        "supportedFlags": SUPPORTED_FLAGS,
+    })
+}
+
+#[cfg(not(feature = "seccomp"))]
+fn seccomp() -> Value {
+    json!({ "enabled": false })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_features() {
+        let features = features();
+
+        assert_eq!(features["ociVersionMin"], OCI_VERSION_MIN);
+        assert_eq!(features["ociVersionMax"], OCI_VERSION_MAX);
+        assert_eq!(features["hooks"].as_array().unwrap().len(), HOOKS.len());
+
+        let options = features["mountOptions"].as_array().unwrap();
+        assert!(options.contains(&json!("rbind")));
+        assert!(options.contains(&json!("rprivate")));
+
+        let linux = &features["linux"];
+        assert_eq!(
+            linux["namespaces"],
+            json!(["cgroup", "ipc", "mount", "network", "pid", "user", "uts"])
+        );
+        let caps = linux["capabilities"].as_array().unwrap();
+        assert!(!caps.is_empty());
+        assert!(caps.iter().all(|c| c.as_str().unwrap().starts_with("CAP_")));
+
+        assert_eq!(linux["seccomp"]["enabled"], cfg!(feature = "seccomp"));
+        assert_eq!(linux["cgroup"]["systemd"], false);
+        if MOCK_CGROUP {
+            assert_eq!(linux["cgroup"]["v1"], false);
+            assert_eq!(linux["cgroup"]["v2"], false);
+        }
+        assert!(
+            features["annotations"]
+                .as_object()
+                .unwrap()
+                .contains_key(&format!("{}.cgroup.mode", ANNOTATION_PREFIX))
+        );
+    }
+}
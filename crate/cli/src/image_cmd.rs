@@ -0,0 +1,167 @@
+//! 本地镜像管理（`image ls` / `image inspect` / `image rm` / `image prune`）
+//!
+//! 带镜像配置的镜像（`oci://`、`oci-archive://`、`docker-archive://` 和 `docker save` 归档）
+//! 在 `ctr run` 加载后记录到镜像目录的镜像索引中，这里的命令都基于该索引。
+
+use anyhow::Result;
+use slog::Logger;
+use storage::image::index::{self, ImageRecord};
+
+use crate::{ImageCommands, config, stats::format_decimal_size};
+
+/// 表格中镜像 ID 和摘要显示的十六进制位数
+const SHORT_HEX_LEN: usize = 12;
+
+/// 处理镜像相关命令
+pub fn handle_image_command(cmd: ImageCommands, logger: &Logger) -> Result<()> {
+    let image_root = config::image_root();
+    match cmd {
+        ImageCommands::List { format } => {
+            let images = index::list_images(image_root)?;
+            slog::info!(logger, "列出镜像"; "count" => images.len());
+            if format == "json" {
+                print_json(&images)
+            } else {
+                print_table(&images);
+                Ok(())
+            }
+        }
+
+        ImageCommands::Inspect { images } => {
+            let output = images
+                .iter()
+                .map(|reference| index::find_image(image_root, reference).map(inspect_json))
+                .collect::<Result<Vec<_>>>()?;
+            println!("{}", serde_json::to_string_pretty(&output)?);
+            Ok(())
+        }
+
+        ImageCommands::Remove { images } => {
+            for reference in &images {
+                let record = index::remove_image(image_root, reference, logger)?;
+                println!("Deleted: {}", record.id);
+            }
+            Ok(())
+        }
+
+        ImageCommands::Prune { all } => {
+            let report = index::prune_images(image_root, all, logger)?;
+            for id in &report.images {
+                println!("Deleted: {}", id);
+            }
+            println!("Deleted layers: {}", report.layers);
+            slog::info!(logger, "镜像清理完成";
+                "images" => report.images.len(), "layers" => report.layers);
+            Ok(())
+        }
+    }
+}
+
+/// 以表格输出镜像列表，每个名称一行，未命名的镜像显示为 `<none>`
+fn print_table(images: &[ImageRecord]) {
+    println!(
+        "{:<40} {:<14} {:<21} {:<20} {}",
+        "NAME", "IMAGE ID", "DIGEST", "CREATED", "SIZE"
+    );
+    for image in images {
+        let id = short_hex(&image.id);
+        let digest = image
+            .digest
+            .as_deref()
+            .map(|d| format!("sha256:{}", short_hex(d)))
+            .unwrap_or_else(|| "<none>".to_string());
+        let created = format_created(image.created());
+        let size = format_decimal_size(image.size);
+
+        let none = ["<none>".to_string()];
+        let names = if image.names.is_empty() {
+            &none[..]
+        } else {
+            &image.names[..]
+        };
+        for name in names {
+            println!(
+                "{:<40} {:<14} {:<21} {:<20} {}",
+                name, id, digest, created, size
+            );
+        }
+    }
+}
+
+/// 以 JSON 输出镜像列表
+fn print_json(images: &[ImageRecord]) -> Result<()> {
+    let output: Vec<serde_json::Value> = images
+        .iter()
+        .map(|image| {
+            serde_json::json!({
+                "id": image.id,
+                "names": image.names,
+                "digest": image.digest,
+                "created": image.created(),
+                "size": image.size,
+            })
+        })
+        .collect();
+    println!("{}", serde_json::to_string_pretty(&output)?);
+    Ok(())
+}
+
+/// `image inspect` 输出的镜像详情
+fn inspect_json(image: ImageRecord) -> serde_json::Value {
+    serde_json::json!({
+        "id": image.id,
+        "names": image.names,
+        "digest": image.digest,
+        "created": image.created(),
+        "size": image.size,
+        "os": image.config.os().to_string(),
+        "architecture": image.config.architecture().to_string(),
+        "layers": image.layers(),
+        "manifest": image.manifest,
+        "config": image.config,
+    })
+}
+
+/// 去掉 `sha256:` 前缀，保留前 12 位
+fn short_hex(digest: &str) -> &str {
+    let hex = digest.split_once(':').map_or(digest, |(_, hex)| hex);
+    &hex[..hex.len().min(SHORT_HEX_LEN)]
+}
+
+/// 将 RFC 3339 格式的创建时间转换为 UTC 的 `%Y-%m-%d %H:%M:%S`
+fn format_created(created: Option<&str>) -> String {
+    created
+        .and_then(|c| chrono::DateTime::parse_from_rfc3339(c).ok())
+        .map(|dt| {
+            dt.with_timezone(&chrono::Utc)
+                .format("%Y-%m-%d %H:%M:%S")
+                .to_string()
+        })
+        .unwrap_or_else(|| "Unknown".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_short_hex() {
+        let id = format!("sha256:{}", "0123456789abcdef".repeat(4));
+        assert_eq!(short_hex(&id), "0123456789ab");
+        assert_eq!(short_hex("abc"), "abc");
+    }
+
+    #[test]
+    fn test_format_created() {
+        assert_eq!(
+            format_created(Some("2024-05-01T08:30:00+08:00")),
+            "2024-05-01 00:30:00"
+        );
+        assert_eq!(
+            format_created(Some("2024-05-01T00:30:00.123456789Z")),
+            "2024-05-01 00:30:00"
+        );
+        assert_eq!(format_created(Some("yesterday")), "Unknown");
+        assert_eq!(format_created(None), "Unknown");
+    }
+}
@@ -0,0 +1,359 @@
+//! 镜像配置
+//!
+//! 镜像带有 OCI/Docker 配置时，`ctr run` 按配置生成默认 spec：
+//! - 进程参数为 Entrypoint + Cmd，命令行上的参数替换 Cmd
+//! - Env 合并到模板的环境变量中，同名变量以镜像为准
+//! - WorkingDir 作为工作目录
+//! - User 按 rootfs 中的 `/etc/passwd`、`/etc/group` 解析为 uid、gid 和附加组
+//! - Labels 和 ExposedPorts 写入 spec 注解
+//!
+//! 命令行上的 `-e`、`--workdir`、`--user` 等选项在此之后应用，优先于镜像配置，
+//! `--user` 同样按 rootfs 解析用户名和组名。
+
+use std::{fs, io, path::Path};
+
+use anyhow::{Context, Result, anyhow};
+use oci_spec::{image::ImageConfiguration, runtime::Spec};
+use safe_path::scoped_join;
+
+use crate::process_opts::merge_env;
+
+/// 记录镜像暴露端口的注解（OCI 镜像规范的转换约定）
+const ANNOTATION_EXPOSED_PORTS: &str = "org.opencontainers.image.exposedPorts";
+
+/// 镜像的进程参数：Entrypoint + Cmd，`args` 非空时替换 Cmd
+///
+/// # 返回
+/// 镜像和命令行都未指定命令时返回 `None`
+pub fn command(config: &ImageConfiguration, args: Option<&[String]>) -> Option<Vec<String>> {
+    let image = config.config().as_ref();
+    let mut command = image
+        .and_then(|c| c.entrypoint().clone())
+        .unwrap_or_default();
+    match args {
+        Some(args) => command.extend_from_slice(args),
+        None => command.extend(image.and_then(|c| c.cmd().clone()).unwrap_or_default()),
+    }
+    (!command.is_empty()).then_some(command)
+}
+
+/// 将镜像配置中的环境变量、工作目录、用户和注解应用到 spec
+///
+/// # 参数
+/// - `spec`: 由镜像 rootfs 生成的默认 spec
+/// - `config`: 镜像配置
+/// - `rootfs`: 镜像 rootfs，用于解析用户名和组名
+pub fn apply_image_config(
+    spec: &mut Spec,
+    config: &ImageConfiguration,
+    rootfs: &Path,
+) -> Result<()> {
+    let Some(image) = config.config() else {
+        return Ok(());
+    };
+
+    let mut process = spec
+        .process()
+        .clone()
+        .ok_or_else(|| anyhow!("spec 中缺少 process"))?;
+
+    if let Some(image_env) = image.env() {
+        let mut env = process.env().clone().unwrap_or_default();
+        for e in image_env.iter().filter(|e| e.contains('=')) {
+            merge_env(&mut env, e);
+        }
+        process.set_env(Some(env));
+    }
+
+    if let Some(workdir) = image.working_dir().as_deref().filter(|w| !w.is_empty()) {
+        // 镜像中的相对路径按根目录解析
+        process.set_cwd(Path::new("/").join(workdir));
+    }
+
+    if let Some(user) = image.user().as_deref().filter(|u| !u.is_empty()) {
+        let resolved =
+            resolve_user(rootfs, user).with_context(|| format!("无法解析镜像用户 '{}'", user))?;
+        let mut u = process.user().clone();
+        u.set_uid(resolved.uid);
+        u.set_gid(resolved.gid);
+        u.set_additional_gids(
+            (!resolved.additional_gids.is_empty()).then_some(resolved.additional_gids),
+        );
+        process.set_user(u);
+    }
+    spec.set_process(Some(process));
+
+    let mut annotations = spec.annotations().clone().unwrap_or_default();
+    if let Some(labels) = image.labels() {
+        annotations.extend(labels.iter().map(|(k, v)| (k.clone(), v.clone())));
+    }
+    if let Some(ports) = image.exposed_ports().as_ref().filter(|p| !p.is_empty()) {
+        let mut ports = ports.clone();
+        ports.sort();
+        annotations.insert(ANNOTATION_EXPOSED_PORTS.to_string(), ports.join(","));
+    }
+    if !annotations.is_empty() {
+        spec.set_annotations(Some(annotations));
+    }
+
+    Ok(())
+}
+
+/// 解析后的容器用户
+#[derive(Debug, PartialEq)]
+pub(crate) struct ResolvedUser {
+    pub(crate) uid: u32,
+    pub(crate) gid: u32,
+    pub(crate) additional_gids: Vec<u32>,
+}
+
+/// `/etc/passwd` 中的一行
+struct PasswdEntry {
+    name: String,
+    uid: u32,
+    gid: u32,
+}
+
+/// `/etc/group` 中的一行
+struct GroupEntry {
+    name: String,
+    gid: u32,
+    members: Vec<String>,
+}
+
+/// 按 rootfs 中的 `/etc/passwd`、`/etc/group` 解析 `user[:group]`
+///
+/// 用户和组可以是名称或数字 id。与 Docker 一致：
+/// - 未指定组时使用用户的主组，并把用户所属的其他组作为附加组
+/// - 数字 uid 在 `/etc/passwd` 中不存在时主组为 0
+/// - 名称在对应文件中不存在时报错
+pub(crate) fn resolve_user(rootfs: &Path, user: &str) -> Result<ResolvedUser> {
+    let (user, group) = match user.split_once(':') {
+        Some((user, group)) => (user, Some(group)),
+        None => (user, None),
+    };
+
+    let passwd = parse_passwd(&read_rootfs_file(rootfs, "etc/passwd")?);
+    let groups = parse_group(&read_rootfs_file(rootfs, "etc/group")?);
+
+    let entry = match user.parse::<u32>() {
+        Ok(uid) => passwd.iter().find(|e| e.uid == uid),
+        Err(_) => passwd.iter().find(|e| e.name == user),
+    };
+    let (uid, primary_gid) = match (entry, user.parse::<u32>()) {
+        (Some(entry), _) => (entry.uid, entry.gid),
+        (None, Ok(uid)) => (uid, 0),
+        (None, Err(_)) => return Err(anyhow!("用户 {} 不在 /etc/passwd 中", user)),
+    };
+
+    let Some(group) = group else {
+        let additional_gids = match entry {
+            Some(entry) => {
+                let mut gids: Vec<u32> = groups
+                    .iter()
+                    .filter(|g| g.gid != primary_gid && g.members.contains(&entry.name))
+                    .map(|g| g.gid)
+                    .collect();
+                gids.sort_unstable();
+                gids.dedup();
+                gids
+            }
+            None => Vec::new(),
+        };
+        return Ok(ResolvedUser {
+            uid,
+            gid: primary_gid,
+            additional_gids,
+        });
+    };
+
+    let gid = match group.parse::<u32>() {
+        Ok(gid) => gid,
+        Err(_) => groups
+            .iter()
+            .find(|g| g.name == group)
+            .map(|g| g.gid)
+            .ok_or_else(|| anyhow!("组 {} 不在 /etc/group 中", group))?,
+    };
+    Ok(ResolvedUser {
+        uid,
+        gid,
+        additional_gids: Vec::new(),
+    })
+}
+
+/// 读取 rootfs 中的文件，符号链接限制在 rootfs 内解析，文件不存在时返回空字符串
+fn read_rootfs_file(rootfs: &Path, path: &str) -> Result<String> {
+    let path = scoped_join(rootfs, path)?;
+    match fs::read_to_string(&path) {
+        Ok(content) => Ok(content),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(String::new()),
+        Err(e) => Err(e).with_context(|| format!("无法读取 {}", path.display())),
+    }
+}
+
+/// 解析 `name:password:uid:gid:gecos:home:shell`，忽略格式错误的行
+fn parse_passwd(content: &str) -> Vec<PasswdEntry> {
+    content
+        .lines()
+        .filter(|l| !l.trim().is_empty() && !l.starts_with('#'))
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split(':').collect();
+            Some(PasswdEntry {
+                name: fields.first()?.to_string(),
+                uid: fields.get(2)?.parse().ok()?,
+                gid: fields.get(3)?.parse().ok()?,
+            })
+        })
+        .collect()
+}
+
+/// 解析 `name:password:gid:member,member`，忽略格式错误的行
+fn parse_group(content: &str) -> Vec<GroupEntry> {
+    content
+        .lines()
+        .filter(|l| !l.trim().is_empty() && !l.starts_with('#'))
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split(':').collect();
+            Some(GroupEntry {
+                name: fields.first()?.to_string(),
+                gid: fields.get(2)?.parse().ok()?,
+                members: fields
+                    .get(3)
+                    .map(|m| {
+                        m.split(',')
+                            .filter(|m| !m.is_empty())
+                            .map(String::from)
+                            .collect()
+                    })
+                    .unwrap_or_default(),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::spec_opts::default_spec;
+
+    fn image_config(config: serde_json::Value) -> ImageConfiguration {
+        serde_json::from_value(json!({
+            "architecture": "amd64",
+            "os": "linux",
+            "config": config,
+            "rootfs": { "type": "layers", "diff_ids": [] },
+        }))
+        .unwrap()
+    }
+
+    fn test_rootfs(name: &str) -> std::path::PathBuf {
+        let rootfs = std::env::temp_dir().join(format!(
+            "runcell-image-config-{}-{}",
+            name,
+            std::process::id()
+        ));
+        fs::create_dir_all(rootfs.join("etc")).unwrap();
+        fs::write(
+            rootfs.join("etc/passwd"),
+            "root:x:0:0:root:/root:/bin/sh\napp:x:1000:1000::/home/app:/bin/sh\n",
+        )
+        .unwrap();
+        fs::write(
+            rootfs.join("etc/group"),
+            "root:x:0:\napp:x:1000:\nwheel:x:10:root,app\naudio:x:29:app\n",
+        )
+        .unwrap();
+        rootfs
+    }
+
+    #[test]
+    fn test_command() {
+        let config = image_config(json!({
+            "Entrypoint": ["/entrypoint.sh"],
+            "Cmd": ["serve", "--port", "80"],
+        }));
+        assert_eq!(
+            command(&config, None).unwrap(),
+            ["/entrypoint.sh", "serve", "--port", "80"]
+        );
+        let args = ["debug".to_string()];
+        assert_eq!(
+            command(&config, Some(&args)).unwrap(),
+            ["/entrypoint.sh", "debug"]
+        );
+
+        let config = image_config(json!({ "Cmd": ["/bin/bash"] }));
+        assert_eq!(command(&config, None).unwrap(), ["/bin/bash"]);
+        assert!(command(&image_config(json!({})), None).is_none());
+    }
+
+    #[test]
+    fn test_resolve_user() {
+        let rootfs = test_rootfs("user");
+
+        assert_eq!(
+            resolve_user(&rootfs, "app").unwrap(),
+            ResolvedUser {
+                uid: 1000,
+                gid: 1000,
+                additional_gids: vec![10, 29],
+            }
+        );
+        assert_eq!(
+            resolve_user(&rootfs, "1000:wheel").unwrap(),
+            ResolvedUser {
+                uid: 1000,
+                gid: 10,
+                additional_gids: vec![],
+            }
+        );
+        assert_eq!(
+            resolve_user(&rootfs, "4242").unwrap(),
+            ResolvedUser {
+                uid: 4242,
+                gid: 0,
+                additional_gids: vec![],
+            }
+        );
+        assert!(resolve_user(&rootfs, "nobody").is_err());
+        assert!(resolve_user(&rootfs, "app:nogroup").is_err());
+
+        fs::remove_dir_all(&rootfs).unwrap();
+    }
+
+    #[test]
+    fn test_apply_image_config() {
+        let rootfs = test_rootfs("apply");
+        let config = image_config(json!({
+            "User": "app",
+            "Env": ["PATH=/app/bin:/usr/bin", "APP_ENV=prod"],
+            "WorkingDir": "/srv",
+            "ExposedPorts": { "443/tcp": {}, "80/tcp": {} },
+            "Labels": { "org.example.team": "infra" },
+        }));
+        let mut spec = default_spec(rootfs.to_str().unwrap(), &[], false);
+        apply_image_config(&mut spec, &config, &rootfs).unwrap();
+
+        let process = spec.process().as_ref().unwrap();
+        let env = process.env().as_ref().unwrap();
+        assert!(env.contains(&"PATH=/app/bin:/usr/bin".to_string()));
+        assert!(env.contains(&"APP_ENV=prod".to_string()));
+        assert_eq!(env.iter().filter(|e| e.starts_with("PATH=")).count(), 1);
+        assert_eq!(process.cwd(), Path::new("/srv"));
+        assert_eq!(process.user().uid(), 1000);
+        assert_eq!(process.user().gid(), 1000);
+        assert_eq!(
+            process.user().additional_gids().as_deref(),
+            Some(&[10, 29][..])
+        );
+
+        let annotations = spec.annotations().as_ref().unwrap();
+        assert_eq!(annotations["org.example.team"], "infra");
+        assert_eq!(annotations[ANNOTATION_EXPOSED_PORTS], "443/tcp,80/tcp");
+
+        fs::remove_dir_all(&rootfs).unwrap();
+    }
+}
@@ -0,0 +1,114 @@
+//! JSON 格式的日志输出（`--log-format json`）
+//!
+//! 与 runc 一致，每条日志占一行：
+//! `{"level":"error","msg":"...","time":"2024-01-02T15:04:05.000000000Z", ...}`，
+//! 记录上的键值对作为额外字段。containerd 等调用方从 `--log` 文件中读取这些记录，
+//! 以获取运行时失败的原因。
+
+use std::{
+    fmt,
+    io::{self, Write},
+    sync::Mutex,
+};
+
+use chrono::{SecondsFormat, Utc};
+use serde_json::{Map, Value};
+use slog::{Drain, KV, Level, OwnedKVList, Record};
+
+/// 将日志记录写为 JSON 行的 slog drain
+pub struct JsonDrain<W: Write> {
+    writer: Mutex<W>,
+}
+
+impl<W: Write> JsonDrain<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer: Mutex::new(writer),
+        }
+    }
+}
+
+impl<W: Write> Drain for JsonDrain<W> {
+    type Ok = ();
+    type Err = io::Error;
+
+    fn log(&self, record: &Record, values: &OwnedKVList) -> io::Result<()> {
+        let mut fields = Map::new();
+        let mut serializer = FieldSerializer(&mut fields);
+        values
+            .serialize(record, &mut serializer)
+            .and_then(|_| record.kv().serialize(record, &mut serializer))
+            .map_err(|e| io::Error::other(e.to_string()))?;
+
+        fields.insert("level".to_string(), level_name(record.level()).into());
+        fields.insert("msg".to_string(), record.msg().to_string().into());
+        fields.insert(
+            "time".to_string(),
+            Utc::now()
+                .to_rfc3339_opts(SecondsFormat::Nanos, true)
+                .into(),
+        );
+
+        let mut line = serde_json::to_vec(&fields)?;
+        line.push(b'\n');
+
+        let mut writer = self.writer.lock().unwrap_or_else(|e| e.into_inner());
+        writer.write_all(&line)?;
+        writer.flush()
+    }
+}
+
+/// 与 logrus 一致的级别名称
+fn level_name(level: Level) -> &'static str {
+    match level {
+        Level::Critical => "fatal",
+        Level::Error => "error",
+        Level::Warning => "warning",
+        Level::Info => "info",
+        Level::Debug => "debug",
+        Level::Trace => "trace",
+    }
+}
+
+/// 把键值对收集为 JSON 字符串字段
+struct FieldSerializer<'a>(&'a mut Map<String, Value>);
+
+impl slog::Serializer for FieldSerializer<'_> {
+    fn emit_arguments(&mut self, key: slog::Key, val: &fmt::Arguments) -> slog::Result {
+        self.0.insert(key.to_string(), val.to_string().into());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use slog::{Logger, o};
+
+    use super::*;
+
+    #[test]
+    fn test_json_drain() {
+        let drain = Arc::new(JsonDrain::new(Vec::new()));
+        let logger = Logger::root(drain.clone().fuse(), o!("version" => "1.0"));
+
+        slog::error!(logger, "创建容器失败"; "id" => "c1");
+        slog::info!(logger, "second");
+
+        let output = drain.writer.lock().unwrap().clone();
+        let lines: Vec<Value> = String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["level"], "error");
+        assert_eq!(lines[0]["msg"], "创建容器失败");
+        assert_eq!(lines[0]["id"], "c1");
+        assert_eq!(lines[0]["version"], "1.0");
+        assert!(lines[0]["time"].as_str().unwrap().ends_with('Z'));
+        assert_eq!(lines[1]["level"], "info");
+    }
+}
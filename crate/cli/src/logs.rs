@@ -0,0 +1,380 @@
+//! 容器日志（`ctr logs`）
+//!
+//! `ctr run -d` 启动的容器的输出由 shim 写入状态目录中的 `container.log`
+//! （json-file 格式，见 `celler::container_log`），本模块负责读取、过滤和跟踪这些日志。
+
+use std::{
+    fs::{self, File},
+    io::{self, Read, Seek, Write},
+    os::unix::fs::MetadataExt,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result, anyhow};
+use celler::{
+    container::{is_process_running, load_container_state},
+    container_log::{LOG_FILE_NAME, LogConfig, LogEntry, log_files, parse_entries},
+};
+use chrono::{DateTime, Duration, Utc};
+use slog::Logger;
+
+use crate::{config::state_base, container_cmd::POLL_INTERVAL, resource_opts::parse_size};
+
+/// 容器的日志文件路径
+pub fn log_path(id: &str) -> PathBuf {
+    Path::new(state_base()).join(id).join(LOG_FILE_NAME)
+}
+
+/// 解析 `--log-opt` 选项
+///
+/// 支持 `max-size=<大小>`（如 `10m`，`-1` 表示不限制）和 `max-file=<数量>`，
+/// 与 docker 一致，`max-file` 大于 1 时必须同时指定 `max-size`。
+pub fn parse_log_opts(opts: &[String]) -> Result<LogConfig> {
+    let mut config = LogConfig::default();
+
+    for opt in opts {
+        let (key, value) = opt
+            .split_once('=')
+            .ok_or_else(|| anyhow!("无效的日志选项 '{}'，格式为 key=value", opt))?;
+        match key {
+            "max-size" => {
+                config.max_size = if value == "-1" {
+                    None
+                } else {
+                    let size = parse_size(value)?;
+                    if size <= 0 {
+                        return Err(anyhow!("无效的 max-size: {}，必须大于 0", value));
+                    }
+                    Some(size as u64)
+                };
+            }
+            "max-file" => {
+                config.max_file = value
+                    .parse()
+                    .ok()
+                    .filter(|n| *n >= 1)
+                    .ok_or_else(|| anyhow!("无效的 max-file: {}，必须为正整数", value))?;
+            }
+            _ => {
+                return Err(anyhow!(
+                    "不支持的日志选项 '{}'（可选 max-size, max-file）",
+                    key
+                ));
+            }
+        }
+    }
+
+    if config.max_file > 1 && config.max_size.is_none() {
+        return Err(anyhow!("max-file 只能与 max-size 一起使用"));
+    }
+
+    Ok(config)
+}
+
+/// 解析 `--since`
+///
+/// 支持 RFC3339 时间（`2024-01-02T15:04:05Z`）、Unix 时间戳（`1704207845`）
+/// 和相对于 `now` 的时长（`90s`、`10m`、`1h30m`）。
+fn parse_since(s: &str, now: DateTime<Utc>) -> Result<DateTime<Utc>> {
+    if let Ok(time) = DateTime::parse_from_rfc3339(s) {
+        return Ok(time.with_timezone(&Utc));
+    }
+
+    if let Ok(secs) = s.parse::<f64>() {
+        return DateTime::from_timestamp(secs.trunc() as i64, (secs.fract() * 1e9) as u32)
+            .ok_or_else(|| anyhow!("无效的时间戳: {}", s));
+    }
+
+    let total = parse_duration(s).ok_or_else(|| {
+        anyhow!(
+            "无效的 --since '{}'（可用 RFC3339 时间、Unix 时间戳或 10m 等时长）",
+            s
+        )
+    })?;
+
+    Ok(now - total)
+}
+
+/// 解析由 `h`、`m`、`s`、`ms` 组成的时长（如 `90s`、`1h30m`），无效或为零时返回 `None`
+pub(crate) fn parse_duration(s: &str) -> Option<Duration> {
+    let mut total = Duration::zero();
+    let mut rest = s;
+    while !rest.is_empty() {
+        let digits = rest.find(|c: char| !c.is_ascii_digit())?;
+        let value: i64 = rest[..digits].parse().ok()?;
+        rest = &rest[digits..];
+
+        let unit_len = rest
+            .find(|c: char| c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        total += match &rest[..unit_len] {
+            "h" => Duration::hours(value),
+            "m" => Duration::minutes(value),
+            "s" => Duration::seconds(value),
+            "ms" => Duration::milliseconds(value),
+            _ => return None,
+        };
+        rest = &rest[unit_len..];
+    }
+
+    (!total.is_zero()).then_some(total)
+}
+
+/// 从当前日志文件中读取新追加的记录
+///
+/// 记录读到的位置，`--follow` 时只输出之后追加的内容。shim 轮转日志时，
+/// 读完旧文件的剩余内容后切换到新文件；文件被清空（`max-file=1`）时从头读取。
+struct LogTail {
+    path: PathBuf,
+    file: Option<File>,
+    /// 尚未读到换行的不完整记录
+    pending: Vec<u8>,
+}
+
+impl LogTail {
+    fn new(path: &Path) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            file: None,
+            pending: Vec::new(),
+        }
+    }
+
+    fn read_new(&mut self) -> Result<Vec<LogEntry>> {
+        let mut entries = Vec::new();
+
+        loop {
+            if self.file.is_none() {
+                match File::open(&self.path) {
+                    Ok(file) => self.file = Some(file),
+                    Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(entries),
+                    Err(e) => {
+                        return Err(anyhow::Error::new(e)
+                            .context(format!("无法打开日志文件: {}", self.path.display())));
+                    }
+                }
+            }
+            let file = self.file.as_mut().unwrap();
+
+            let meta = file.metadata()?;
+            // 先判断是否已轮转，之后旧文件不会再有新内容，读完即可切换
+            let rotated = fs::metadata(&self.path).map_or(true, |m| m.ino() != meta.ino());
+            if meta.len() < file.stream_position()? {
+                file.rewind()?;
+                self.pending.clear();
+            }
+            file.read_to_end(&mut self.pending)
+                .with_context(|| format!("无法读取日志文件: {}", self.path.display()))?;
+
+            let complete = self
+                .pending
+                .iter()
+                .rposition(|b| *b == b'\n')
+                .map_or(0, |i| i + 1);
+            let rest = self.pending.split_off(complete);
+            entries.extend(parse_entries(&String::from_utf8_lossy(&self.pending)));
+            self.pending = rest;
+
+            if !rotated {
+                return Ok(entries);
+            }
+            self.file = None;
+            self.pending.clear();
+        }
+    }
+}
+
+/// 输出容器日志
+///
+/// # 参数
+/// - `id`: 容器 ID
+/// - `follow`: 输出现有日志后继续跟踪新日志，直到容器退出
+/// - `since`: 只输出该时间之后的日志，格式见 `parse_since`
+/// - `tail`: 只输出现有日志的最后 N 行
+/// - `timestamps`: 在每行前输出 RFC3339 时间戳
+pub async fn show_logs(
+    id: &str,
+    follow: bool,
+    since: Option<&str>,
+    tail: Option<usize>,
+    timestamps: bool,
+    logger: &Logger,
+) -> Result<()> {
+    if !Path::new(state_base()).join(id).exists() {
+        return Err(anyhow!("容器 '{}' 不存在", id));
+    }
+
+    let path = log_path(id);
+    let files = log_files(&path);
+    if files.is_empty() {
+        return Err(anyhow!(
+            "容器 '{}' 没有日志（只记录 ctr run -d 启动的容器的输出）",
+            id
+        ));
+    }
+
+    slog::info!(logger, "读取容器日志"; "id" => id, "files" => files.len(), "follow" => follow);
+
+    let since = since.map(|s| parse_since(s, Utc::now())).transpose()?;
+    let keep = |entry: &LogEntry| {
+        since.is_none_or(|since| entry.timestamp().is_some_and(|time| time >= since))
+    };
+
+    // 已轮转的文件一次读完，当前文件由 LogTail 读取并记录位置
+    let mut entries = Vec::new();
+    for file in files.iter().filter(|f| **f != path) {
+        let content = fs::read_to_string(file)
+            .with_context(|| format!("无法读取日志文件: {}", file.display()))?;
+        entries.extend(parse_entries(&content));
+    }
+    let mut current = LogTail::new(&path);
+    entries.extend(current.read_new()?);
+
+    entries.retain(|e| keep(e));
+    if let Some(n) = tail {
+        entries.drain(..entries.len().saturating_sub(n));
+    }
+    if !print_entries(&entries, timestamps)? || !follow {
+        return Ok(());
+    }
+
+    loop {
+        // 先确认容器是否已结束，再读取最后的日志，避免漏掉退出前的输出
+        let finished = !container_active(id);
+
+        let entries: Vec<LogEntry> = current.read_new()?.into_iter().filter(keep).collect();
+        if !print_entries(&entries, timestamps)? || finished {
+            return Ok(());
+        }
+
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+}
+
+/// 容器是否仍可能产生日志：init 进程存活，或 shim 仍在写入剩余输出
+fn container_active(id: &str) -> bool {
+    match load_container_state(state_base(), id) {
+        Ok(state) => state.is_running() || state.supervisor_pid.is_some_and(is_process_running),
+        Err(_) => false,
+    }
+}
+
+/// 按输出流将日志写到标准输出或标准错误
+///
+/// # 返回
+/// 读取端已关闭（如通过管道交给 `head`）时返回 false
+fn print_entries(entries: &[LogEntry], timestamps: bool) -> Result<bool> {
+    let mut stdout = io::stdout().lock();
+    let mut stderr = io::stderr().lock();
+
+    for entry in entries {
+        let out: &mut dyn Write = if entry.stream == "stderr" {
+            &mut stderr
+        } else {
+            &mut stdout
+        };
+        let res = if timestamps {
+            write!(out, "{} {}", entry.time, entry.log)
+        } else {
+            out.write_all(entry.log.as_bytes())
+        };
+        match res {
+            Ok(()) => {}
+            Err(e) if e.kind() == io::ErrorKind::BrokenPipe => return Ok(false),
+            Err(e) => return Err(anyhow::Error::new(e).context("输出日志失败")),
+        }
+    }
+
+    match stdout.flush() {
+        Err(e) if e.kind() == io::ErrorKind::BrokenPipe => Ok(false),
+        res => res.map(|_| true).context("输出日志失败"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use celler::container_log::JsonFileLogger;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_log_opts() {
+        let opts = vec!["max-size=10m".to_string(), "max-file=3".to_string()];
+        assert_eq!(
+            parse_log_opts(&opts).unwrap(),
+            LogConfig {
+                max_size: Some(10 * 1024 * 1024),
+                max_file: 3,
+            }
+        );
+        assert_eq!(parse_log_opts(&[]).unwrap(), LogConfig::default());
+        assert_eq!(
+            parse_log_opts(&["max-size=-1".to_string()]).unwrap(),
+            LogConfig::default()
+        );
+
+        assert!(parse_log_opts(&["max-file=3".to_string()]).is_err());
+        assert!(parse_log_opts(&["max-file=0".to_string()]).is_err());
+        assert!(parse_log_opts(&["max-size=0".to_string()]).is_err());
+        assert!(parse_log_opts(&["compress=true".to_string()]).is_err());
+        assert!(parse_log_opts(&["max-size".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_parse_since() {
+        let now = DateTime::parse_from_rfc3339("2024-01-02T15:04:05Z")
+            .unwrap()
+            .with_timezone(&Utc);
+
+        assert_eq!(
+            parse_since("2024-01-02T15:00:00+08:00", now).unwrap(),
+            DateTime::parse_from_rfc3339("2024-01-02T07:00:00Z").unwrap()
+        );
+        assert_eq!(
+            parse_since("1704207845", now).unwrap().timestamp(),
+            1704207845
+        );
+        assert_eq!(
+            parse_since("10m", now).unwrap(),
+            now - Duration::minutes(10)
+        );
+        assert_eq!(
+            parse_since("1h30m", now).unwrap(),
+            now - Duration::minutes(90)
+        );
+        assert!(parse_since("10x", now).is_err());
+        assert!(parse_since("m", now).is_err());
+        assert!(parse_since("", now).is_err());
+    }
+
+    #[test]
+    fn test_log_tail_follow_rotation() {
+        let dir = std::env::temp_dir().join(format!("runcell-logs-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(LOG_FILE_NAME);
+        let config = LogConfig {
+            max_size: Some(200),
+            max_file: 2,
+        };
+        let logger = JsonFileLogger::open(&path, config).unwrap();
+        let mut writer = logger.writer("stdout");
+        let mut tail = LogTail::new(&path);
+
+        writeln!(writer, "line 0").unwrap();
+        let entries = tail.read_new().unwrap();
+        assert_eq!(entries.len(), 1);
+        assert!(tail.read_new().unwrap().is_empty());
+
+        // 跨越多次轮转后仍按顺序读到新增的记录
+        let mut logs = Vec::new();
+        for i in 1..10 {
+            writeln!(writer, "line {}", i).unwrap();
+            logs.extend(tail.read_new().unwrap().into_iter().map(|e| e.log));
+        }
+        let expected: Vec<String> = (1..10).map(|i| format!("line {}\n", i)).collect();
+        assert_eq!(logs, expected);
+        assert!(path.with_extension("log.1").exists());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
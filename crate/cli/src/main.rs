@@ -2,12 +2,30 @@
 //!
 //! 容器运行时命令行工具
 
-use anyhow::Result;
-use clap::{Parser, Subcommand};
+use anyhow::{Context, Result};
+use celler::container_log::LogConfig;
+use clap::{Args, Parser, Subcommand};
 use slog::{Drain, Logger, o};
 
+mod attach;
+mod config;
 mod container_cmd;
+mod features;
+mod image_cmd;
+mod image_config;
+mod json_log;
+mod logs;
+mod process_opts;
+mod ps;
+mod resource_opts;
+#[cfg(feature = "standard-oci-runtime")]
+mod runc_cmd;
+mod shim;
+mod signals;
+mod spec_opts;
+mod stats;
 mod storage_cmd;
+mod terminal;
 
 /// Runcell - 轻量级容器运行时
 #[derive(Parser)]
@@ -15,9 +33,22 @@ mod storage_cmd;
 #[command(about = "容器运行时工具", long_about = None)]
 struct Cli {
     /// 启用详细日志
-    #[arg(short, long)]
+    #[arg(short, long, visible_alias = "debug")]
     verbose: bool,
 
+    /// 运行时根目录，保存容器状态和 bundle（默认 root 用户为 /run/runcell，
+    /// 其他用户为 $XDG_RUNTIME_DIR/runcell，可用 RUNCELL_ROOT 环境变量或配置文件指定）
+    #[arg(long)]
+    root: Option<String>,
+
+    /// 将日志写入该文件（默认输出到终端）
+    #[arg(long)]
+    log: Option<String>,
+
+    /// 日志文件的格式
+    #[arg(long, default_value = "text", value_parser = ["text", "json"])]
+    log_format: String,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -28,26 +59,101 @@ enum Commands {
     #[command(subcommand)]
     Storage(StorageCommands),
 
+    /// 本地镜像管理命令
+    #[command(subcommand)]
+    Image(ImageCommands),
+
     /// 容器管理命令
     #[command(subcommand, visible_alias = "ctr")]
     Container(ContainerCommands),
+
+    /// 生成默认的 OCI 配置文件 config.json（类似 runc spec）
+    Spec {
+        /// Bundle 目录，config.json 写入其中
+        #[arg(short, long, default_value = ".")]
+        bundle: String,
+
+        /// 生成非 root 用户可运行的 rootless 配置
+        #[arg(long)]
+        rootless: bool,
+    },
+
+    /// 以 OCI features.json 格式输出运行时支持的特性（类似 runc features）
+    Features,
+
+    #[cfg(feature = "standard-oci-runtime")]
+    #[command(flatten)]
+    Runc(RuncCommands),
+
+    /// 容器监督进程（内部使用，由 create/run -d 启动）
+    #[command(hide = true)]
+    Shim {
+        /// 容器 ID
+        #[arg(long)]
+        id: String,
+
+        /// Bundle 目录（包含 config.json）
+        #[arg(long)]
+        bundle: String,
+
+        /// 向 CLI 报告创建结果的管道 fd
+        #[arg(long)]
+        sync_fd: i32,
+
+        /// 创建后立即启动容器（run -d），否则等待 start 命令
+        #[arg(long)]
+        start: bool,
+
+        /// 由 shim 持有容器的标准输入输出，并监听 attach socket
+        #[arg(long)]
+        attach: bool,
+
+        /// 接受 attach 客户端的输入（对应 run 的 -i）
+        #[arg(long, requires = "attach")]
+        stdin: bool,
+
+        /// 单个日志文件的最大字节数，未指定时不轮转
+        #[arg(long, requires = "attach")]
+        log_max_size: Option<u64>,
+
+        /// 最多保留的日志文件数
+        #[arg(long, requires = "attach", default_value_t = 1)]
+        log_max_file: u32,
+    },
+}
+
+impl Commands {
+    /// 是否使用运行时根目录或镜像目录
+    ///
+    /// `spec` 和 `features` 不访问容器状态和镜像，非 root 用户没有运行时目录时也可以执行。
+    fn uses_dirs(&self) -> bool {
+        !matches!(self, Commands::Spec { .. } | Commands::Features)
+    }
 }
 
 #[derive(Subcommand, Debug)]
 enum ContainerCommands {
-    /// 创建容器
+    /// 创建容器（init 进程阻塞在 exec.fifo 上，等待 start）
     Create {
         /// 容器 ID
         #[arg(short, long)]
         id: String,
 
-        /// Rootfs 路径
-        #[arg(short, long)]
-        rootfs: String,
+        /// Rootfs 路径（指定 --bundle 时覆盖 config.json 中的 root.path）
+        #[arg(short, long, required_unless_present = "bundle")]
+        rootfs: Option<String>,
 
-        /// Bundle 目录（可选，默认在 /tmp/runcell/bundles/{id}）
+        /// 使用 OCI bundle 目录中的 config.json 创建容器
         #[arg(short, long)]
         bundle: Option<String>,
+
+        /// 日志选项（max-size=10m、max-file=3，可重复）
+        #[arg(long = "log-opt")]
+        log_opt: Vec<String>,
+
+        /// 要执行的命令及其参数（放在最后）
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+        command: Vec<String>,
     },
 
     /// 运行容器（创建并启动）
@@ -56,9 +162,13 @@ enum ContainerCommands {
         #[arg(long)]
         id: String,
 
-        /// 镜像源（支持 file://, dir://, 或本地路径）
-        #[arg(short = 'm', long)]
-        image: String,
+        /// 镜像源（支持 file://, oci://, oci-archive://, docker-archive://, dir://, 或本地路径）
+        #[arg(short = 'm', long, required_unless_present = "bundle")]
+        image: Option<String>,
+
+        /// 使用 OCI bundle 目录中的 config.json 运行容器
+        #[arg(short, long)]
+        bundle: Option<String>,
 
         /// 分配伪终端（TTY）
         #[arg(short = 't', long)]
@@ -72,18 +182,114 @@ enum ContainerCommands {
         #[arg(short = 'd', long)]
         detach: bool,
 
-        /// 要执行的命令及其参数（放在最后）
+        /// 日志选项（max-size=10m、max-file=3，可重复）
+        #[arg(long = "log-opt")]
+        log_opt: Vec<String>,
+
+        #[command(flatten)]
+        opts: SpecOptions,
+
+        /// 要执行的命令及其参数（放在最后，替换镜像配置中的 Cmd）
         #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
         command: Vec<String>,
     },
 
-    /// 启动已创建的容器
+    /// 启动已创建的容器（解除 init 进程在 exec.fifo 上的阻塞）
     Start {
         /// 容器 ID
         #[arg(short, long)]
         id: String,
     },
 
+    /// 向容器发送信号
+    Kill {
+        /// 容器 ID
+        #[arg(short, long)]
+        id: String,
+
+        /// 信号名称或编号（如 TERM、SIGKILL、9）
+        #[arg(default_value = "SIGTERM")]
+        signal: String,
+
+        /// 向容器 cgroup 内的所有进程发送信号
+        #[arg(short, long)]
+        all: bool,
+    },
+
+    /// 停止容器（先发送 SIGTERM，超时后发送 SIGKILL）
+    Stop {
+        /// 容器 ID
+        #[arg(short, long)]
+        id: String,
+
+        /// 等待容器退出的秒数，超时后发送 SIGKILL
+        #[arg(short, long, default_value_t = 10)]
+        timeout: u64,
+    },
+
+    /// 暂停容器（冻结容器内所有进程）
+    Pause {
+        /// 容器 ID
+        #[arg(short, long)]
+        id: String,
+    },
+
+    /// 恢复已暂停的容器
+    Resume {
+        /// 容器 ID
+        #[arg(short, long)]
+        id: String,
+    },
+
+    /// 连接到后台运行容器的标准输入输出（ctr run -d 启动的容器）
+    Attach {
+        /// 容器 ID
+        #[arg(short, long)]
+        id: String,
+
+        /// 断开连接的按键序列（如 ctrl-p,ctrl-q），容器继续运行
+        #[arg(long, default_value = "ctrl-p,ctrl-q")]
+        detach_keys: String,
+
+        /// 只接收容器输出，不转发标准输入
+        #[arg(long)]
+        no_stdin: bool,
+    },
+
+    /// 查看容器的日志
+    Logs {
+        /// 容器 ID
+        #[arg(short, long)]
+        id: String,
+
+        /// 持续输出新日志，直到容器退出
+        #[arg(short, long)]
+        follow: bool,
+
+        /// 只显示该时间之后的日志（RFC3339 时间、Unix 时间戳或 10m 等相对时长）
+        #[arg(long)]
+        since: Option<String>,
+
+        /// 只显示最后 N 行
+        #[arg(short = 'n', long)]
+        tail: Option<usize>,
+
+        /// 显示每行日志的时间戳
+        #[arg(short, long)]
+        timestamps: bool,
+    },
+
+    /// 等待容器退出并输出其退出码
+    Wait {
+        /// 容器 ID
+        #[arg(short, long)]
+        id: String,
+
+        /// 等待条件：not-running（容器退出）或 removed（容器被删除）
+        #[arg(long, default_value = "not-running", value_parser = ["not-running", "removed"])]
+        condition: String,
+    },
+
     /// 删除容器
     #[command(visible_alias = "rm")]
     Delete {
@@ -104,6 +310,58 @@ enum ContainerCommands {
         all: bool,
     },
 
+    /// 列出容器内的进程
+    #[command(visible_alias = "top")]
+    Ps {
+        /// 容器 ID
+        #[arg(short, long)]
+        id: String,
+
+        /// 输出格式 (table, json)
+        #[arg(short, long, default_value = "table")]
+        format: String,
+    },
+
+    /// 显示容器资源使用统计（CPU、内存、块设备 I/O、进程数）
+    Stats {
+        /// 容器 ID（可多次指定），未指定时显示所有运行中的容器
+        #[arg(short, long, conflicts_with = "all")]
+        id: Vec<String>,
+
+        /// 显示所有容器（包括已停止的）
+        #[arg(short, long)]
+        all: bool,
+
+        /// 只输出一次结果，不持续刷新
+        #[arg(long)]
+        no_stream: bool,
+
+        /// 输出格式 (table, json)
+        #[arg(short, long, default_value = "table")]
+        format: String,
+    },
+
+    /// 以 JSON 显示容器的状态和保存的 OCI 配置
+    Inspect {
+        /// 容器 ID（可多次指定）
+        #[arg(short, long, required = true)]
+        id: Vec<String>,
+    },
+
+    /// 更新运行中容器的资源限制
+    Update {
+        /// 容器 ID
+        #[arg(short, long)]
+        id: String,
+
+        /// 从 JSON 文件读取 OCI LinuxResources（`-` 表示标准输入）
+        #[arg(short, long)]
+        resources: Option<String>,
+
+        #[command(flatten)]
+        opts: ResourceOptions,
+    },
+
     /// 在运行中的容器内执行命令
     Exec {
         /// 容器 ID
@@ -118,12 +376,256 @@ enum ContainerCommands {
         #[arg(short = 'i', long)]
         interactive: bool,
 
+        /// 后台运行，不等待命令结束
+        #[arg(short = 'd', long)]
+        detach: bool,
+
+        /// 从 JSON 文件加载完整的 OCI Process 配置（类似 runc exec -p）
+        #[arg(short = 'p', long)]
+        process: Option<String>,
+
+        #[command(flatten)]
+        opts: ProcessOptions,
+
         /// 要执行的命令及其参数（放在最后）
         #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
         command: Vec<String>,
     },
 }
 
+/// runc 兼容的命令
+///
+/// 参数与 runc 相同，containerd、podman、CRI-O 等可以把 runcell 当作 OCI 运行时调用。
+/// 容器由调用方监督：`create` 完成后 runcell 进程即退出，不启动 shim。
+#[cfg(feature = "standard-oci-runtime")]
+#[derive(Subcommand, Debug)]
+enum RuncCommands {
+    /// 创建容器（init 进程阻塞在 exec.fifo 上，等待 start）
+    Create {
+        /// 容器 ID
+        id: String,
+
+        /// Bundle 目录（包含 config.json）
+        #[arg(short, long, default_value = ".")]
+        bundle: String,
+
+        /// 将容器 init 进程的 PID 写入该文件
+        #[arg(long)]
+        pid_file: Option<String>,
+
+        /// 接收伪终端 master 的 unix socket（process.terminal 为 true 时必需）
+        #[arg(long)]
+        console_socket: Option<String>,
+
+        /// 不使用 pivot_root 切换根文件系统
+        #[arg(long)]
+        no_pivot: bool,
+
+        /// 不为容器创建新的 session keyring
+        #[arg(long)]
+        no_new_keyring: bool,
+    },
+
+    /// 启动已创建的容器
+    Start {
+        /// 容器 ID
+        id: String,
+    },
+
+    /// 以 OCI JSON 格式输出容器状态
+    State {
+        /// 容器 ID
+        id: String,
+    },
+
+    /// 向容器发送信号
+    Kill {
+        /// 向容器内的所有进程发送
+        #[arg(short, long)]
+        all: bool,
+
+        /// 容器 ID
+        id: String,
+
+        /// 信号名称或编号
+        #[arg(default_value = "SIGTERM")]
+        signal: String,
+    },
+
+    /// 删除容器
+    Delete {
+        /// 强制删除运行中的容器（先发送 SIGKILL）
+        #[arg(short, long)]
+        force: bool,
+
+        /// 容器 ID
+        id: String,
+    },
+
+    /// 在运行中的容器内执行进程
+    Exec {
+        /// 从 JSON 文件加载完整的 OCI Process 配置
+        #[arg(short, long)]
+        process: Option<String>,
+
+        /// 分配伪终端（需要 --console-socket）
+        #[arg(short, long)]
+        tty: bool,
+
+        /// 接收伪终端 master 的 unix socket
+        #[arg(long)]
+        console_socket: Option<String>,
+
+        /// 将进程的 PID 写入该文件
+        #[arg(long)]
+        pid_file: Option<String>,
+
+        /// 启动后立即返回，不等待进程结束
+        #[arg(short, long)]
+        detach: bool,
+
+        #[command(flatten)]
+        opts: ProcessOptions,
+
+        /// 容器 ID
+        id: String,
+
+        /// 要执行的命令及其参数
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+        command: Vec<String>,
+    },
+
+    /// 列出容器内的进程
+    Ps {
+        /// 输出格式：table 或 json（PID 列表）
+        #[arg(short, long, default_value = "table")]
+        format: String,
+
+        /// 容器 ID
+        id: String,
+    },
+
+    /// 输出容器的资源统计事件
+    Events {
+        /// 只输出一次统计数据
+        #[arg(long)]
+        stats: bool,
+
+        /// 统计间隔（如 5s、1m）
+        #[arg(long, default_value = "5s")]
+        interval: String,
+
+        /// 容器 ID
+        id: String,
+    },
+
+    /// 暂停容器内的所有进程
+    Pause {
+        /// 容器 ID
+        id: String,
+    },
+
+    /// 恢复已暂停的容器
+    Resume {
+        /// 容器 ID
+        id: String,
+    },
+}
+
+/// 容器进程配置选项（覆盖 OCI spec 中 process 的对应字段）
+#[derive(Args, Debug, Default, Clone)]
+struct ProcessOptions {
+    /// 运行用户，格式为 uid[:gid]（ctr run/exec 还可以使用 rootfs 中的用户名和组名）
+    #[arg(short = 'u', long)]
+    user: Option<String>,
+
+    /// 设置环境变量（KEY=VAL，可重复；只写 KEY 时取当前环境中的值）
+    #[arg(short = 'e', long = "env")]
+    env: Vec<String>,
+
+    /// 从文件读取环境变量（每行一个 KEY=VAL，可重复）
+    #[arg(long)]
+    env_file: Vec<String>,
+
+    /// 工作目录（容器内的绝对路径）
+    #[arg(short = 'w', long, alias = "cwd")]
+    workdir: Option<String>,
+
+    /// 添加 capability（如 NET_ADMIN，可重复，ALL 表示全部）
+    #[arg(long, alias = "cap")]
+    cap_add: Vec<String>,
+
+    /// 移除 capability（如 CHOWN，可重复，ALL 表示全部）
+    #[arg(long)]
+    cap_drop: Vec<String>,
+
+    /// 授予全部 capability
+    #[arg(long)]
+    privileged: bool,
+}
+
+/// 容器配置选项（在默认 spec 模板上生效）
+#[derive(Args, Debug, Default, Clone)]
+struct SpecOptions {
+    /// 绑定挂载宿主机目录（宿主机路径:容器路径[:ro|rw]，可重复）
+    #[arg(short = 'v', long)]
+    volume: Vec<String>,
+
+    /// 挂载（type=bind|tmpfs,source=...,target=...[,readonly]，可重复）
+    #[arg(long)]
+    mount: Vec<String>,
+
+    /// 挂载 tmpfs（容器路径[:选项]，如 /run:size=64m，可重复）
+    #[arg(long)]
+    tmpfs: Vec<String>,
+
+    /// 容器主机名
+    #[arg(long)]
+    hostname: Option<String>,
+
+    /// 以只读方式挂载根文件系统
+    #[arg(long)]
+    read_only: bool,
+
+    #[command(flatten)]
+    process: ProcessOptions,
+
+    #[command(flatten)]
+    resources: ResourceOptions,
+}
+
+/// 容器资源限制选项（覆盖 OCI spec 中 linux.resources 的对应字段）
+#[derive(Args, Debug, Default, Clone)]
+struct ResourceOptions {
+    /// 内存限制（如 512m、1g，单位 b/k/m/g/t）
+    #[arg(long)]
+    memory: Option<String>,
+
+    /// 内存与 swap 的总限制，-1 表示不限制 swap
+    #[arg(long, allow_negative_numbers = true)]
+    memory_swap: Option<String>,
+
+    /// 可使用的 CPU 核数（如 1.5）
+    #[arg(long)]
+    cpus: Option<f64>,
+
+    /// CPU 相对权重
+    #[arg(long)]
+    cpu_shares: Option<u64>,
+
+    /// 允许使用的 CPU（如 0-3、0,1）
+    #[arg(long)]
+    cpuset_cpus: Option<String>,
+
+    /// 最大进程数，0 或 -1 表示不限制
+    #[arg(long, allow_negative_numbers = true)]
+    pids_limit: Option<i64>,
+
+    /// 块设备 I/O 权重（10-1000）
+    #[arg(long)]
+    blkio_weight: Option<u16>,
+}
+
 #[derive(Subcommand, Debug)]
 enum StorageCommands {
     /// 测试绑定挂载
@@ -174,10 +676,66 @@ enum StorageCommands {
     },
 }
 
-fn setup_logger(verbose: bool) -> Logger {
-    let decorator = slog_term::TermDecorator::new().build();
-    let drain = slog_term::FullFormat::new(decorator).build().fuse();
-    let drain = slog_async::Async::new(drain).build().fuse();
+#[derive(Subcommand, Debug)]
+enum ImageCommands {
+    /// 列出本地镜像
+    #[command(visible_alias = "ls")]
+    List {
+        /// 输出格式 (table, json)
+        #[arg(short, long, default_value = "table")]
+        format: String,
+    },
+
+    /// 显示镜像的配置和镜像层
+    Inspect {
+        /// 镜像名称、镜像 ID（或其前缀）或 manifest 摘要
+        #[arg(required = true)]
+        images: Vec<String>,
+    },
+
+    /// 删除镜像（被容器使用的镜像不能删除）
+    #[command(visible_alias = "rm")]
+    Remove {
+        /// 镜像名称、镜像 ID（或其前缀）或 manifest 摘要
+        #[arg(required = true)]
+        images: Vec<String>,
+    },
+
+    /// 删除未被容器使用的镜像和镜像层
+    Prune {
+        /// 删除所有未使用的镜像，而不只是未命名的镜像
+        #[arg(short, long)]
+        all: bool,
+    },
+}
+
+/// 创建日志记录器
+///
+/// 未指定 `log` 时输出到终端；否则追加写入该文件，`log_format` 为 `json` 时
+/// 每条日志一行 JSON（与 runc 的 `--log-format json` 一致）。
+fn setup_logger(verbose: bool, log: Option<&str>, log_format: &str) -> Result<Logger> {
+    let drain = match log {
+        None => {
+            let decorator = slog_term::TermDecorator::new().build();
+            let drain = slog_term::FullFormat::new(decorator).build().fuse();
+            slog_async::Async::new(drain).build()
+        }
+        Some(path) => {
+            let file = std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .with_context(|| format!("无法打开日志文件: {}", path))?;
+            if log_format == "json" {
+                slog_async::Async::new(json_log::JsonDrain::new(file).fuse()).build()
+            } else {
+                let decorator = slog_term::PlainSyncDecorator::new(file);
+                let drain = slog_term::FullFormat::new(decorator).build().fuse();
+                slog_async::Async::new(drain).build()
+            }
+        }
+    };
+    let drain = drain.fuse();
 
     // 优先级：-v/--verbose 标志 > RUNCELL_LOG 环境变量 > 默认 warn
     let level = if verbose {
@@ -194,10 +752,10 @@ fn setup_logger(verbose: bool) -> Logger {
         }
     };
 
-    Logger::root(
+    Ok(Logger::root(
         drain.filter_level(level).fuse(),
         o!("version" => env!("CARGO_PKG_VERSION")),
-    )
+    ))
 }
 
 #[tokio::main]
@@ -212,18 +770,65 @@ async fn main() -> Result<()> {
 
     let cli = Cli::parse();
 
-    let logger = setup_logger(cli.verbose);
+    let logger = setup_logger(cli.verbose, cli.log.as_deref(), &cli.log_format)?;
+    if cli.command.uses_dirs() {
+        config::init(cli.root.as_deref())?;
+        // 目录属于其他用户或为符号链接时不能继续使用
+        config::create_dirs()?;
+    }
     let _guard = slog_scope::set_global_logger(logger.clone());
 
     slog::info!(logger, "Runcell starting"; "command" => format!("{:?}", cli.command));
 
-    match cli.command {
+    let ret = match cli.command {
         Commands::Storage(storage_cmd) => {
-            storage_cmd::handle_storage_command(storage_cmd, &logger).await?;
+            storage_cmd::handle_storage_command(storage_cmd, &logger).await
         }
+        Commands::Image(image_cmd) => image_cmd::handle_image_command(image_cmd, &logger),
         Commands::Container(container_cmd) => {
-            container_cmd::handle_container_command(container_cmd, &logger).await?;
+            container_cmd::handle_container_command(container_cmd, &logger).await
+        }
+        Commands::Spec { bundle, rootless } => spec_opts::generate_spec(&bundle, rootless, &logger),
+        Commands::Features => features::print_features(),
+        #[cfg(feature = "standard-oci-runtime")]
+        Commands::Runc(runc_cmd) => runc_cmd::handle_runc_command(runc_cmd, &logger).await,
+        Commands::Shim {
+            id,
+            bundle,
+            sync_fd,
+            start,
+            attach,
+            stdin,
+            log_max_size,
+            log_max_file,
+        } => {
+            let stdio = if attach {
+                let log = LogConfig {
+                    max_size: log_max_size,
+                    max_file: log_max_file,
+                };
+                shim::ShimStdio::Attach { stdin, log }
+            } else {
+                shim::ShimStdio::Inherit
+            };
+            shim::run_shim(&id, &bundle, sync_fd, start, stdio, &logger).await
+        }
+    };
+
+    if let Err(e) = ret {
+        // 容器进程的非零退出码原样作为 CLI 的退出码
+        if let Some(&shim::ExitError(code)) = e.downcast_ref::<shim::ExitError>() {
+            slog::info!(logger, "Command completed"; "exit_code" => code);
+            // process::exit 不会运行析构函数，先释放 logger 以刷新异步日志
+            drop(_guard);
+            drop(logger);
+            std::process::exit(code);
+        }
+        // 调用方（如 containerd）从日志文件中读取失败原因
+        if cli.log.is_some() {
+            slog::error!(logger, "{:#}", e);
         }
+        return Err(e);
     }
 
     slog::info!(logger, "Command completed successfully");
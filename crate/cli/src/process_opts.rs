@@ -0,0 +1,300 @@
+//! 容器进程配置选项
+//!
+//! 将命令行上的 `--user`、`-e`、`--env-file`、`--workdir`、`--cap-add`、
+//! `--cap-drop`、`--privileged` 应用到 OCI `Process` 上，供 exec 等命令共用。
+
+use std::{collections::HashSet, fs, path::Path, str::FromStr};
+
+use anyhow::{Context, Result, anyhow};
+use celler::capabilities::get_all_caps;
+use oci_spec::runtime::{Capability, LinuxCapabilities, Process};
+
+use crate::{ProcessOptions, image_config::resolve_user};
+
+/// 将命令行选项应用到 OCI Process
+///
+/// 未指定的选项保持 `process` 中原有的值。
+/// 环境变量按 `--env-file`、`-e` 的顺序合并，同名变量后者覆盖前者。
+///
+/// # 参数
+/// - `process`: 要修改的 OCI Process
+/// - `opts`: 命令行选项
+/// - `rootfs`: 容器 rootfs，给出时 `--user` 按其中的 `/etc/passwd`、`/etc/group` 解析，
+///   可以使用用户名和组名；否则只接受 `uid[:gid]`
+pub fn apply_process_options(
+    process: &mut Process,
+    opts: &ProcessOptions,
+    rootfs: Option<&Path>,
+) -> Result<()> {
+    if let Some(user) = &opts.user {
+        let (uid, gid, additional_gids) = match rootfs {
+            Some(rootfs) => {
+                let resolved = resolve_user(rootfs, user)
+                    .with_context(|| format!("无法解析用户 '{}'", user))?;
+                (resolved.uid, resolved.gid, resolved.additional_gids)
+            }
+            None => {
+                let (uid, gid) = parse_user(user)?;
+                (uid, gid, Vec::new())
+            }
+        };
+        let mut u = process.user().clone();
+        u.set_uid(uid);
+        u.set_gid(gid);
+        // 显式指定的用户不沿用原有的附加组（如镜像用户所属的组）
+        u.set_additional_gids((!additional_gids.is_empty()).then_some(additional_gids));
+        process.set_user(u);
+    }
+
+    if !opts.env_file.is_empty() || !opts.env.is_empty() {
+        let mut env = process.env().clone().unwrap_or_default();
+        for path in &opts.env_file {
+            for e in load_env_file(path)? {
+                merge_env(&mut env, &e);
+            }
+        }
+        for e in &opts.env {
+            merge_env(&mut env, e);
+        }
+        process.set_env(Some(env));
+    }
+
+    if let Some(workdir) = &opts.workdir {
+        if !workdir.starts_with('/') {
+            return Err(anyhow!("工作目录必须是绝对路径: {}", workdir));
+        }
+        process.set_cwd(workdir.into());
+    }
+
+    if opts.privileged || !opts.cap_add.is_empty() || !opts.cap_drop.is_empty() {
+        let mut caps = process.capabilities().clone().unwrap_or_default();
+        if opts.privileged {
+            grant_caps(&mut caps, &all_caps());
+        }
+        grant_caps(&mut caps, &parse_caps(&opts.cap_add)?);
+        revoke_caps(&mut caps, &parse_caps(&opts.cap_drop)?);
+        process.set_capabilities(Some(caps));
+    }
+
+    Ok(())
+}
+
+/// 解析 `uid[:gid]` 格式的用户，gid 缺省时为 0（与 runc exec 一致）
+fn parse_user(user: &str) -> Result<(u32, u32)> {
+    let (uid, gid) = match user.split_once(':') {
+        Some((uid, gid)) => (uid, Some(gid)),
+        None => (user, None),
+    };
+
+    let uid = uid
+        .parse::<u32>()
+        .with_context(|| format!("无效的用户 '{}'，格式应为 uid[:gid]", user))?;
+    let gid = match gid {
+        Some(gid) => gid
+            .parse::<u32>()
+            .with_context(|| format!("无效的用户 '{}'，格式应为 uid[:gid]", user))?,
+        None => 0,
+    };
+
+    Ok((uid, gid))
+}
+
+/// 读取环境变量文件
+///
+/// 每行一个 `KEY=VAL`，忽略空行和以 `#` 开头的注释行。
+fn load_env_file(path: &str) -> Result<Vec<String>> {
+    let content =
+        fs::read_to_string(path).with_context(|| format!("无法读取环境变量文件: {}", path))?;
+
+    Ok(content
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .map(String::from)
+        .collect())
+}
+
+/// 合并一个环境变量，同名变量被覆盖
+///
+/// 只写 `KEY` 时取当前进程环境中的值，当前环境中也不存在则忽略。
+pub fn merge_env(env: &mut Vec<String>, entry: &str) {
+    let entry = match entry.split_once('=') {
+        Some(_) => entry.to_string(),
+        None => match std::env::var(entry) {
+            Ok(val) => format!("{}={}", entry, val),
+            Err(_) => return,
+        },
+    };
+
+    let key = entry.split('=').next().unwrap_or_default();
+    let prefix = format!("{}=", key);
+    match env.iter_mut().find(|e| e.starts_with(&prefix)) {
+        Some(e) => *e = entry,
+        None => env.push(entry),
+    }
+}
+
+/// 解析 capability 名称列表
+///
+/// 大小写不敏感，`CAP_` 前缀可选，`ALL` 表示全部 capability。
+fn parse_caps(names: &[String]) -> Result<HashSet<Capability>> {
+    let mut caps = HashSet::new();
+    for name in names {
+        let upper = name.to_uppercase();
+        if upper == "ALL" {
+            caps.extend(all_caps());
+            continue;
+        }
+
+        let cap = Capability::from_str(upper.trim_start_matches("CAP_"))
+            .map_err(|_| anyhow!("未知的 capability: {}", name))?;
+        caps.insert(cap);
+    }
+
+    Ok(caps)
+}
+
+/// 当前内核支持的全部 capability
+fn all_caps() -> HashSet<Capability> {
+    get_all_caps()
+        .iter()
+        .filter_map(|c| Capability::from_str(c.to_string().trim_start_matches("CAP_")).ok())
+        .collect()
+}
+
+/// 将 capability 加入 bounding、effective、permitted 和 inheritable 集合
+fn grant_caps(caps: &mut LinuxCapabilities, add: &HashSet<Capability>) {
+    if add.is_empty() {
+        return;
+    }
+
+    let extend = |set: &Option<HashSet<Capability>>| {
+        let mut set = set.clone().unwrap_or_default();
+        set.extend(add.iter().copied());
+        Some(set)
+    };
+
+    caps.set_bounding(extend(caps.bounding()));
+    caps.set_effective(extend(caps.effective()));
+    caps.set_permitted(extend(caps.permitted()));
+    caps.set_inheritable(extend(caps.inheritable()));
+}
+
+/// 从所有 capability 集合中移除指定的 capability
+fn revoke_caps(caps: &mut LinuxCapabilities, remove: &HashSet<Capability>) {
+    if remove.is_empty() {
+        return;
+    }
+
+    let retain = |set: &Option<HashSet<Capability>>| {
+        set.clone()
+            .map(|set| set.difference(remove).copied().collect::<HashSet<_>>())
+    };
+
+    caps.set_bounding(retain(caps.bounding()));
+    caps.set_effective(retain(caps.effective()));
+    caps.set_permitted(retain(caps.permitted()));
+    caps.set_inheritable(retain(caps.inheritable()));
+    caps.set_ambient(retain(caps.ambient()));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_user() {
+        assert_eq!(parse_user("1000").unwrap(), (1000, 0));
+        assert_eq!(parse_user("1000:100").unwrap(), (1000, 100));
+        assert!(parse_user("nobody").is_err());
+        assert!(parse_user("1000:users").is_err());
+    }
+
+    #[test]
+    fn test_merge_env() {
+        let mut env = vec!["PATH=/bin".to_string(), "A=1".to_string()];
+        merge_env(&mut env, "A=2");
+        merge_env(&mut env, "B=3");
+        merge_env(&mut env, "RUNCELL_TEST_SURELY_UNSET_VAR");
+        assert_eq!(env, vec!["PATH=/bin", "A=2", "B=3"]);
+    }
+
+    #[test]
+    fn test_parse_caps() {
+        let caps = parse_caps(&["net_admin".to_string(), "CAP_SYS_ADMIN".to_string()]).unwrap();
+        assert!(caps.contains(&Capability::NetAdmin));
+        assert!(caps.contains(&Capability::SysAdmin));
+        assert!(parse_caps(&["NO_SUCH_CAP".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_apply_process_options() {
+        let mut process = Process::default();
+        let opts = ProcessOptions {
+            user: Some("1000:1000".to_string()),
+            env: vec!["FOO=bar".to_string()],
+            workdir: Some("/data".to_string()),
+            cap_add: vec!["NET_ADMIN".to_string()],
+            cap_drop: vec!["KILL".to_string()],
+            ..Default::default()
+        };
+        apply_process_options(&mut process, &opts, None).unwrap();
+
+        assert_eq!(process.user().uid(), 1000);
+        assert_eq!(process.user().gid(), 1000);
+        assert!(
+            process
+                .env()
+                .as_ref()
+                .unwrap()
+                .contains(&"FOO=bar".to_string())
+        );
+        assert_eq!(process.cwd().to_str(), Some("/data"));
+
+        let caps = process.capabilities().as_ref().unwrap();
+        let bounding = caps.bounding().as_ref().unwrap();
+        assert!(bounding.contains(&Capability::NetAdmin));
+        assert!(!bounding.contains(&Capability::Kill));
+
+        let opts = ProcessOptions {
+            workdir: Some("relative".to_string()),
+            ..Default::default()
+        };
+        assert!(apply_process_options(&mut process, &opts, None).is_err());
+    }
+
+    #[test]
+    fn test_apply_process_options_user_name() {
+        let rootfs =
+            std::env::temp_dir().join(format!("runcell-process-opts-{}", std::process::id()));
+        fs::create_dir_all(rootfs.join("etc")).unwrap();
+        fs::write(
+            rootfs.join("etc/passwd"),
+            "root:x:0:0:root:/root:/bin/sh\napp:x:1000:1000::/home/app:/bin/sh\n",
+        )
+        .unwrap();
+        fs::write(rootfs.join("etc/group"), "app:x:1000:\nwheel:x:10:app\n").unwrap();
+
+        let mut process = Process::default();
+        let opts = ProcessOptions {
+            user: Some("app".to_string()),
+            ..Default::default()
+        };
+        assert!(apply_process_options(&mut process, &opts, None).is_err());
+        apply_process_options(&mut process, &opts, Some(&rootfs)).unwrap();
+        assert_eq!(process.user().uid(), 1000);
+        assert_eq!(process.user().gid(), 1000);
+        assert_eq!(process.user().additional_gids(), &Some(vec![10]));
+
+        let opts = ProcessOptions {
+            user: Some("0:wheel".to_string()),
+            ..Default::default()
+        };
+        apply_process_options(&mut process, &opts, Some(&rootfs)).unwrap();
+        assert_eq!(process.user().uid(), 0);
+        assert_eq!(process.user().gid(), 10);
+        assert_eq!(process.user().additional_gids(), &None);
+
+        fs::remove_dir_all(&rootfs).unwrap();
+    }
+}
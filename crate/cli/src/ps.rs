@@ -0,0 +1,286 @@
+//! 容器内进程列表（`ctr ps` / `ctr top`）
+//!
+//! 通过 `LinuxContainer::pids` 获取容器内的进程，再从 `/proc/<pid>` 读取
+//! 每个进程的 namespace 内 PID、用户、状态、CPU 时间、RSS 和命令行。
+
+use std::fs;
+
+use anyhow::{Context, Result, anyhow};
+use nix::unistd::{Uid, User};
+use slog::Logger;
+
+use crate::{container_cmd::load_running_container, stats::format_binary_size};
+
+/// 从 `/proc` 读取的单个进程信息
+#[derive(Debug, Clone, PartialEq)]
+struct ProcessInfo {
+    /// 宿主机视角的 PID
+    pid: i32,
+    /// 容器 pid namespace 内的 PID（`/proc/<pid>/status` 中 NSpid 的最后一项）
+    ns_pid: i32,
+    /// 真实用户 ID
+    uid: u32,
+    /// 进程状态（R、S、D、Z、T 等）
+    state: String,
+    /// 用户态与内核态 CPU 时间之和（秒）
+    cpu_time: f64,
+    /// 常驻内存（字节）
+    rss: u64,
+    /// 命令行，内核线程或僵尸进程为 `[comm]`
+    command: Vec<String>,
+}
+
+/// 列出容器内的进程
+///
+/// # 参数
+/// - `id`: 容器 ID
+/// - `format`: 输出格式，`table` 或 `json`
+pub fn list_processes(id: &str, format: &str, logger: &Logger) -> Result<()> {
+    if format != "table" && format != "json" {
+        return Err(anyhow!("不支持的输出格式: {}（可选 table, json）", format));
+    }
+
+    slog::info!(logger, "列出容器进程"; "id" => id);
+
+    let container = load_running_container(id, logger)?;
+    let pids = container
+        .pids()
+        .with_context(|| format!("获取容器 '{}' 的进程列表失败", id))?;
+
+    let clock_ticks = clock_ticks();
+    let processes: Vec<ProcessInfo> = pids
+        .into_iter()
+        .filter_map(|pid| match read_process_info(pid, clock_ticks) {
+            Ok(info) => Some(info),
+            Err(e) => {
+                // 进程可能在读取期间退出
+                slog::debug!(logger, "读取进程信息失败";
+                    "pid" => pid, "error" => format!("{:?}", e));
+                None
+            }
+        })
+        .collect();
+
+    if format == "json" {
+        let json_output: Vec<_> = processes
+            .iter()
+            .map(|p| {
+                serde_json::json!({
+                    "pid": p.pid,
+                    "ns_pid": p.ns_pid,
+                    "uid": p.uid,
+                    "user": user_name(p.uid),
+                    "state": p.state,
+                    "cpu_time": p.cpu_time,
+                    "rss": p.rss,
+                    "command": p.command,
+                })
+            })
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&json_output)?);
+    } else {
+        println!(
+            "{:<8} {:<8} {:<12} {:<6} {:<10} {:<10} {}",
+            "PID", "CPID", "USER", "STATE", "TIME", "RSS", "COMMAND"
+        );
+        for p in &processes {
+            println!(
+                "{:<8} {:<8} {:<12} {:<6} {:<10} {:<10} {}",
+                p.pid,
+                p.ns_pid,
+                user_name(p.uid),
+                p.state,
+                format_cpu_time(p.cpu_time),
+                format_binary_size(p.rss),
+                p.command.join(" ")
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// 读取 `/proc/<pid>` 下的进程信息
+fn read_process_info(pid: i32, clock_ticks: f64) -> Result<ProcessInfo> {
+    let status = fs::read_to_string(format!("/proc/{}/status", pid))
+        .with_context(|| format!("无法读取 /proc/{}/status", pid))?;
+    let stat = fs::read_to_string(format!("/proc/{}/stat", pid))
+        .with_context(|| format!("无法读取 /proc/{}/stat", pid))?;
+    let cmdline = fs::read(format!("/proc/{}/cmdline", pid))
+        .with_context(|| format!("无法读取 /proc/{}/cmdline", pid))?;
+
+    let mut info = parse_status(pid, &status)?;
+    info.cpu_time = parse_cpu_ticks(&stat)? as f64 / clock_ticks;
+
+    let args = parse_cmdline(&cmdline);
+    if !args.is_empty() {
+        info.command = args;
+    }
+
+    Ok(info)
+}
+
+/// 解析 `/proc/<pid>/status`
+///
+/// 命令行先以 `[Name]` 占位，读取到 cmdline 后再替换。
+fn parse_status(pid: i32, content: &str) -> Result<ProcessInfo> {
+    let mut info = ProcessInfo {
+        pid,
+        ns_pid: pid,
+        uid: 0,
+        state: String::new(),
+        cpu_time: 0.0,
+        rss: 0,
+        command: Vec::new(),
+    };
+
+    for line in content.lines() {
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        match key {
+            "Name" => info.command = vec![format!("[{}]", value)],
+            "State" => info.state = value.split_whitespace().next().unwrap_or("?").to_string(),
+            "Uid" => {
+                info.uid = value
+                    .split_whitespace()
+                    .next()
+                    .and_then(|v| v.parse().ok())
+                    .ok_or_else(|| anyhow!("无效的 Uid 字段: {}", value))?;
+            }
+            // 嵌套 pid namespace 时依次列出各层 PID，最后一项为最内层
+            "NSpid" => {
+                if let Some(ns_pid) = value.split_whitespace().last().and_then(|v| v.parse().ok()) {
+                    info.ns_pid = ns_pid;
+                }
+            }
+            // 内核线程没有 VmRSS
+            "VmRSS" => {
+                let kb: u64 = value
+                    .trim_end_matches("kB")
+                    .trim()
+                    .parse()
+                    .with_context(|| format!("无效的 VmRSS 字段: {}", value))?;
+                info.rss = kb * 1024;
+            }
+            _ => {}
+        }
+    }
+
+    Ok(info)
+}
+
+/// 从 `/proc/<pid>/stat` 解析 utime + stime（时钟滴答数）
+fn parse_cpu_ticks(content: &str) -> Result<u64> {
+    // comm 字段可能包含空格和括号，从最后一个 ')' 之后开始解析
+    let rest = content
+        .rfind(')')
+        .map(|i| &content[i + 1..])
+        .ok_or_else(|| anyhow!("无效的 stat 格式"))?;
+    let fields: Vec<&str> = rest.split_whitespace().collect();
+
+    // fields[0] 为第 3 列 state，utime、stime 为第 14、15 列
+    let field = |idx: usize| -> Result<u64> {
+        fields
+            .get(idx)
+            .ok_or_else(|| anyhow!("stat 缺少第 {} 列", idx + 3))?
+            .parse::<u64>()
+            .with_context(|| format!("无效的 stat 第 {} 列", idx + 3))
+    };
+
+    Ok(field(11)? + field(12)?)
+}
+
+/// 解析以 NUL 分隔的 `/proc/<pid>/cmdline`
+fn parse_cmdline(content: &[u8]) -> Vec<String> {
+    content
+        .split(|b| *b == 0)
+        .filter(|arg| !arg.is_empty())
+        .map(|arg| String::from_utf8_lossy(arg).to_string())
+        .collect()
+}
+
+/// 每秒的时钟滴答数
+fn clock_ticks() -> f64 {
+    let ticks = unsafe { libc::sysconf(libc::_SC_CLK_TCK) };
+    if ticks > 0 { ticks as f64 } else { 100.0 }
+}
+
+/// 将 uid 解析为宿主机上的用户名，找不到时显示数字
+fn user_name(uid: u32) -> String {
+    match User::from_uid(Uid::from_raw(uid)) {
+        Ok(Some(user)) => user.name,
+        _ => uid.to_string(),
+    }
+}
+
+/// 按 `[DD-]HH:MM:SS` 格式化 CPU 时间（与 ps 一致）
+fn format_cpu_time(secs: f64) -> String {
+    let total = secs as u64;
+    let (days, rem) = (total / 86400, total % 86400);
+    let (hours, minutes, seconds) = (rem / 3600, rem % 3600 / 60, rem % 60);
+    if days > 0 {
+        format!("{}-{:02}:{:02}:{:02}", days, hours, minutes, seconds)
+    } else {
+        format!("{:02}:{:02}:{:02}", hours, minutes, seconds)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STATUS: &str = "Name:\tsleep\n\
+        State:\tS (sleeping)\n\
+        Tgid:\t4242\n\
+        Pid:\t4242\n\
+        Uid:\t1000\t1000\t1000\t1000\n\
+        Gid:\t1000\t1000\t1000\t1000\n\
+        NSpid:\t4242\t7\n\
+        VmRSS:\t    1536 kB\n";
+
+    #[test]
+    fn test_parse_status() {
+        let info = parse_status(4242, STATUS).unwrap();
+        assert_eq!(info.pid, 4242);
+        assert_eq!(info.ns_pid, 7);
+        assert_eq!(info.uid, 1000);
+        assert_eq!(info.state, "S");
+        assert_eq!(info.rss, 1536 * 1024);
+        assert_eq!(info.command, vec!["[sleep]"]);
+    }
+
+    #[test]
+    fn test_parse_cpu_ticks() {
+        let stat = "4242 (my (odd) proc) S 1 4242 4242 0 -1 4194560 100 0 0 0 \
+                    250 50 0 0 20 0 1 0 12345 1000 100";
+        assert_eq!(parse_cpu_ticks(stat).unwrap(), 300);
+        assert!(parse_cpu_ticks("garbage").is_err());
+    }
+
+    #[test]
+    fn test_parse_cmdline() {
+        assert_eq!(
+            parse_cmdline(b"/bin/sh\0-c\0sleep 10\0"),
+            vec!["/bin/sh", "-c", "sleep 10"]
+        );
+        assert!(parse_cmdline(b"").is_empty());
+    }
+
+    #[test]
+    fn test_read_process_info_self() {
+        let pid = nix::unistd::getpid().as_raw();
+        let info = read_process_info(pid, clock_ticks()).unwrap();
+        assert_eq!(info.pid, pid);
+        assert_eq!(info.uid, nix::unistd::getuid().as_raw());
+        assert!(!info.command.is_empty());
+    }
+
+    #[test]
+    fn test_format_cpu_time() {
+        assert_eq!(format_cpu_time(0.0), "00:00:00");
+        assert_eq!(format_cpu_time(3723.5), "01:02:03");
+        assert_eq!(format_cpu_time(90061.0), "1-01:01:01");
+    }
+}
@@ -0,0 +1,256 @@
+//! 容器资源限制选项
+//!
+//! 将命令行上的 `--memory`、`--memory-swap`、`--cpus`、`--cpu-shares`、`--cpuset-cpus`、
+//! `--pids-limit`、`--blkio-weight` 应用到 OCI `LinuxResources` 上，供 update 等命令共用。
+
+use std::{fs, io};
+
+use anyhow::{Context, Result, anyhow};
+use oci_spec::runtime::LinuxResources;
+
+use crate::ResourceOptions;
+
+/// `--cpus` 换算 CFS 配额时使用的默认周期（微秒）
+const DEFAULT_CPU_PERIOD: u64 = 100_000;
+
+impl ResourceOptions {
+    /// 是否未指定任何资源选项
+    pub fn is_empty(&self) -> bool {
+        self.memory.is_none()
+            && self.memory_swap.is_none()
+            && self.cpus.is_none()
+            && self.cpu_shares.is_none()
+            && self.cpuset_cpus.is_none()
+            && self.pids_limit.is_none()
+            && self.blkio_weight.is_none()
+    }
+}
+
+/// 将命令行选项应用到 OCI LinuxResources
+///
+/// 未指定的选项保持 `resources` 中原有的值。
+pub fn apply_resource_options(
+    resources: &mut LinuxResources,
+    opts: &ResourceOptions,
+) -> Result<()> {
+    if opts.memory.is_some() || opts.memory_swap.is_some() {
+        let mut memory = resources.memory().clone().unwrap_or_default();
+        if let Some(limit) = &opts.memory {
+            memory.set_limit(Some(parse_size(limit)?));
+        }
+        if let Some(swap) = &opts.memory_swap {
+            let swap = if swap == "-1" { -1 } else { parse_size(swap)? };
+            memory.set_swap(Some(swap));
+        }
+        if let (Some(limit), Some(swap)) = (memory.limit(), memory.swap())
+            && limit > 0
+            && swap > 0
+            && swap < limit
+        {
+            return Err(anyhow!("--memory-swap 不能小于 --memory"));
+        }
+        resources.set_memory(Some(memory));
+    }
+
+    if opts.cpus.is_some() || opts.cpu_shares.is_some() || opts.cpuset_cpus.is_some() {
+        let mut cpu = resources.cpu().clone().unwrap_or_default();
+        if let Some(cpus) = opts.cpus {
+            if cpus <= 0.0 {
+                return Err(anyhow!("无效的 --cpus: {}，必须大于 0", cpus));
+            }
+            let period = cpu.period().unwrap_or(DEFAULT_CPU_PERIOD);
+            cpu.set_period(Some(period));
+            cpu.set_quota(Some((cpus * period as f64) as i64));
+        }
+        if let Some(shares) = opts.cpu_shares {
+            cpu.set_shares(Some(shares));
+        }
+        if let Some(cpuset) = &opts.cpuset_cpus {
+            cpu.set_cpus(Some(cpuset.clone()));
+        }
+        resources.set_cpu(Some(cpu));
+    }
+
+    if let Some(limit) = opts.pids_limit {
+        let mut pids = resources.pids().clone().unwrap_or_default();
+        pids.set_limit(limit);
+        resources.set_pids(Some(pids));
+    }
+
+    if let Some(weight) = opts.blkio_weight {
+        if !(10..=1000).contains(&weight) {
+            return Err(anyhow!("无效的 --blkio-weight: {}，范围为 10-1000", weight));
+        }
+        let mut block_io = resources.block_io().clone().unwrap_or_default();
+        block_io.set_weight(Some(weight));
+        resources.set_block_io(Some(block_io));
+    }
+
+    Ok(())
+}
+
+/// 从 JSON 文件读取 OCI LinuxResources
+///
+/// `path` 为 `-` 时从标准输入读取。
+pub fn load_resources(path: &str) -> Result<LinuxResources> {
+    let content = if path == "-" {
+        io::read_to_string(io::stdin()).context("无法从标准输入读取资源配置")?
+    } else {
+        fs::read_to_string(path).with_context(|| format!("无法读取资源配置文件: {}", path))?
+    };
+
+    serde_json::from_str(&content).with_context(|| format!("无法解析资源配置: {}", path))
+}
+
+/// 将 `update` 中指定的资源合并到 `base`
+///
+/// 以 memory、cpu、pids、blockIO 等小节为单位替换，未出现的小节保持不变。
+pub fn merge_resources(base: &mut LinuxResources, update: LinuxResources) {
+    if let Some(memory) = update.memory().clone() {
+        base.set_memory(Some(memory));
+    }
+    if let Some(cpu) = update.cpu().clone() {
+        base.set_cpu(Some(cpu));
+    }
+    if let Some(pids) = update.pids().clone() {
+        base.set_pids(Some(pids));
+    }
+    if let Some(block_io) = update.block_io().clone() {
+        base.set_block_io(Some(block_io));
+    }
+    if let Some(hugepage_limits) = update.hugepage_limits().clone() {
+        base.set_hugepage_limits(Some(hugepage_limits));
+    }
+    if let Some(network) = update.network().clone() {
+        base.set_network(Some(network));
+    }
+}
+
+/// 解析带单位的字节数
+///
+/// 支持 `1024`、`512k`、`512m`、`1.5g`、`2GiB` 等形式，单位大小写不敏感，按 1024 进制换算。
+pub fn parse_size(s: &str) -> Result<i64> {
+    let lower = s.trim().to_lowercase();
+    let split = lower
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(lower.len());
+    let (num, unit) = lower.split_at(split);
+
+    let multiplier: u64 = match unit.trim() {
+        "" | "b" => 1,
+        "k" | "kb" | "kib" => 1 << 10,
+        "m" | "mb" | "mib" => 1 << 20,
+        "g" | "gb" | "gib" => 1 << 30,
+        "t" | "tb" | "tib" => 1 << 40,
+        _ => return Err(anyhow!("无效的大小 '{}'：未知单位 '{}'", s, unit)),
+    };
+
+    let value: f64 = num.parse().with_context(|| format!("无效的大小 '{}'", s))?;
+    let bytes = value * multiplier as f64;
+    if bytes > i64::MAX as f64 {
+        return Err(anyhow!("无效的大小 '{}'：超出范围", s));
+    }
+
+    Ok(bytes as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use oci_spec::runtime::{LinuxBlockIo, LinuxCpu, LinuxMemory, LinuxPids};
+
+    use super::*;
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("1024").unwrap(), 1024);
+        assert_eq!(parse_size("512k").unwrap(), 512 * 1024);
+        assert_eq!(parse_size("512M").unwrap(), 512 * 1024 * 1024);
+        assert_eq!(parse_size("1.5g").unwrap(), 1536 * 1024 * 1024);
+        assert_eq!(parse_size("2GiB").unwrap(), 2 * 1024 * 1024 * 1024);
+        assert!(parse_size("10x").is_err());
+        assert!(parse_size("m").is_err());
+    }
+
+    #[test]
+    fn test_apply_resource_options() {
+        let mut resources = LinuxResources::default();
+        let opts = ResourceOptions {
+            memory: Some("256m".to_string()),
+            memory_swap: Some("-1".to_string()),
+            cpus: Some(1.5),
+            cpuset_cpus: Some("0-1".to_string()),
+            pids_limit: Some(100),
+            blkio_weight: Some(500),
+            ..Default::default()
+        };
+        apply_resource_options(&mut resources, &opts).unwrap();
+
+        let memory = resources.memory().as_ref().unwrap();
+        assert_eq!(memory.limit(), Some(256 * 1024 * 1024));
+        assert_eq!(memory.swap(), Some(-1));
+
+        let cpu = resources.cpu().as_ref().unwrap();
+        assert_eq!(cpu.period(), Some(100_000));
+        assert_eq!(cpu.quota(), Some(150_000));
+        assert_eq!(cpu.cpus().as_deref(), Some("0-1"));
+
+        assert_eq!(resources.pids().as_ref().unwrap().limit(), 100);
+        assert_eq!(resources.block_io().as_ref().unwrap().weight(), Some(500));
+
+        // 未指定的选项保持不变
+        let opts = ResourceOptions {
+            cpu_shares: Some(512),
+            ..Default::default()
+        };
+        apply_resource_options(&mut resources, &opts).unwrap();
+        let cpu = resources.cpu().as_ref().unwrap();
+        assert_eq!(cpu.shares(), Some(512));
+        assert_eq!(cpu.quota(), Some(150_000));
+        assert_eq!(
+            resources.memory().as_ref().unwrap().limit(),
+            Some(256 * 1024 * 1024)
+        );
+    }
+
+    #[test]
+    fn test_apply_resource_options_invalid() {
+        let mut resources = LinuxResources::default();
+        let opts = ResourceOptions {
+            memory: Some("512m".to_string()),
+            memory_swap: Some("256m".to_string()),
+            ..Default::default()
+        };
+        assert!(apply_resource_options(&mut resources, &opts).is_err());
+
+        let opts = ResourceOptions {
+            blkio_weight: Some(5),
+            ..Default::default()
+        };
+        assert!(apply_resource_options(&mut resources, &opts).is_err());
+    }
+
+    #[test]
+    fn test_merge_resources() {
+        let mut base = LinuxResources::default();
+        let mut memory = LinuxMemory::default();
+        memory.set_limit(Some(1024));
+        base.set_memory(Some(memory));
+        let mut pids = LinuxPids::default();
+        pids.set_limit(10);
+        base.set_pids(Some(pids));
+
+        let mut update = LinuxResources::default();
+        let mut cpu = LinuxCpu::default();
+        cpu.set_shares(Some(256));
+        update.set_cpu(Some(cpu));
+        let mut block_io = LinuxBlockIo::default();
+        block_io.set_weight(Some(100));
+        update.set_block_io(Some(block_io));
+
+        merge_resources(&mut base, update);
+        assert_eq!(base.memory().as_ref().unwrap().limit(), Some(1024));
+        assert_eq!(base.pids().as_ref().unwrap().limit(), 10);
+        assert_eq!(base.cpu().as_ref().unwrap().shares(), Some(256));
+        assert_eq!(base.block_io().as_ref().unwrap().weight(), Some(100));
+    }
+}
@@ -0,0 +1,571 @@
+//! runc 兼容的命令实现
+//!
+//! 供 containerd、podman、CRI-O 等把 runcell 当作 OCI 运行时调用：
+//! - `create` 按 bundle 中的 config.json 创建容器，init 进程阻塞在 exec.fifo 上，
+//!   runcell 进程随即退出，容器由调用方（如 containerd-shim）监督；
+//! - 标准输入输出直接继承自调用方，`process.terminal` 为 true 时伪终端 master
+//!   通过 `--console-socket` 发送给调用方；
+//! - `delete` 只清理容器状态和 cgroup，rootfs 由调用方挂载和卸载。
+
+use std::{
+    fs,
+    io::{self, Write},
+    path::Path,
+    sync::{Arc, RwLock},
+};
+
+use anyhow::{Context, Result};
+use celler::{
+    cgroups::DevicesCgroupInfo,
+    container::{
+        BaseContainer, Container, LinuxContainer, is_init_process_alive, load_container_state,
+        save_container_state,
+    },
+    process::Process,
+};
+use protocols::agent::CgroupStats;
+use runtime_spec::ContainerState;
+use serde_json::{Value, json};
+use slog::Logger;
+
+use crate::{
+    ProcessOptions, RuncCommands,
+    config::state_base,
+    container_cmd::{self, create_opts, load_running_container, new_exec_id, new_init_process},
+    logs,
+    process_opts::apply_process_options,
+    ps, shim, signals, spec_opts,
+};
+
+/// 处理 runc 兼容命令
+pub async fn handle_runc_command(cmd: RuncCommands, logger: &Logger) -> Result<()> {
+    match cmd {
+        RuncCommands::Create {
+            id,
+            bundle,
+            pid_file,
+            console_socket,
+            no_pivot,
+            no_new_keyring,
+        } => {
+            create(
+                &id,
+                &bundle,
+                pid_file.as_deref(),
+                console_socket.as_deref(),
+                no_pivot,
+                no_new_keyring,
+                logger,
+            )
+            .await
+        }
+        RuncCommands::Start { id } => container_cmd::start_container(&id, logger).await,
+        RuncCommands::State { id } => state(&id, logger),
+        RuncCommands::Kill { all, id, signal } => {
+            container_cmd::kill_container(&id, &signal, all, logger)
+        }
+        RuncCommands::Delete { force, id } => delete(&id, force, logger).await,
+        RuncCommands::Exec {
+            process,
+            tty,
+            console_socket,
+            pid_file,
+            detach,
+            opts,
+            id,
+            command,
+        } => {
+            exec(
+                &id,
+                &command,
+                process.as_deref(),
+                tty,
+                console_socket.as_deref(),
+                pid_file.as_deref(),
+                detach,
+                &opts,
+                logger,
+            )
+            .await
+        }
+        RuncCommands::Ps { format, id } => list_pids(&id, &format, logger),
+        RuncCommands::Events {
+            stats,
+            interval,
+            id,
+        } => events(&id, stats, &interval, logger).await,
+        RuncCommands::Pause { id } => container_cmd::pause_container(&id, logger),
+        RuncCommands::Resume { id } => container_cmd::resume_container(&id, logger),
+    }
+}
+
+/// 创建容器（`runc create`）
+///
+/// 从 bundle 加载 config.json，在 `state_base()/{id}` 下创建容器并启动 init 进程，
+/// init 进程完成初始化后阻塞在 exec.fifo 上，等待 `start`。
+///
+/// # 参数
+/// - `bundle`: 包含 config.json 的 bundle 目录
+/// - `pid_file`: 写入 init 进程 PID 的文件
+/// - `console_socket`: 接收伪终端 master 的 unix socket，`process.terminal` 为 true 时必需
+/// - `no_pivot`: 使用 chroot 而不是 pivot_root 切换根文件系统
+/// - `no_new_keyring`: 不为容器创建新的 session keyring
+async fn create(
+    id: &str,
+    bundle: &str,
+    pid_file: Option<&str>,
+    console_socket: Option<&str>,
+    no_pivot: bool,
+    no_new_keyring: bool,
+    logger: &Logger,
+) -> Result<()> {
+    slog::info!(logger, "创建容器"; "id" => id, "bundle" => bundle);
+
+    if Path::new(state_base()).join(id).exists() {
+        return Err(anyhow::anyhow!("容器 '{}' 已存在", id));
+    }
+
+    let spec = spec_opts::load_bundle_spec(bundle)?;
+    let terminal = spec
+        .process()
+        .as_ref()
+        .and_then(|p| p.terminal())
+        .unwrap_or(false);
+    check_console_socket(terminal, console_socket)?;
+
+    let mut opts = create_opts(id, &spec);
+    opts.no_pivot_root = no_pivot;
+    opts.no_new_keyring = no_new_keyring;
+
+    let devcg_info = Some(Arc::new(RwLock::new(DevicesCgroupInfo::default())));
+    let mut container = LinuxContainer::new(id, state_base(), devcg_info, opts, logger)?;
+    if let Some(socket) = console_socket {
+        container.set_console_socket(&std::path::absolute(socket)?)?;
+    }
+
+    let process = new_init_process(id, &spec, logger)?;
+    if let Err(e) = container.start_container(process).await {
+        let _ = fs::remove_dir_all(&container.root);
+        return Err(e.context("创建容器进程失败"));
+    }
+
+    container.save_state().context("保存容器状态失败")?;
+
+    // bundle 以调用方传入的目录为准，rootfs 不一定位于 bundle 内
+    let mut state = load_container_state(state_base(), id)?;
+    state.bundle = fs::canonicalize(bundle)?.display().to_string();
+    save_container_state(state_base(), &state)?;
+
+    if let Some(path) = pid_file {
+        write_pid_file(path, container.init_process_pid)?;
+    }
+
+    slog::info!(logger, "容器已创建"; "id" => id, "pid" => container.init_process_pid);
+
+    Ok(())
+}
+
+/// 检查伪终端与 `--console-socket` 的组合是否有效
+fn check_console_socket(terminal: bool, console_socket: Option<&str>) -> Result<()> {
+    match (terminal, console_socket) {
+        (true, None) => Err(anyhow::anyhow!(
+            "process.terminal 为 true 时必须指定 --console-socket"
+        )),
+        (false, Some(_)) => Err(anyhow::anyhow!(
+            "只有 process.terminal 为 true 时才能指定 --console-socket"
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// 以 OCI 状态格式输出容器状态（`runc state`）
+///
+/// init 进程已退出的容器状态为 `stopped`，PID 为 0。
+fn state(id: &str, logger: &Logger) -> Result<()> {
+    let state_file =
+        load_container_state(state_base(), id).with_context(|| format!("容器 '{}' 不存在", id))?;
+    let container = LinuxContainer::load(id, state_base(), None, logger)
+        .with_context(|| format!("容器 '{}' 不存在", id))?;
+
+    let mut state = container.oci_state()?;
+    if !state_file.is_running() {
+        state.status = ContainerState::Stopped;
+        state.pid = 0;
+    }
+    state.bundle = state_file.bundle;
+
+    println!("{}", serde_json::to_string_pretty(&state)?);
+
+    Ok(())
+}
+
+/// 删除容器（`runc delete`）
+///
+/// 只有 Created 或已停止的容器可以直接删除，运行中的容器需要 `--force`，
+/// 此时先向 cgroup 内所有进程发送 SIGKILL。之后执行 poststop 钩子，
+/// 删除容器的 cgroup 和状态目录。
+///
+/// # 参数
+/// - `force`: 强制删除运行中的容器；容器不存在时也不报错
+async fn delete(id: &str, force: bool, logger: &Logger) -> Result<()> {
+    slog::info!(logger, "删除容器"; "id" => id, "force" => force);
+
+    if !Path::new(state_base()).join(id).exists() {
+        if force {
+            return Ok(());
+        }
+        return Err(anyhow::anyhow!("容器 '{}' 不存在", id));
+    }
+
+    let mut container = LinuxContainer::load(id, state_base(), None, logger)
+        .with_context(|| format!("加载容器 '{}' 失败", id))?;
+    let pid = container.init_process_pid;
+    let start_time = container.init_process_start_time;
+
+    if is_init_process_alive(pid, start_time) {
+        let status = container.status();
+        if status != ContainerState::Created && !force {
+            return Err(anyhow::anyhow!(
+                "容器 '{}' 当前状态为 {:?}，删除运行中的容器需要 --force",
+                id,
+                status
+            ));
+        }
+    }
+
+    container_cmd::destroy_container(&mut container, logger).await?;
+
+    fs::remove_dir_all(&container.root)
+        .with_context(|| format!("删除容器状态目录失败: {}", container.root))?;
+
+    slog::info!(logger, "容器删除完成"; "id" => id);
+
+    Ok(())
+}
+
+/// 在运行中的容器内执行进程（`runc exec`）
+///
+/// 与 `ctr exec` 不同，标准输入输出直接继承自调用方，伪终端 master 通过
+/// `--console-socket` 发送给调用方。
+///
+/// # 参数
+/// - `command`: 要执行的命令，为空时使用 `process_file` 或 init 进程的 args
+/// - `process_file`: 完整的 OCI Process JSON 文件
+/// - `tty`: 分配伪终端
+/// - `pid_file`: 写入进程 PID 的文件
+/// - `detach`: 启动后立即返回，不等待进程结束
+#[allow(clippy::too_many_arguments)]
+async fn exec(
+    id: &str,
+    command: &[String],
+    process_file: Option<&str>,
+    tty: bool,
+    console_socket: Option<&str>,
+    pid_file: Option<&str>,
+    detach: bool,
+    opts: &ProcessOptions,
+    logger: &Logger,
+) -> Result<()> {
+    slog::info!(logger, "在容器内执行命令";
+        "id" => id, "command" => format!("{:?}", command), "detach" => detach);
+
+    if !detach {
+        // exec 进程是中间进程 fork 出的孙进程，需要成为 subreaper 才能等待它
+        shim::set_child_subreaper()?;
+    }
+
+    let mut container = load_running_container(id, logger)?;
+    if container.status() == ContainerState::Paused {
+        return Err(anyhow::anyhow!("容器 '{}' 已暂停，无法执行命令", id));
+    }
+
+    let mut oci_process = match process_file {
+        Some(path) => {
+            let content = fs::read_to_string(path)
+                .with_context(|| format!("无法读取 process 配置: {}", path))?;
+            serde_json::from_str::<oci_spec::runtime::Process>(&content)
+                .with_context(|| format!("无法解析 process 配置: {}", path))?
+        }
+        None => container
+            .config
+            .spec
+            .as_ref()
+            .and_then(|spec| spec.process().clone())
+            .ok_or_else(|| anyhow::anyhow!("OCI spec 中缺少 process 配置"))?,
+    };
+
+    if !command.is_empty() {
+        oci_process.set_args(Some(command.to_vec()));
+    } else if process_file.is_none() {
+        return Err(anyhow::anyhow!("未指定要执行的命令"));
+    }
+    if tty || process_file.is_none() {
+        oci_process.set_terminal(Some(tty));
+    }
+    // 与 runc exec 一致，--user 只接受 uid[:gid]
+    apply_process_options(&mut oci_process, opts, None)?;
+
+    check_console_socket(oci_process.terminal().unwrap_or(false), console_socket)?;
+    if let Some(socket) = console_socket {
+        container.set_console_socket(&std::path::absolute(socket)?)?;
+    }
+
+    let exec_id = new_exec_id();
+    let process = Process::new(logger, &oci_process, &exec_id, false, 0, None)
+        .context("创建 Process 失败")?;
+    container
+        .start_container(process)
+        .await
+        .context("在容器内启动进程失败")?;
+
+    let pid = container
+        .processes
+        .get(&exec_id)
+        .map(|p| p.pid)
+        .ok_or_else(|| anyhow::anyhow!("找不到 exec 进程 '{}'", exec_id))?;
+
+    slog::info!(logger, "exec 进程已启动"; "exec_id" => &exec_id, "pid" => pid);
+
+    if let Some(path) = pid_file {
+        write_pid_file(path, pid)?;
+    }
+
+    if detach {
+        return Ok(());
+    }
+
+    let _signals = signals::forward_signals(pid, None, logger)?;
+    let status = shim::wait_process(pid).await?;
+    if status.code != 0 {
+        return Err(shim::ExitError(status.code).into());
+    }
+
+    Ok(())
+}
+
+/// 列出容器内的进程（`runc ps`）
+///
+/// `json` 格式与 runc 一致，输出 PID 数组；`table` 格式与 `ctr ps` 相同。
+fn list_pids(id: &str, format: &str, logger: &Logger) -> Result<()> {
+    match format {
+        "json" => {
+            let container = load_running_container(id, logger)?;
+            let pids = container
+                .pids()
+                .with_context(|| format!("获取容器 '{}' 的进程列表失败", id))?;
+            println!("{}", serde_json::to_string(&pids)?);
+            Ok(())
+        }
+        "table" => ps::list_processes(id, format, logger),
+        _ => Err(anyhow::anyhow!(
+            "不支持的输出格式: {}（可选 table, json）",
+            format
+        )),
+    }
+}
+
+/// 输出容器的资源统计事件（`runc events`）
+///
+/// 每隔 `interval` 输出一行 `{"type":"stats","id":...,"data":{...}}`，
+/// 容器停止后退出；`once` 为 true 时只输出一次。启用 mock-cgroup 时没有统计数据，直接报错。
+async fn events(id: &str, once: bool, interval: &str, logger: &Logger) -> Result<()> {
+    container_cmd::ensure_cgroup("输出资源统计事件")?;
+
+    let interval = logs::parse_duration(interval)
+        .and_then(|d| d.to_std().ok())
+        .ok_or_else(|| anyhow::anyhow!("无效的统计间隔: {}", interval))?;
+
+    loop {
+        let container = load_running_container(id, logger)?;
+        let resp = container
+            .stats()
+            .with_context(|| format!("获取容器 '{}' 的统计数据失败", id))?;
+        let event = stats_event(id, resp.cgroup_stats.get_or_default());
+
+        let mut stdout = io::stdout().lock();
+        writeln!(stdout, "{}", serde_json::to_string(&event)?)?;
+        stdout.flush()?;
+
+        if once {
+            return Ok(());
+        }
+
+        tokio::time::sleep(interval).await;
+        let state = load_container_state(state_base(), id)?;
+        if !state.is_running() {
+            return Ok(());
+        }
+    }
+}
+
+/// 把 cgroup 统计数据转换为 runc 的 stats 事件
+fn stats_event(id: &str, stats: &CgroupStats) -> Value {
+    let cpu = stats.cpu_stats.get_or_default();
+    let cpu_usage = cpu.cpu_usage.get_or_default();
+    let throttling = cpu.throttling_data.get_or_default();
+    let memory = stats.memory_stats.get_or_default();
+    let pids = stats.pids_stats.get_or_default();
+    let blkio = stats.blkio_stats.get_or_default();
+
+    let memory_entry = |data: &protocols::agent::MemoryData| {
+        json!({
+            "usage": data.usage,
+            "max": data.max_usage,
+            "failcnt": data.failcnt,
+            "limit": data.limit,
+        })
+    };
+    let blkio_entries = |entries: &[protocols::agent::BlkioStatsEntry]| {
+        entries
+            .iter()
+            .map(|e| {
+                json!({
+                    "major": e.major,
+                    "minor": e.minor,
+                    "op": e.op,
+                    "value": e.value,
+                })
+            })
+            .collect::<Vec<_>>()
+    };
+    let hugetlb: serde_json::Map<String, Value> = stats
+        .hugetlb_stats
+        .iter()
+        .map(|(size, h)| {
+            (
+                size.clone(),
+                json!({ "usage": h.usage, "max": h.max_usage, "failcnt": h.failcnt }),
+            )
+        })
+        .collect();
+
+    json!({
+        "type": "stats",
+        "id": id,
+        "data": {
+            "cpu": {
+                "usage": {
+                    "total": cpu_usage.total_usage,
+                    "percpu": cpu_usage.percpu_usage,
+                    "kernel": cpu_usage.usage_in_kernelmode,
+                    "user": cpu_usage.usage_in_usermode,
+                },
+                "throttling": {
+                    "periods": throttling.periods,
+                    "throttledPeriods": throttling.throttled_periods,
+                    "throttledTime": throttling.throttled_time,
+                },
+            },
+            "memory": {
+                "cache": memory.cache,
+                "usage": memory_entry(memory.usage.get_or_default()),
+                "swap": memory_entry(memory.swap_usage.get_or_default()),
+                "kernel": memory_entry(memory.kernel_usage.get_or_default()),
+                "raw": memory.stats,
+            },
+            "pids": {
+                "current": pids.current,
+                "limit": pids.limit,
+            },
+            "blkio": {
+                "ioServiceBytesRecursive": blkio_entries(&blkio.io_service_bytes_recursive),
+                "ioServicedRecursive": blkio_entries(&blkio.io_serviced_recursive),
+            },
+            "hugetlb": hugetlb,
+        },
+    })
+}
+
+/// 写入 PID 文件
+///
+/// 与 runc 一致，先写入同目录下的临时文件再重命名，调用方不会读到不完整的内容；
+/// 文件内容不带换行。
+fn write_pid_file(path: &str, pid: i32) -> Result<()> {
+    let path =
+        std::path::absolute(path).with_context(|| format!("无效的 PID 文件路径: {}", path))?;
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| anyhow::anyhow!("无效的 PID 文件路径: {}", path.display()))?;
+    let tmp_path = path.with_file_name(format!(".{}", file_name.to_string_lossy()));
+
+    fs::write(&tmp_path, pid.to_string())
+        .with_context(|| format!("写入 PID 文件失败: {}", tmp_path.display()))?;
+    fs::rename(&tmp_path, &path).with_context(|| format!("写入 PID 文件失败: {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use protocols::agent::{BlkioStats, BlkioStatsEntry, CpuStats, CpuUsage, PidsStats};
+
+    use super::*;
+
+    #[test]
+    fn test_check_console_socket() {
+        assert!(check_console_socket(false, None).is_ok());
+        assert!(check_console_socket(true, Some("/run/console.sock")).is_ok());
+        assert!(check_console_socket(true, None).is_err());
+        assert!(check_console_socket(false, Some("/run/console.sock")).is_err());
+    }
+
+    #[test]
+    fn test_write_pid_file() {
+        let dir = std::env::temp_dir().join(format!("runcell-pid-file-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("container.pid");
+
+        write_pid_file(path.to_str().unwrap(), 1234).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "1234");
+        assert!(!dir.join(".container.pid").exists());
+
+        // 已存在时覆盖
+        write_pid_file(path.to_str().unwrap(), 42).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "42");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_stats_event() {
+        let mut stats = CgroupStats::new();
+        stats.cpu_stats = Some(CpuStats {
+            cpu_usage: Some(CpuUsage {
+                total_usage: 300,
+                percpu_usage: vec![100, 200],
+                ..Default::default()
+            })
+            .into(),
+            ..Default::default()
+        })
+        .into();
+        stats.pids_stats = Some(PidsStats {
+            current: 3,
+            limit: 100,
+            ..Default::default()
+        })
+        .into();
+        stats.blkio_stats = Some(BlkioStats {
+            io_service_bytes_recursive: vec![BlkioStatsEntry {
+                major: 8,
+                minor: 0,
+                op: "Read".to_string(),
+                value: 4096,
+                ..Default::default()
+            }],
+            ..Default::default()
+        })
+        .into();
+
+        let event = stats_event("c1", &stats);
+        assert_eq!(event["type"], "stats");
+        assert_eq!(event["id"], "c1");
+        assert_eq!(event["data"]["cpu"]["usage"]["total"], 300);
+        assert_eq!(event["data"]["cpu"]["usage"]["percpu"], json!([100, 200]));
+        assert_eq!(event["data"]["pids"]["current"], 3);
+        assert_eq!(event["data"]["pids"]["limit"], 100);
+        assert_eq!(event["data"]["memory"]["usage"]["usage"], 0);
+        assert_eq!(
+            event["data"]["blkio"]["ioServiceBytesRecursive"][0],
+            json!({ "major": 8, "minor": 0, "op": "Read", "value": 4096 })
+        );
+    }
+}
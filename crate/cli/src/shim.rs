@@ -0,0 +1,368 @@
+//! 容器监督进程（shim）
+//!
+//! 后台容器（`ctr create`、`ctr run -d`）由一个常驻的 shim 进程负责：
+//! - 通过 `PR_SET_CHILD_SUBREAPER` 成为子进程收割者，容器 init 进程
+//!   （中间进程 double-fork 出的孙进程）会被重新挂到 shim 之下；
+//! - 持有容器的标准输入输出 / 伪终端 fd，将输出写入容器日志，并通过 attach socket
+//!   提供给 `ctr attach`；
+//! - 等待 init 进程退出，并把退出码、退出时间和信号写入 state.json。
+//!
+//! 前台运行的容器由 CLI 进程自身承担同样的职责。
+//!
+//! # 同步协议
+//! ```text
+//! CLI                          shim
+//!  |-- pipe() + spawn -------->  |
+//!  |                             |-- 创建容器
+//!  |<-- {"pid": N} 或 ----------|
+//!  |    {"error": "..."}         |
+//!  |   （关闭管道）              |-- waitpid 等待 init 退出
+//! 退出                           |-- 写入退出信息后退出
+//! ```
+
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader, Write},
+    os::unix::io::{FromRawFd, RawFd},
+    process::{Command, Stdio},
+    time::Duration,
+};
+
+use anyhow::{Context, Result, anyhow};
+use celler::{
+    container::{LinuxContainer, load_container_state, save_container_state},
+    container_log::{JsonFileLogger, LogConfig},
+};
+use nix::{
+    errno::Errno,
+    fcntl::{self, FcntlArg, FdFlag},
+    sys::wait::{self, WaitStatus},
+    unistd::{self, Pid},
+};
+use oci_spec::runtime::Spec;
+use slog::Logger;
+
+use crate::{
+    attach::{self, AttachIo, AttachServer},
+    config::{self, state_base},
+    container_cmd::{new_container, new_init_process},
+    logs,
+};
+
+/// 容器退出后等待剩余输出转发给 attach 客户端的最长时间
+const ATTACH_DRAIN_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// init 进程的退出信息
+#[derive(Debug, Clone, Copy)]
+pub struct ExitStatus {
+    /// 退出码（被信号终止时为 128 + 信号值）
+    pub code: i32,
+    /// 终止进程的信号值
+    pub signal: Option<i32>,
+}
+
+/// 容器进程以非零状态退出
+///
+/// 作为错误从命令中返回，`main` 据此以相同的退出码结束 CLI 进程，
+/// 而不是统一返回 1。
+#[derive(Debug, Clone, Copy)]
+pub struct ExitError(pub i32);
+
+impl std::fmt::Display for ExitError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "容器进程退出，退出码: {}", self.0)
+    }
+}
+
+impl std::error::Error for ExitError {}
+
+/// 容器标准输入输出的去向
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ShimStdio {
+    /// 与 `runc create` 一致，容器直接继承 CLI 的标准输入输出
+    Inherit,
+    /// shim 持有容器的标准输入输出，在状态目录中监听 attach socket，并将输出写入日志
+    Attach {
+        /// 是否接受 attach 客户端的输入（对应 `-i`）
+        stdin: bool,
+        /// 日志轮转配置
+        log: LogConfig,
+    },
+}
+
+/// 将当前进程设置为子进程收割者
+///
+/// 容器 init 进程是中间进程的子进程，中间进程退出后 init 会被重新挂到
+/// 最近的 subreaper 下，这样当前进程才能 `waitpid` 到它。
+pub fn set_child_subreaper() -> Result<()> {
+    let ret = unsafe { libc::prctl(libc::PR_SET_CHILD_SUBREAPER, 1, 0, 0, 0) };
+    Errno::result(ret).context("设置 PR_SET_CHILD_SUBREAPER 失败")?;
+    Ok(())
+}
+
+/// 启动容器的 shim 进程，并等待其报告创建结果
+///
+/// # 参数
+/// - `id`: 容器 ID
+/// - `bundle`: bundle 目录，shim 从 `{bundle}/config.json` 读取 spec
+/// - `start`: 创建后是否立即启动容器
+/// - `stdio`: 容器标准输入输出的去向，`Attach` 时 shim 自身的标准输入输出重定向到 /dev/null
+///
+/// # 返回
+/// 容器 init 进程的 PID
+pub fn spawn_shim(
+    id: &str,
+    bundle: &str,
+    start: bool,
+    stdio: ShimStdio,
+    logger: &Logger,
+) -> Result<i32> {
+    let (rfd, wfd) = unistd::pipe().context("创建同步管道失败")?;
+    // 读端只留给 CLI，写端需要被 shim 继承
+    fcntl::fcntl(rfd, FcntlArg::F_SETFD(FdFlag::FD_CLOEXEC))?;
+    let reader = unsafe { File::from_raw_fd(rfd) };
+
+    let mut cmd = Command::new(std::env::current_exe()?);
+    cmd.arg("--root")
+        .arg(config::root())
+        .arg("shim")
+        .arg("--id")
+        .arg(id)
+        .arg("--bundle")
+        .arg(bundle)
+        .arg("--sync-fd")
+        .arg(wfd.to_string());
+    if start {
+        cmd.arg("--start");
+    }
+    if let ShimStdio::Attach { stdin, log } = stdio {
+        cmd.arg("--attach")
+            .arg("--log-max-file")
+            .arg(log.max_file.to_string());
+        if let Some(max_size) = log.max_size {
+            cmd.arg("--log-max-size").arg(max_size.to_string());
+        }
+        if stdin {
+            cmd.arg("--stdin");
+        }
+        cmd.stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null());
+    }
+
+    let spawned = cmd.spawn();
+    let _ = unistd::close(wfd);
+    let child = spawned.context("启动 shim 进程失败")?;
+
+    slog::debug!(logger, "shim 进程已启动"; "id" => id, "shim_pid" => child.id());
+
+    let mut line = String::new();
+    BufReader::new(reader)
+        .read_line(&mut line)
+        .context("读取 shim 同步消息失败")?;
+
+    if line.trim().is_empty() {
+        return Err(anyhow!("shim 进程在创建容器前异常退出"));
+    }
+
+    let msg: serde_json::Value =
+        serde_json::from_str(line.trim()).context("解析 shim 同步消息失败")?;
+    if let Some(err) = msg.get("error").and_then(|e| e.as_str()) {
+        return Err(anyhow!("{}", err));
+    }
+
+    msg.get("pid")
+        .and_then(|p| p.as_i64())
+        .map(|p| p as i32)
+        .ok_or_else(|| anyhow!("无效的 shim 同步消息: {}", line.trim()))
+}
+
+/// shim 进程入口
+///
+/// 创建容器（`start` 为 true 时同时启动），通过同步管道报告结果，
+/// 然后一直等待 init 进程退出并记录退出信息。
+pub async fn run_shim(
+    id: &str,
+    bundle: &str,
+    sync_fd: RawFd,
+    start: bool,
+    stdio: ShimStdio,
+    logger: &Logger,
+) -> Result<()> {
+    // 避免容器进程继承同步管道，否则 CLI 读不到 EOF
+    fcntl::fcntl(sync_fd, FcntlArg::F_SETFD(FdFlag::FD_CLOEXEC))?;
+    let mut sync = unsafe { File::from_raw_fd(sync_fd) };
+
+    // 脱离 CLI 的会话，CLI 退出或终端关闭时 shim 不受影响
+    let _ = unistd::setsid();
+
+    let (pid, server) = match create_supervised(id, bundle, start, stdio, logger).await {
+        Ok(created) => created,
+        Err(e) => {
+            let msg = serde_json::json!({ "error": format!("{:#}", e) });
+            let _ = writeln!(sync, "{}", msg);
+            return Err(e);
+        }
+    };
+
+    writeln!(sync, "{}", serde_json::json!({ "pid": pid }))?;
+    drop(sync);
+
+    let status = wait_init_process(id, pid, logger).await?;
+    slog::info!(logger, "容器 init 进程已退出";
+        "id" => id, "code" => status.code, "signal" => status.signal);
+
+    if let Some(server) = server {
+        server.finish(ATTACH_DRAIN_TIMEOUT);
+    }
+
+    // 回收容器内剩余的孤儿进程（共享 pid namespace 时可能存在）
+    loop {
+        match wait::waitpid(Pid::from_raw(-1), None) {
+            Ok(_) | Err(Errno::EINTR) => continue,
+            Err(_) => break,
+        }
+    }
+
+    Ok(())
+}
+
+/// 在 shim 内创建容器并登记监督进程
+///
+/// # 返回
+/// init 进程的 PID，以及 `ShimStdio::Attach` 时的 attach 服务
+async fn create_supervised(
+    id: &str,
+    bundle: &str,
+    start: bool,
+    stdio: ShimStdio,
+    logger: &Logger,
+) -> Result<(i32, Option<AttachServer>)> {
+    set_child_subreaper()?;
+
+    let config_path = format!("{}/config.json", bundle);
+    let spec = Spec::load(&config_path).with_context(|| format!("无法加载 {}", config_path))?;
+
+    let mut container = new_container(id, &spec, logger)?;
+    let mut process = new_init_process(id, &spec, logger)?;
+    if stdio == ShimStdio::Inherit {
+        process.inherit_stdio().context("继承标准输入输出失败")?;
+    }
+
+    let ret = if start {
+        container.run_container(process).await
+    } else {
+        container.start_container(process).await
+    };
+    if let Err(e) = ret {
+        let _ = std::fs::remove_dir_all(&container.root);
+        return Err(e.context("创建容器进程失败"));
+    }
+
+    container.save_state().context("保存容器状态失败")?;
+
+    let server = match stdio {
+        ShimStdio::Inherit => {
+            drain_terminal(&container, id);
+            None
+        }
+        ShimStdio::Attach { stdin, log } => {
+            let process = container
+                .processes
+                .get(id)
+                .ok_or_else(|| anyhow!("找不到容器 init 进程"))?;
+            let io = AttachIo::from_process(process, stdin)?;
+            let log = JsonFileLogger::open(&logs::log_path(id), log)?;
+            Some(AttachServer::serve(
+                &attach::socket_path(id),
+                io,
+                log,
+                logger,
+            )?)
+        }
+    };
+    record_supervisor(id, unistd::getpid().as_raw())?;
+
+    Ok((container.init_process_pid, server))
+}
+
+/// 持续读取容器伪终端的输出，避免缓冲区写满后容器进程阻塞
+fn drain_terminal(container: &LinuxContainer, exec_id: &str) {
+    let Some(master) = container.processes.get(exec_id).and_then(|p| p.term_master) else {
+        return;
+    };
+
+    let Ok(fd) = unistd::dup(master) else {
+        return;
+    };
+
+    std::thread::spawn(move || {
+        let mut master = unsafe { File::from_raw_fd(fd) };
+        let _ = io::copy(&mut master, &mut io::stdout());
+    });
+}
+
+/// 在 state.json 中登记监督进程的 PID
+///
+/// 前台运行时由 CLI 进程自身调用。
+pub fn record_supervisor(id: &str, supervisor_pid: i32) -> Result<()> {
+    let mut state = load_container_state(state_base(), id)?;
+    state.supervisor_pid = Some(supervisor_pid);
+    save_container_state(state_base(), &state)
+}
+
+/// 等待容器 init 进程退出，并将退出信息写入 state.json
+///
+/// 调用方必须是 init 进程的父进程或 subreaper（见 `set_child_subreaper`），
+/// 期间回收到的其他子进程（如已退出的中间进程）会被忽略。
+pub async fn wait_init_process(id: &str, init_pid: i32, logger: &Logger) -> Result<ExitStatus> {
+    let status = wait_process(init_pid).await?;
+
+    match load_container_state(state_base(), id) {
+        Ok(mut state) => {
+            state.set_exited(status.code, status.signal);
+            save_container_state(state_base(), &state)?;
+        }
+        Err(e) => {
+            // 容器可能已被 delete 删除
+            slog::debug!(logger, "容器状态不存在，跳过记录退出信息";
+                "id" => id, "error" => format!("{:?}", e));
+        }
+    }
+
+    Ok(status)
+}
+
+/// 等待容器内的进程退出
+///
+/// 适用于 init 进程和 exec 进程：两者都是中间进程 fork 出的孙进程，
+/// 调用方必须先通过 `set_child_subreaper` 成为 subreaper。
+pub async fn wait_process(pid: i32) -> Result<ExitStatus> {
+    tokio::task::spawn_blocking(move || reap_process(pid))
+        .await
+        .context("等待容器进程的任务异常结束")?
+}
+
+fn reap_process(target: i32) -> Result<ExitStatus> {
+    loop {
+        match wait::waitpid(Pid::from_raw(-1), None) {
+            Ok(WaitStatus::Exited(pid, code)) if pid.as_raw() == target => {
+                return Ok(ExitStatus { code, signal: None });
+            }
+            Ok(WaitStatus::Signaled(pid, sig, _)) if pid.as_raw() == target => {
+                return Ok(ExitStatus {
+                    code: 128 + sig as i32,
+                    signal: Some(sig as i32),
+                });
+            }
+            Ok(_) | Err(Errno::EINTR) => continue,
+            Err(Errno::ECHILD) => {
+                return Err(anyhow!(
+                    "容器进程 (PID {}) 不是当前进程的子进程，无法等待其退出",
+                    target
+                ));
+            }
+            Err(e) => return Err(anyhow!(e).context("waitpid 失败")),
+        }
+    }
+}
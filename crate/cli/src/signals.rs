@@ -0,0 +1,233 @@
+//! 信号解析与转发
+
+use std::{os::unix::io::RawFd, str::FromStr};
+
+use anyhow::{Context, Result, anyhow};
+use nix::{errno::Errno, sys::signal::Signal};
+use slog::Logger;
+use tokio::{
+    signal::unix::{SignalKind, signal as signal_stream},
+    task::JoinHandle,
+};
+
+use crate::terminal;
+
+/// 前台运行时不转发给容器进程的信号
+///
+/// 与 runc 的信号代理一致，其余可捕获的信号（包括实时信号）都转发给容器进程：
+/// SIGCHLD 由监督进程自己处理；SIGWINCH 不转发，而是同步窗口大小到伪终端；
+/// SIGURG 被 Go 等运行时用于抢占调度，转发没有意义；SIGKILL、SIGSTOP 无法捕获；
+/// SIGSEGV 等同步产生的故障信号捕获后无法恢复执行，保持默认处理。
+const NOT_FORWARDED_SIGNALS: &[Signal] = &[
+    Signal::SIGCHLD,
+    Signal::SIGWINCH,
+    Signal::SIGURG,
+    Signal::SIGKILL,
+    Signal::SIGSTOP,
+    Signal::SIGSEGV,
+    Signal::SIGBUS,
+    Signal::SIGFPE,
+    Signal::SIGILL,
+    Signal::SIGTRAP,
+    Signal::SIGSYS,
+];
+
+/// 前台运行时转发给容器进程的信号编号
+fn forwarded_signals() -> Vec<i32> {
+    Signal::iterator()
+        .filter(|sig| !NOT_FORWARDED_SIGNALS.contains(sig))
+        .map(|sig| sig as i32)
+        .chain(libc::SIGRTMIN()..=libc::SIGRTMAX())
+        .collect()
+}
+
+/// 信号编号对应的名称，实时信号显示为 `SIGRTMIN+n`
+fn signal_name(sig: i32) -> String {
+    match Signal::try_from(sig) {
+        Ok(sig) => sig.as_str().to_string(),
+        Err(_) => format!("SIGRTMIN+{}", sig - libc::SIGRTMIN()),
+    }
+}
+
+/// 解析信号名称或编号
+///
+/// 支持 `9`、`KILL`、`SIGKILL`、`sigkill` 等形式。
+pub fn parse_signal(s: &str) -> Result<Signal> {
+    if let Ok(num) = s.parse::<i32>() {
+        return Signal::try_from(num).map_err(|_| anyhow!("无效的信号编号: {}", s));
+    }
+
+    let upper = s.to_uppercase();
+    let name = if upper.starts_with("SIG") {
+        upper
+    } else {
+        format!("SIG{}", upper)
+    };
+
+    Signal::from_str(&name).map_err(|_| anyhow!("无效的信号名称: {}", s))
+}
+
+/// 信号转发器，离开作用域时停止转发
+pub struct SignalForwarder {
+    tasks: Vec<JoinHandle<()>>,
+}
+
+impl Drop for SignalForwarder {
+    fn drop(&mut self) {
+        for task in &self.tasks {
+            task.abort();
+        }
+    }
+}
+
+/// 将当前进程收到的可捕获信号转发给容器进程（`NOT_FORWARDED_SIGNALS` 除外）
+///
+/// 前台运行的容器进程位于独立的会话中（使用伪终端时）或与 CLI 无关的进程树上，
+/// 用户对 CLI 发送的 SIGTERM 等信号必须显式转发才能到达容器。
+///
+/// # 参数
+/// - `pid`: 接收信号的容器进程（init 进程或 exec 进程）
+/// - `master`: 伪终端 master fd，指定时收到 SIGWINCH 后同步窗口大小
+pub fn forward_signals(
+    pid: i32,
+    master: Option<RawFd>,
+    logger: &Logger,
+) -> Result<SignalForwarder> {
+    let mut tasks = Vec::new();
+
+    for sig in forwarded_signals() {
+        let name = signal_name(sig);
+        let mut stream = signal_stream(SignalKind::from_raw(sig))
+            .with_context(|| format!("注册 {} 处理函数失败", name))?;
+        let logger = logger.clone();
+        tasks.push(tokio::spawn(async move {
+            while stream.recv().await.is_some() {
+                slog::debug!(logger, "转发信号"; "signal" => &name, "pid" => pid);
+                // 实时信号没有对应的 `Signal`，直接使用信号编号
+                match Errno::result(unsafe { libc::kill(pid, sig) }) {
+                    Ok(_) | Err(Errno::ESRCH) => {}
+                    Err(e) => {
+                        slog::warn!(logger, "转发信号失败";
+                            "signal" => &name, "pid" => pid, "error" => format!("{:?}", e));
+                    }
+                }
+            }
+        }));
+    }
+
+    if let Some(master) = master {
+        let mut stream =
+            signal_stream(SignalKind::window_change()).context("注册 SIGWINCH 处理函数失败")?;
+        let logger = logger.clone();
+        tasks.push(tokio::spawn(async move {
+            while stream.recv().await.is_some() {
+                if let Err(e) = terminal::resize_pty(master) {
+                    slog::debug!(logger, "同步终端窗口大小失败"; "error" => format!("{:?}", e));
+                }
+            }
+        }));
+    }
+
+    Ok(SignalForwarder { tasks })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{BufRead, BufReader},
+        os::unix::process::ExitStatusExt,
+        process::{Command, Stdio},
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_parse_signal() {
+        assert_eq!(parse_signal("9").unwrap(), Signal::SIGKILL);
+        assert_eq!(parse_signal("KILL").unwrap(), Signal::SIGKILL);
+        assert_eq!(parse_signal("SIGTERM").unwrap(), Signal::SIGTERM);
+        assert_eq!(parse_signal("hup").unwrap(), Signal::SIGHUP);
+        assert!(parse_signal("0").is_err());
+        assert!(parse_signal("NOSUCHSIG").is_err());
+    }
+
+    #[test]
+    fn test_forwarded_signals() {
+        let signals = forwarded_signals();
+        for sig in [
+            Signal::SIGCHLD,
+            Signal::SIGWINCH,
+            Signal::SIGURG,
+            Signal::SIGKILL,
+        ] {
+            assert!(!signals.contains(&(sig as i32)), "{} 不应转发", sig);
+        }
+        for sig in [
+            Signal::SIGTERM,
+            Signal::SIGPWR,
+            Signal::SIGCONT,
+            Signal::SIGTSTP,
+        ] {
+            assert!(signals.contains(&(sig as i32)), "{} 应当转发", sig);
+        }
+        assert!(signals.contains(&libc::SIGRTMAX()));
+        assert_eq!(signal_name(libc::SIGRTMIN() + 2), "SIGRTMIN+2");
+    }
+
+    /// 子进程模式的环境变量，设置时测试函数作为转发信号的一方运行
+    const FORWARD_CHILD_ENV: &str = "RUNCELL_TEST_FORWARD_CHILD";
+
+    #[test]
+    fn test_forward_signals() {
+        if std::env::var_os(FORWARD_CHILD_ENV).is_some() {
+            forward_signals_child();
+        }
+
+        // 在子进程中运行转发器，避免向测试进程自身发送信号
+        let mut child = Command::new(std::env::current_exe().unwrap())
+            .args([
+                "signals::tests::test_forward_signals",
+                "--exact",
+                "--nocapture",
+            ])
+            .env(FORWARD_CHILD_ENV, "1")
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let stdout = BufReader::new(child.stdout.take().unwrap());
+        for line in stdout.lines() {
+            // 测试框架的输出可能与 ready 位于同一行
+            if line.unwrap().ends_with("ready") {
+                break;
+            }
+        }
+
+        // 实时信号此前不在转发列表中，子进程的 sleep 应因此终止
+        let sig = libc::SIGRTMIN() + 1;
+        assert_eq!(unsafe { libc::kill(child.id() as i32, sig) }, 0);
+
+        let status = child.wait().unwrap();
+        assert_eq!(status.code(), Some(sig));
+    }
+
+    /// 启动 sleep 并把收到的信号转发给它，以 sleep 被终止的信号作为退出码
+    fn forward_signals_child() -> ! {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let code = runtime.block_on(async {
+            let logger = slog::Logger::root(slog::Discard, slog::o!());
+            let mut sleep = Command::new("sleep").arg("30").spawn().unwrap();
+            let _forwarder = forward_signals(sleep.id() as i32, None, &logger).unwrap();
+            println!("ready");
+
+            let status = tokio::task::spawn_blocking(move || sleep.wait())
+                .await
+                .unwrap()
+                .unwrap();
+            status.signal().unwrap_or(-1)
+        });
+        std::process::exit(code);
+    }
+}
@@ -0,0 +1,830 @@
+//! 容器 spec 模板与 run 配置选项
+//!
+//! [`default_spec`] 生成与 `runc spec` 相当的默认配置：完整的 namespace、
+//! `/proc`、`/dev`、`/sys` 等基础挂载、PATH 环境变量、默认 capability、
+//! rlimits 以及 masked/readonly paths。[`apply_spec_options`] 再把 `ctr run`
+//! 的 `-v`、`--mount`、`--tmpfs`、`--hostname`、`--read-only` 以及进程、资源选项
+//! 应用到该模板上。`runcell spec` 同样以该模板生成 config.json，
+//! [`make_rootless`] 负责 `--rootless` 的转换。
+
+use std::{fs, path::Path};
+
+use anyhow::{Context, Result, anyhow};
+use celler::container::DEFAULT_DEVICES;
+use nix::unistd::{getegid, geteuid};
+use oci_spec::runtime::{
+    LinuxDeviceCgroup, LinuxIdMapping, LinuxNamespace, LinuxNamespaceType, Mount, Spec,
+};
+use slog::Logger;
+
+use crate::{
+    SpecOptions,
+    process_opts::apply_process_options,
+    resource_opts::{apply_resource_options, parse_size},
+};
+
+/// 默认主机名
+pub const DEFAULT_HOSTNAME: &str = "runcell";
+
+/// 支持的挂载传播类型
+const PROPAGATIONS: &[&str] = &[
+    "private",
+    "rprivate",
+    "shared",
+    "rshared",
+    "slave",
+    "rslave",
+    "unbindable",
+    "runbindable",
+];
+
+/// `--tmpfs` 与 `--mount type=tmpfs` 的默认挂载选项
+const TMPFS_OPTIONS: &[&str] = &["nosuid", "nodev", "noexec"];
+
+/// 生成默认的 OCI spec
+///
+/// 以 oci-spec 的默认配置（与 `runc spec` 一致）为模板，替换 rootfs、
+/// 进程参数和终端设置。根文件系统默认可写，主机名为 [`DEFAULT_HOSTNAME`]。
+/// 与 `runc spec` 一样创建 network namespace，`ctr` 命令另见 [`share_host_network`]。
+/// 设备 cgroup 在拒绝全部设备之后放行 `DEFAULT_DEVICES`；这些设备节点由运行时
+/// 自动创建，因此不写入 `linux.devices`。
+///
+/// # 参数
+/// - `rootfs`: 根文件系统路径
+/// - `args`: 容器进程的命令及参数
+/// - `terminal`: 是否分配伪终端
+pub fn default_spec(rootfs: &str, args: &[String], terminal: bool) -> Spec {
+    let mut spec = Spec::default();
+    spec.set_hostname(Some(DEFAULT_HOSTNAME.to_string()));
+
+    let mut root = spec.root().clone().unwrap_or_default();
+    root.set_path(rootfs.into());
+    root.set_readonly(Some(false));
+    spec.set_root(Some(root));
+
+    let mut process = spec.process().clone().unwrap_or_default();
+    process.set_args(Some(args.to_vec()));
+    process.set_terminal(Some(terminal));
+    spec.set_process(Some(process));
+
+    if let Some(mut linux) = spec.linux().clone() {
+        let mut resources = linux.resources().clone().unwrap_or_default();
+        let mut devices = resources.devices().clone().unwrap_or_default();
+        devices.extend(DEFAULT_DEVICES.iter().map(|dev| {
+            let mut rule = LinuxDeviceCgroup::default();
+            rule.set_allow(true);
+            rule.set_typ(Some(dev.typ()));
+            rule.set_major(Some(dev.major()));
+            rule.set_minor(Some(dev.minor()));
+            rule.set_access(Some("rwm".to_string()));
+            rule
+        }));
+        resources.set_devices(Some(devices));
+        linux.set_resources(Some(resources));
+        spec.set_linux(Some(linux));
+    }
+
+    spec
+}
+
+/// 去掉 network namespace，使容器共享宿主机网络
+///
+/// runcell 不负责配置容器网络，`ctr run` 和 `ctr create` 在 [`default_spec`] 生成的
+/// 模板上调用此函数，否则容器内只有 loopback。
+pub fn share_host_network(spec: &mut Spec) {
+    let Some(mut linux) = spec.linux().clone() else {
+        return;
+    };
+    let namespaces = linux.namespaces().clone().map(|ns| {
+        ns.into_iter()
+            .filter(|ns| ns.typ() != LinuxNamespaceType::Network)
+            .collect()
+    });
+    linux.set_namespaces(namespaces);
+    spec.set_linux(Some(linux));
+}
+
+/// 将 spec 转换为 rootless 配置（与 `runc spec --rootless` 一致）
+///
+/// 1. 添加 user namespace，并把容器内的 root 映射为当前用户（euid/egid）
+/// 2. 去掉 `/sys` 下的挂载，改为只读递归绑定挂载宿主机的 `/sys`
+///    （非特权用户无法挂载 sysfs 和 cgroup）
+/// 3. 去掉挂载选项中的 `uid=`、`gid=`，这些 id 在映射中不存在
+/// 4. 去掉资源限制，非特权用户通常无法写 cgroup
+///
+/// 结果满足 celler `validator` 对 rootless 容器的检查。
+pub fn make_rootless(spec: &mut Spec, uid: u32, gid: u32) {
+    let mut linux = spec.linux().clone().unwrap_or_default();
+
+    let mut namespaces: Vec<LinuxNamespace> = linux
+        .namespaces()
+        .clone()
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|ns| {
+            !matches!(
+                ns.typ(),
+                LinuxNamespaceType::Network | LinuxNamespaceType::User
+            )
+        })
+        .collect();
+    let mut userns = LinuxNamespace::default();
+    userns.set_typ(LinuxNamespaceType::User);
+    namespaces.push(userns);
+    linux.set_namespaces(Some(namespaces));
+
+    let id_mapping = |host_id: u32| {
+        let mut mapping = LinuxIdMapping::default();
+        mapping.set_container_id(0);
+        mapping.set_host_id(host_id);
+        mapping.set_size(1);
+        vec![mapping]
+    };
+    linux.set_uid_mappings(Some(id_mapping(uid)));
+    linux.set_gid_mappings(Some(id_mapping(gid)));
+    linux.set_resources(None);
+    spec.set_linux(Some(linux));
+
+    let mut mounts: Vec<Mount> = spec
+        .mounts()
+        .clone()
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|m| !m.destination().starts_with("/sys"))
+        .map(|mut m| {
+            let options = m.options().clone().map(|o| {
+                o.into_iter()
+                    .filter(|o| !o.starts_with("uid=") && !o.starts_with("gid="))
+                    .collect()
+            });
+            m.set_options(options);
+            m
+        })
+        .collect();
+
+    let mut sys = Mount::default();
+    sys.set_destination("/sys".into());
+    sys.set_typ(Some("none".to_string()));
+    sys.set_source(Some("/sys".into()));
+    sys.set_options(Some(
+        ["rbind", "nosuid", "noexec", "nodev", "ro"]
+            .iter()
+            .map(|o| o.to_string())
+            .collect(),
+    ));
+    mounts.push(sys);
+    spec.set_mounts(Some(mounts));
+}
+
+/// 在 bundle 目录下生成默认的 config.json（runcell spec）
+///
+/// 与 `runc spec` 一样，rootfs 为 bundle 下的 `rootfs` 目录，进程为带终端的 `sh`。
+/// config.json 已存在时报错，避免覆盖用户的配置。
+///
+/// # 参数
+/// - `bundle`: bundle 目录
+/// - `rootless`: 是否生成当前用户可运行的 rootless 配置
+pub fn generate_spec(bundle: &str, rootless: bool, logger: &Logger) -> Result<()> {
+    let config_path = Path::new(bundle).join("config.json");
+    if config_path.exists() {
+        return Err(anyhow!(
+            "{} 已存在，请先删除后再生成",
+            config_path.display()
+        ));
+    }
+
+    let mut spec = default_spec("rootfs", &["sh".to_string()], true);
+    if rootless {
+        make_rootless(&mut spec, geteuid().as_raw(), getegid().as_raw());
+    }
+
+    fs::create_dir_all(bundle).with_context(|| format!("无法创建 bundle 目录: {}", bundle))?;
+    spec.save(&config_path)
+        .with_context(|| format!("无法保存 {}", config_path.display()))?;
+
+    slog::info!(logger, "已生成 OCI 配置"; "config" => config_path.display().to_string(),
+        "rootless" => rootless);
+
+    Ok(())
+}
+
+/// 将 `ctr run` 的命令行选项应用到 spec
+///
+/// 只修改命令行上显式指定的部分，未指定任何选项时 spec 保持不变。
+/// 用户指定的挂载追加在模板挂载之后，目标路径相同的挂载会替换原有挂载。
+/// `--privileged` 除授予全部 capability 外，还会取消 masked/readonly paths，
+/// 并以读写方式挂载 `/sys` 和 cgroup。
+pub fn apply_spec_options(spec: &mut Spec, opts: &SpecOptions) -> Result<()> {
+    if let Some(hostname) = &opts.hostname {
+        if hostname.is_empty() {
+            return Err(anyhow!("主机名不能为空"));
+        }
+        spec.set_hostname(Some(hostname.clone()));
+    }
+
+    if opts.read_only {
+        let mut root = spec
+            .root()
+            .clone()
+            .ok_or_else(|| anyhow!("spec 中缺少 root"))?;
+        root.set_readonly(Some(true));
+        spec.set_root(Some(root));
+    }
+
+    // 相对的 root.path 依赖 bundle 目录，此时 --user 只接受 uid[:gid]
+    let rootfs = spec
+        .root()
+        .as_ref()
+        .map(|root| root.path().clone())
+        .filter(|path| path.is_absolute());
+    let mut process = spec
+        .process()
+        .clone()
+        .ok_or_else(|| anyhow!("spec 中缺少 process"))?;
+    apply_process_options(&mut process, &opts.process, rootfs.as_deref())?;
+    spec.set_process(Some(process));
+
+    if !opts.resources.is_empty() || opts.process.privileged {
+        let mut linux = spec.linux().clone().unwrap_or_default();
+        if !opts.resources.is_empty() {
+            let mut resources = linux.resources().clone().unwrap_or_default();
+            apply_resource_options(&mut resources, &opts.resources)?;
+            linux.set_resources(Some(resources));
+        }
+        if opts.process.privileged {
+            linux.set_masked_paths(None);
+            linux.set_readonly_paths(None);
+        }
+        spec.set_linux(Some(linux));
+    }
+
+    if !opts.process.privileged
+        && opts.volume.is_empty()
+        && opts.mount.is_empty()
+        && opts.tmpfs.is_empty()
+    {
+        return Ok(());
+    }
+
+    let mut mounts = spec.mounts().clone().unwrap_or_default();
+    if opts.process.privileged {
+        for m in mounts.iter_mut() {
+            if matches!(m.typ().as_deref(), Some("sysfs") | Some("cgroup")) {
+                let options = m.options().clone().map(|o| {
+                    o.into_iter()
+                        .map(|o| if o == "ro" { "rw".to_string() } else { o })
+                        .collect()
+                });
+                m.set_options(options);
+            }
+        }
+    }
+    for v in &opts.volume {
+        add_mount(&mut mounts, parse_volume(v)?);
+    }
+    for m in &opts.mount {
+        add_mount(&mut mounts, parse_mount(m)?);
+    }
+    for t in &opts.tmpfs {
+        add_mount(&mut mounts, parse_tmpfs(t)?);
+    }
+    spec.set_mounts(Some(mounts));
+
+    Ok(())
+}
+
+/// 加载 bundle 目录中的 config.json
+///
+/// 相对的 `root.path` 按 bundle 目录解析为绝对路径，因此返回的 spec
+/// 可以保存到 bundle 以外的位置使用。
+///
+/// # 参数
+/// - `bundle`: bundle 目录
+pub fn load_bundle_spec(bundle: &str) -> Result<Spec> {
+    let bundle =
+        fs::canonicalize(bundle).with_context(|| format!("无效的 bundle 目录: {}", bundle))?;
+    let config_path = bundle.join("config.json");
+    let mut spec =
+        Spec::load(&config_path).with_context(|| format!("无法加载 {}", config_path.display()))?;
+
+    let mut root = spec
+        .root()
+        .clone()
+        .ok_or_else(|| anyhow!("{} 中缺少 root", config_path.display()))?;
+    if root.path().is_relative() {
+        root.set_path(bundle.join(root.path()));
+        spec.set_root(Some(root));
+    }
+
+    Ok(spec)
+}
+
+/// 用命令行显式指定的 rootfs、命令和终端设置覆盖 spec
+///
+/// 参数为 `None` 时保持 spec 中原有的值。
+pub fn override_spec(
+    spec: &mut Spec,
+    rootfs: Option<&str>,
+    args: Option<&[String]>,
+    terminal: Option<bool>,
+) -> Result<()> {
+    if let Some(rootfs) = rootfs {
+        let mut root = spec.root().clone().unwrap_or_default();
+        root.set_path(rootfs.into());
+        spec.set_root(Some(root));
+    }
+
+    if args.is_some() || terminal.is_some() {
+        let mut process = spec
+            .process()
+            .clone()
+            .ok_or_else(|| anyhow!("spec 中缺少 process"))?;
+        if let Some(args) = args {
+            process.set_args(Some(args.to_vec()));
+        }
+        if let Some(terminal) = terminal {
+            process.set_terminal(Some(terminal));
+        }
+        spec.set_process(Some(process));
+    }
+
+    Ok(())
+}
+
+/// 添加挂载，目标路径相同的已有挂载被替换
+fn add_mount(mounts: &mut Vec<Mount>, mount: Mount) {
+    mounts.retain(|m| m.destination() != mount.destination());
+    mounts.push(mount);
+}
+
+/// 解析 `-v 宿主机路径:容器路径[:选项]`
+///
+/// 选项以逗号分隔，可为 `ro`、`rw` 或挂载传播类型（如 `rshared`）。
+fn parse_volume(volume: &str) -> Result<Mount> {
+    let parts: Vec<&str> = volume.split(':').collect();
+    let (source, destination, mode) = match parts.as_slice() {
+        [source, destination] => (*source, *destination, None),
+        [source, destination, mode] => (*source, *destination, Some(*mode)),
+        _ => {
+            return Err(anyhow!(
+                "无效的卷 '{}'，格式应为 宿主机路径:容器路径[:ro|rw]",
+                volume
+            ));
+        }
+    };
+
+    let mut readonly = false;
+    let mut propagation = None;
+    for opt in mode.into_iter().flat_map(|m| m.split(',')) {
+        match opt {
+            "ro" => readonly = true,
+            "rw" => readonly = false,
+            p if PROPAGATIONS.contains(&p) => propagation = Some(p),
+            _ => return Err(anyhow!("无效的卷选项 '{}'：{}", volume, opt)),
+        }
+    }
+
+    bind_mount(source, destination, readonly, propagation)
+}
+
+/// 解析 `--mount type=bind|tmpfs,source=...,target=...[,readonly][,...]`
+fn parse_mount(spec: &str) -> Result<Mount> {
+    let mut typ = None;
+    let mut source = None;
+    let mut target = None;
+    let mut readonly = false;
+    let mut propagation = None;
+    let mut tmpfs_size = None;
+    let mut tmpfs_mode = None;
+
+    for field in spec.split(',').filter(|f| !f.is_empty()) {
+        let (key, value) = match field.split_once('=') {
+            Some((key, value)) => (key, Some(value)),
+            None => (field, None),
+        };
+
+        match (key, value) {
+            ("type", Some(v)) => typ = Some(v),
+            ("source" | "src", Some(v)) => source = Some(v),
+            ("target" | "destination" | "dst", Some(v)) => target = Some(v),
+            ("readonly" | "ro", None) => readonly = true,
+            ("readonly" | "ro", Some(v)) => readonly = parse_bool(v)?,
+            ("bind-propagation", Some(v)) => {
+                if !PROPAGATIONS.contains(&v) {
+                    return Err(anyhow!("无效的挂载传播类型: {}", v));
+                }
+                propagation = Some(v);
+            }
+            ("tmpfs-size", Some(v)) => tmpfs_size = Some(parse_size(v)?),
+            ("tmpfs-mode", Some(v)) => {
+                tmpfs_mode = Some(
+                    u32::from_str_radix(v, 8)
+                        .with_context(|| format!("无效的 tmpfs-mode '{}'，应为八进制数", v))?,
+                )
+            }
+            _ => return Err(anyhow!("无效的挂载字段 '{}'", field)),
+        }
+    }
+
+    let target = target.ok_or_else(|| anyhow!("挂载 '{}' 缺少 target", spec))?;
+    match typ {
+        Some("bind") => {
+            let source = source.ok_or_else(|| anyhow!("bind 挂载 '{}' 缺少 source", spec))?;
+            if tmpfs_size.is_some() || tmpfs_mode.is_some() {
+                return Err(anyhow!("tmpfs-size/tmpfs-mode 只能用于 tmpfs 挂载"));
+            }
+            bind_mount(source, target, readonly, propagation)
+        }
+        Some("tmpfs") => {
+            if source.is_some() || propagation.is_some() {
+                return Err(anyhow!("tmpfs 挂载不支持 source 和 bind-propagation"));
+            }
+            let mut options: Vec<String> = Vec::new();
+            if readonly {
+                options.push("ro".to_string());
+            }
+            if let Some(size) = tmpfs_size {
+                options.push(format!("size={}", size));
+            }
+            if let Some(mode) = tmpfs_mode {
+                options.push(format!("mode={:o}", mode));
+            }
+            tmpfs_mount(target, options)
+        }
+        Some(other) => Err(anyhow!("不支持的挂载类型 '{}'（可选 bind、tmpfs）", other)),
+        None => Err(anyhow!("挂载 '{}' 缺少 type", spec)),
+    }
+}
+
+/// 解析 `--tmpfs 容器路径[:选项]`，选项以逗号分隔（如 `size=64m,mode=1777`）
+fn parse_tmpfs(tmpfs: &str) -> Result<Mount> {
+    let (destination, options) = match tmpfs.split_once(':') {
+        Some((destination, options)) => (destination, options),
+        None => (tmpfs, ""),
+    };
+
+    let options = options
+        .split(',')
+        .filter(|o| !o.is_empty())
+        .map(String::from)
+        .collect();
+    tmpfs_mount(destination, options)
+}
+
+/// 构造绑定挂载，源路径必须是已存在的绝对路径
+fn bind_mount(
+    source: &str,
+    destination: &str,
+    readonly: bool,
+    propagation: Option<&str>,
+) -> Result<Mount> {
+    check_destination(destination)?;
+    if !source.starts_with('/') {
+        return Err(anyhow!("挂载源必须是宿主机上的绝对路径: {}", source));
+    }
+    if !Path::new(source).exists() {
+        return Err(anyhow!("挂载源不存在: {}", source));
+    }
+
+    let mut options = vec!["rbind".to_string()];
+    options.extend(propagation.map(String::from));
+    options.push(if readonly { "ro" } else { "rw" }.to_string());
+
+    let mut mount = Mount::default();
+    mount.set_destination(destination.into());
+    mount.set_typ(Some("bind".to_string()));
+    mount.set_source(Some(source.into()));
+    mount.set_options(Some(options));
+    Ok(mount)
+}
+
+/// 构造 tmpfs 挂载，`extra` 追加在默认选项之后
+fn tmpfs_mount(destination: &str, extra: Vec<String>) -> Result<Mount> {
+    check_destination(destination)?;
+
+    let mut options: Vec<String> = TMPFS_OPTIONS.iter().map(|o| o.to_string()).collect();
+    options.extend(extra);
+
+    let mut mount = Mount::default();
+    mount.set_destination(destination.into());
+    mount.set_typ(Some("tmpfs".to_string()));
+    mount.set_source(Some("tmpfs".into()));
+    mount.set_options(Some(options));
+    Ok(mount)
+}
+
+/// 检查容器内的挂载点为绝对路径且不是根目录
+fn check_destination(destination: &str) -> Result<()> {
+    if !destination.starts_with('/') {
+        return Err(anyhow!("容器内挂载路径必须是绝对路径: {}", destination));
+    }
+    if destination.trim_end_matches('/').is_empty() {
+        return Err(anyhow!("不能挂载到容器根目录"));
+    }
+    Ok(())
+}
+
+fn parse_bool(s: &str) -> Result<bool> {
+    match s {
+        "true" | "1" => Ok(true),
+        "false" | "0" => Ok(false),
+        _ => Err(anyhow!("无效的布尔值: {}", s)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn find_mount<'a>(spec: &'a Spec, destination: &str) -> Option<&'a Mount> {
+        spec.mounts()
+            .as_ref()?
+            .iter()
+            .find(|m| m.destination() == Path::new(destination))
+    }
+
+    #[test]
+    fn test_default_spec() {
+        let spec = default_spec("/rootfs", &["/bin/sh".to_string()], true);
+
+        assert_eq!(spec.hostname().as_deref(), Some(DEFAULT_HOSTNAME));
+        let root = spec.root().as_ref().unwrap();
+        assert_eq!(root.path(), Path::new("/rootfs"));
+        assert_eq!(root.readonly(), &Some(false));
+
+        let process = spec.process().as_ref().unwrap();
+        assert_eq!(process.args().as_ref().unwrap(), &["/bin/sh"]);
+        assert_eq!(process.terminal(), &Some(true));
+        assert!(
+            process
+                .env()
+                .as_ref()
+                .unwrap()
+                .iter()
+                .any(|e| e.starts_with("PATH="))
+        );
+        assert!(process.capabilities().is_some());
+
+        let namespaces = spec
+            .linux()
+            .as_ref()
+            .unwrap()
+            .namespaces()
+            .as_ref()
+            .unwrap();
+        for typ in [
+            LinuxNamespaceType::Pid,
+            LinuxNamespaceType::Network,
+            LinuxNamespaceType::Mount,
+            LinuxNamespaceType::Uts,
+            LinuxNamespaceType::Ipc,
+        ] {
+            assert!(namespaces.iter().any(|ns| ns.typ() == typ));
+        }
+        assert!(find_mount(&spec, "/proc").is_some());
+        assert!(find_mount(&spec, "/dev").is_some());
+
+        let rules = spec
+            .linux()
+            .as_ref()
+            .unwrap()
+            .resources()
+            .as_ref()
+            .unwrap()
+            .devices()
+            .as_ref()
+            .unwrap();
+        for dev in DEFAULT_DEVICES.iter() {
+            assert!(rules.iter().any(|r| {
+                r.allow() && r.major() == Some(dev.major()) && r.minor() == Some(dev.minor())
+            }));
+        }
+    }
+
+    #[test]
+    fn test_share_host_network() {
+        let mut spec = default_spec("/rootfs", &["/bin/sh".to_string()], false);
+        share_host_network(&mut spec);
+
+        let namespaces = spec
+            .linux()
+            .as_ref()
+            .unwrap()
+            .namespaces()
+            .as_ref()
+            .unwrap();
+        assert!(
+            !namespaces
+                .iter()
+                .any(|ns| ns.typ() == LinuxNamespaceType::Network)
+        );
+        assert!(
+            namespaces
+                .iter()
+                .any(|ns| ns.typ() == LinuxNamespaceType::Pid)
+        );
+    }
+
+    #[test]
+    fn test_make_rootless() {
+        let mut spec = default_spec("rootfs", &["sh".to_string()], true);
+        make_rootless(&mut spec, 1000, 100);
+
+        let linux = spec.linux().as_ref().unwrap();
+        let namespaces = linux.namespaces().as_ref().unwrap();
+        assert_eq!(
+            namespaces
+                .iter()
+                .filter(|ns| ns.typ() == LinuxNamespaceType::User)
+                .count(),
+            1
+        );
+        let uid_map = &linux.uid_mappings().as_ref().unwrap()[0];
+        assert_eq!(
+            (uid_map.container_id(), uid_map.host_id(), uid_map.size()),
+            (0, 1000, 1)
+        );
+        assert_eq!(linux.gid_mappings().as_ref().unwrap()[0].host_id(), 100);
+        assert!(linux.resources().is_none());
+
+        let mounts = spec.mounts().as_ref().unwrap();
+        let sys: Vec<_> = mounts
+            .iter()
+            .filter(|m| m.destination().starts_with("/sys"))
+            .collect();
+        assert_eq!(sys.len(), 1);
+        assert!(
+            sys[0]
+                .options()
+                .as_ref()
+                .unwrap()
+                .contains(&"rbind".to_string())
+        );
+        assert!(mounts.iter().all(|m| {
+            m.options()
+                .iter()
+                .flatten()
+                .all(|o| !o.starts_with("uid=") && !o.starts_with("gid="))
+        }));
+    }
+
+    #[test]
+    fn test_apply_empty_options() {
+        let mut spec = Spec::default();
+        spec.set_mounts(None);
+        let before = serde_json::to_string(&spec).unwrap();
+        apply_spec_options(&mut spec, &SpecOptions::default()).unwrap();
+        assert_eq!(serde_json::to_string(&spec).unwrap(), before);
+    }
+
+    #[test]
+    fn test_load_bundle_spec() {
+        let logger = slog::Logger::root(slog::Discard, slog::o!());
+        let bundle =
+            std::env::temp_dir().join(format!("runcell-bundle-test-{}", std::process::id()));
+        generate_spec(bundle.to_str().unwrap(), false, &logger).unwrap();
+
+        let mut spec = load_bundle_spec(bundle.to_str().unwrap()).unwrap();
+        let bundle = fs::canonicalize(&bundle).unwrap();
+        assert_eq!(spec.root().as_ref().unwrap().path(), &bundle.join("rootfs"));
+
+        override_spec(&mut spec, None, Some(&["true".to_string()]), None).unwrap();
+        let process = spec.process().as_ref().unwrap();
+        assert_eq!(process.args().as_ref().unwrap(), &["true"]);
+        assert_eq!(process.terminal(), &Some(true));
+        assert_eq!(spec.root().as_ref().unwrap().path(), &bundle.join("rootfs"));
+
+        override_spec(&mut spec, Some("/rootfs"), None, Some(false)).unwrap();
+        assert_eq!(spec.root().as_ref().unwrap().path(), Path::new("/rootfs"));
+        assert_eq!(spec.process().as_ref().unwrap().terminal(), &Some(false));
+
+        fs::remove_dir_all(&bundle).unwrap();
+        assert!(load_bundle_spec(bundle.to_str().unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_generate_spec() {
+        let logger = slog::Logger::root(slog::Discard, slog::o!());
+        let bundle = std::env::temp_dir().join(format!("runcell-spec-test-{}", std::process::id()));
+        let bundle_str = bundle.to_str().unwrap();
+
+        generate_spec(bundle_str, false, &logger).unwrap();
+        let spec = Spec::load(bundle.join("config.json")).unwrap();
+        assert_eq!(spec.root().as_ref().unwrap().path(), Path::new("rootfs"));
+        assert_eq!(
+            spec.process().as_ref().unwrap().args().as_ref().unwrap(),
+            &["sh"]
+        );
+
+        // 不覆盖已有的 config.json
+        assert!(generate_spec(bundle_str, true, &logger).is_err());
+
+        fs::remove_dir_all(&bundle).unwrap();
+    }
+
+    #[test]
+    fn test_parse_volume() {
+        let m = parse_volume("/tmp:/data").unwrap();
+        assert_eq!(m.destination(), Path::new("/data"));
+        assert_eq!(m.source().as_deref(), Some(Path::new("/tmp")));
+        assert_eq!(m.options().as_ref().unwrap(), &["rbind", "rw"]);
+
+        let m = parse_volume("/tmp:/data:ro,rshared").unwrap();
+        assert_eq!(m.options().as_ref().unwrap(), &["rbind", "rshared", "ro"]);
+
+        assert!(parse_volume("/tmp").is_err());
+        assert!(parse_volume("tmp:/data").is_err());
+        assert!(parse_volume("/tmp:data").is_err());
+        assert!(parse_volume("/tmp:/data:rx").is_err());
+        assert!(parse_volume("/runcell-surely-missing:/data").is_err());
+    }
+
+    #[test]
+    fn test_parse_mount() {
+        let m = parse_mount("type=bind,src=/tmp,dst=/data,readonly").unwrap();
+        assert_eq!(m.typ().as_deref(), Some("bind"));
+        assert_eq!(m.options().as_ref().unwrap(), &["rbind", "ro"]);
+
+        let m = parse_mount("type=tmpfs,target=/cache,tmpfs-size=64m,tmpfs-mode=1777").unwrap();
+        assert_eq!(m.typ().as_deref(), Some("tmpfs"));
+        assert_eq!(
+            m.options().as_ref().unwrap(),
+            &["nosuid", "nodev", "noexec", "size=67108864", "mode=1777"]
+        );
+
+        assert!(parse_mount("source=/tmp,target=/data").is_err());
+        assert!(parse_mount("type=bind,target=/data").is_err());
+        assert!(parse_mount("type=volume,source=v,target=/data").is_err());
+        assert!(parse_mount("type=tmpfs,source=/tmp,target=/data").is_err());
+        assert!(parse_mount("type=bind,source=/tmp,target=/data,foo=bar").is_err());
+    }
+
+    #[test]
+    fn test_parse_tmpfs() {
+        let m = parse_tmpfs("/run").unwrap();
+        assert_eq!(
+            m.options().as_ref().unwrap(),
+            &["nosuid", "nodev", "noexec"]
+        );
+
+        let m = parse_tmpfs("/run:size=64m,exec").unwrap();
+        assert_eq!(
+            m.options().as_ref().unwrap(),
+            &["nosuid", "nodev", "noexec", "size=64m", "exec"]
+        );
+
+        assert!(parse_tmpfs("run").is_err());
+        assert!(parse_tmpfs("/").is_err());
+    }
+
+    #[test]
+    fn test_apply_spec_options() {
+        let mut spec = default_spec("/rootfs", &["/bin/sh".to_string()], false);
+        let opts = SpecOptions {
+            hostname: Some("web".to_string()),
+            read_only: true,
+            tmpfs: vec!["/dev/shm:size=1g".to_string()],
+            volume: vec!["/tmp:/data:ro".to_string()],
+            process: crate::ProcessOptions {
+                env: vec!["FOO=bar".to_string()],
+                privileged: true,
+                ..Default::default()
+            },
+            resources: crate::ResourceOptions {
+                pids_limit: Some(100),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mounts_before = spec.mounts().as_ref().unwrap().len();
+        apply_spec_options(&mut spec, &opts).unwrap();
+
+        assert_eq!(spec.hostname().as_deref(), Some("web"));
+        assert_eq!(spec.root().as_ref().unwrap().readonly(), &Some(true));
+
+        let env = spec.process().as_ref().unwrap().env().as_ref().unwrap();
+        assert!(env.iter().any(|e| e.starts_with("PATH=")));
+        assert!(env.contains(&"FOO=bar".to_string()));
+
+        let linux = spec.linux().as_ref().unwrap();
+        assert!(linux.masked_paths().is_none());
+        assert!(linux.readonly_paths().is_none());
+        let pids = linux.resources().as_ref().unwrap().pids().as_ref().unwrap();
+        assert_eq!(pids.limit(), 100);
+
+        // /dev/shm 替换模板中的挂载，/data 为新增挂载
+        assert_eq!(spec.mounts().as_ref().unwrap().len(), mounts_before + 1);
+        let shm = find_mount(&spec, "/dev/shm").unwrap();
+        assert_eq!(shm.typ().as_deref(), Some("tmpfs"));
+        assert!(
+            shm.options()
+                .as_ref()
+                .unwrap()
+                .contains(&"size=1g".to_string())
+        );
+        assert!(find_mount(&spec, "/data").is_some());
+
+        let sys = find_mount(&spec, "/sys").unwrap();
+        assert!(!sys.options().as_ref().unwrap().contains(&"ro".to_string()));
+    }
+}
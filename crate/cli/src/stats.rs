@@ -0,0 +1,436 @@
+//! 容器资源使用统计（`ctr stats`）
+//!
+//! 通过 `BaseContainer::stats` 读取容器 cgroup 中的 CPU、内存、PID 和块设备 I/O 数据。
+//! CPU 使用率由相邻两次采样的累计 CPU 时间之差除以采样间隔得到，
+//! 与 `docker stats` 一样，100% 表示占满一个 CPU 核心。
+
+use std::{
+    collections::HashMap,
+    fs,
+    io::{self, Write},
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, Result, anyhow};
+use celler::container::{BaseContainer, LinuxContainer, load_container_state};
+use slog::Logger;
+
+use crate::{config::state_base, container_cmd::ensure_cgroup};
+
+/// 两次刷新之间的间隔
+const STATS_INTERVAL: Duration = Duration::from_secs(1);
+
+/// 一次采样得到的原始数据
+#[derive(Debug, Clone, Default)]
+struct Sample {
+    /// 容器是否在运行，已停止的容器各项数据均为 0
+    running: bool,
+    /// 累计 CPU 时间（纳秒）
+    cpu_total: u64,
+    /// 内存使用量（字节，已扣除 inactive_file 页缓存）
+    mem_usage: u64,
+    /// 内存限制（字节，未限制时为主机内存总量）
+    mem_limit: u64,
+    /// 容器内的进程数
+    pids: u64,
+    /// 块设备累计读取字节数
+    blk_read: u64,
+    /// 块设备累计写入字节数
+    blk_write: u64,
+}
+
+/// 由相邻两次采样计算出的统计结果
+#[derive(Debug, Clone)]
+struct ContainerStats {
+    id: String,
+    cpu_percent: f64,
+    mem_usage: u64,
+    mem_limit: u64,
+    mem_percent: f64,
+    pids: u64,
+    blk_read: u64,
+    blk_write: u64,
+}
+
+/// 显示容器资源使用统计
+///
+/// # 参数
+/// - `ids`: 指定的容器 ID，为空时显示所有运行中的容器
+/// - `all`: 同时显示已停止的容器
+/// - `no_stream`: 只输出一次结果，否则每秒刷新一次直到被中断
+/// - `format`: 输出格式，`table` 或 `json`（每行一个容器的 JSON 对象）
+pub async fn show_stats(
+    ids: &[String],
+    all: bool,
+    no_stream: bool,
+    format: &str,
+    logger: &Logger,
+) -> Result<()> {
+    if format != "table" && format != "json" {
+        return Err(anyhow!("不支持的输出格式: {}（可选 table, json）", format));
+    }
+    // mock 管理器的各项统计恒为 0
+    ensure_cgroup("统计容器资源使用")?;
+
+    slog::info!(logger, "查看容器资源统计";
+        "ids" => format!("{:?}", ids), "all" => all, "no_stream" => no_stream);
+
+    // 指定的容器必须存在
+    for id in ids {
+        load_container_state(state_base(), id).with_context(|| format!("容器 '{}' 不存在", id))?;
+    }
+
+    let mut prev: HashMap<String, Sample> = take_samples(ids, all, logger)?.into_iter().collect();
+    let mut prev_at = Instant::now();
+
+    loop {
+        tokio::time::sleep(STATS_INTERVAL).await;
+
+        let cur = take_samples(ids, all, logger)?;
+        let now = Instant::now();
+        let interval = now.duration_since(prev_at);
+
+        let stats: Vec<ContainerStats> = cur
+            .iter()
+            .map(|(id, sample)| compute_stats(id, prev.get(id), sample, interval))
+            .collect();
+
+        if format == "json" {
+            print_json(&stats)?;
+        } else {
+            print_table(&stats, !no_stream)?;
+        }
+
+        if no_stream {
+            return Ok(());
+        }
+
+        prev = cur.into_iter().collect();
+        prev_at = now;
+    }
+}
+
+/// 对需要显示的容器逐个采样
+///
+/// 返回值按容器 ID 排序；采样期间被删除的容器会被跳过。
+fn take_samples(ids: &[String], all: bool, logger: &Logger) -> Result<Vec<(String, Sample)>> {
+    let ids = if ids.is_empty() {
+        list_container_ids(all)?
+    } else {
+        ids.to_vec()
+    };
+
+    let mut samples = Vec::new();
+    for id in ids {
+        match take_sample(&id, logger) {
+            Ok(sample) => samples.push((id, sample)),
+            Err(e) => {
+                slog::debug!(logger, "读取容器资源统计失败";
+                    "id" => &id, "error" => format!("{:?}", e));
+            }
+        }
+    }
+
+    Ok(samples)
+}
+
+/// 列出状态目录中的容器 ID
+///
+/// `all` 为 false 时只返回仍在运行的容器。
+fn list_container_ids(all: bool) -> Result<Vec<String>> {
+    let state_dir = Path::new(state_base());
+    if !state_dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut ids = Vec::new();
+    for entry in fs::read_dir(state_dir)? {
+        let id = entry?.file_name().to_string_lossy().to_string();
+        match load_container_state(state_base(), &id) {
+            Ok(state) if all || state.is_running() => ids.push(id),
+            _ => continue,
+        }
+    }
+    ids.sort();
+
+    Ok(ids)
+}
+
+/// 读取单个容器的 cgroup 统计数据
+fn take_sample(id: &str, logger: &Logger) -> Result<Sample> {
+    let state = load_container_state(state_base(), id)?;
+    if !state.is_running() {
+        return Ok(Sample::default());
+    }
+
+    let container = LinuxContainer::load(id, state_base(), None, logger)?;
+    let resp = container.stats()?;
+    let cgroup = resp.cgroup_stats.get_or_default();
+
+    let cpu_total = cgroup
+        .cpu_stats
+        .get_or_default()
+        .cpu_usage
+        .get_or_default()
+        .total_usage;
+
+    let memory = cgroup.memory_stats.get_or_default();
+    let usage = memory.usage.get_or_default();
+    // 与 docker 一致，不把可回收的页缓存计入使用量（v1 为 total_inactive_file，v2 为 inactive_file）
+    let inactive_file = memory
+        .stats
+        .get("total_inactive_file")
+        .or_else(|| memory.stats.get("inactive_file"))
+        .copied()
+        .unwrap_or(0);
+    let mem_usage = usage.usage.saturating_sub(inactive_file);
+    let mem_limit = effective_memory_limit(usage.limit, host_memory_total());
+
+    let (blk_read, blk_write) = sum_blkio_bytes(
+        cgroup
+            .blkio_stats
+            .get_or_default()
+            .io_service_bytes_recursive
+            .iter()
+            .map(|e| (e.op.as_str(), e.value)),
+    );
+
+    Ok(Sample {
+        running: true,
+        cpu_total,
+        mem_usage,
+        mem_limit,
+        pids: cgroup.pids_stats.get_or_default().current,
+        blk_read,
+        blk_write,
+    })
+}
+
+/// 根据两次采样计算统计结果
+///
+/// 第一次出现的容器没有上一次采样，CPU 使用率记为 0。
+fn compute_stats(
+    id: &str,
+    prev: Option<&Sample>,
+    cur: &Sample,
+    interval: Duration,
+) -> ContainerStats {
+    let cpu_percent = match prev {
+        Some(prev) if cur.running && prev.running && !interval.is_zero() => {
+            let delta = cur.cpu_total.saturating_sub(prev.cpu_total);
+            delta as f64 / interval.as_nanos() as f64 * 100.0
+        }
+        _ => 0.0,
+    };
+
+    let mem_percent = if cur.mem_limit > 0 {
+        cur.mem_usage as f64 / cur.mem_limit as f64 * 100.0
+    } else {
+        0.0
+    };
+
+    ContainerStats {
+        id: id.to_string(),
+        cpu_percent,
+        mem_usage: cur.mem_usage,
+        mem_limit: cur.mem_limit,
+        mem_percent,
+        pids: cur.pids,
+        blk_read: cur.blk_read,
+        blk_write: cur.blk_write,
+    }
+}
+
+/// 未设置内存限制时 cgroup 报告一个极大值，此时以主机内存总量作为限制
+fn effective_memory_limit(limit: u64, host_total: u64) -> u64 {
+    if limit == 0 || (host_total > 0 && limit > host_total) {
+        host_total
+    } else {
+        limit
+    }
+}
+
+/// 读取主机内存总量（字节）
+fn host_memory_total() -> u64 {
+    fs::read_to_string("/proc/meminfo")
+        .ok()
+        .and_then(|content| {
+            content
+                .lines()
+                .find_map(|l| l.strip_prefix("MemTotal:"))
+                .and_then(|v| v.trim().trim_end_matches("kB").trim().parse::<u64>().ok())
+        })
+        .map(|kb| kb * 1024)
+        .unwrap_or(0)
+}
+
+/// 汇总块设备读写字节数
+///
+/// cgroup v1 的操作名为 `Read`/`Write`，v2 为 `read`/`write`。
+fn sum_blkio_bytes<'a>(entries: impl Iterator<Item = (&'a str, u64)>) -> (u64, u64) {
+    let mut read = 0u64;
+    let mut write = 0u64;
+    for (op, value) in entries {
+        if op.eq_ignore_ascii_case("read") {
+            read = read.saturating_add(value);
+        } else if op.eq_ignore_ascii_case("write") {
+            write = write.saturating_add(value);
+        }
+    }
+    (read, write)
+}
+
+/// 以表格形式输出，持续刷新时先清屏
+fn print_table(stats: &[ContainerStats], clear: bool) -> Result<()> {
+    let mut out = io::stdout().lock();
+    if clear {
+        write!(out, "\x1b[2J\x1b[H")?;
+    }
+
+    writeln!(
+        out,
+        "{:<20} {:<8} {:<22} {:<8} {:<20} {}",
+        "CONTAINER ID", "CPU %", "MEM USAGE / LIMIT", "MEM %", "BLOCK I/O", "PIDS"
+    )?;
+    for s in stats {
+        let id_display = if s.id.len() > 18 { &s.id[..18] } else { &s.id };
+        writeln!(
+            out,
+            "{:<20} {:<8} {:<22} {:<8} {:<20} {}",
+            id_display,
+            format!("{:.2}%", s.cpu_percent),
+            format!(
+                "{} / {}",
+                format_binary_size(s.mem_usage),
+                format_binary_size(s.mem_limit)
+            ),
+            format!("{:.2}%", s.mem_percent),
+            format!(
+                "{} / {}",
+                format_decimal_size(s.blk_read),
+                format_decimal_size(s.blk_write)
+            ),
+            s.pids
+        )?;
+    }
+    out.flush()?;
+
+    Ok(())
+}
+
+/// 以 JSON 形式输出，每行一个容器
+fn print_json(stats: &[ContainerStats]) -> Result<()> {
+    let mut out = io::stdout().lock();
+    for s in stats {
+        let value = serde_json::json!({
+            "id": s.id,
+            "cpu_percent": s.cpu_percent,
+            "memory_usage": s.mem_usage,
+            "memory_limit": s.mem_limit,
+            "memory_percent": s.mem_percent,
+            "pids": s.pids,
+            "block_read": s.blk_read,
+            "block_write": s.blk_write,
+        });
+        writeln!(out, "{}", value)?;
+    }
+    out.flush()?;
+
+    Ok(())
+}
+
+/// 以 1024 为进制格式化字节数（内存）
+pub(crate) fn format_binary_size(bytes: u64) -> String {
+    format_size(bytes, 1024.0, &["B", "KiB", "MiB", "GiB", "TiB"])
+}
+
+/// 以 1000 为进制格式化字节数（块设备 I/O、镜像大小）
+pub(crate) fn format_decimal_size(bytes: u64) -> String {
+    format_size(bytes, 1000.0, &["B", "kB", "MB", "GB", "TB"])
+}
+
+fn format_size(bytes: u64, base: f64, units: &[&str]) -> String {
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= base && unit < units.len() - 1 {
+        value /= base;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{}{}", bytes, units[0])
+    } else {
+        format!("{:.2}{}", value, units[unit])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compute_stats_cpu_percent() {
+        let prev = Sample {
+            running: true,
+            cpu_total: 1_000_000_000,
+            ..Default::default()
+        };
+        let cur = Sample {
+            running: true,
+            cpu_total: 1_500_000_000,
+            mem_usage: 256 * 1024 * 1024,
+            mem_limit: 1024 * 1024 * 1024,
+            ..Default::default()
+        };
+
+        let stats = compute_stats("c1", Some(&prev), &cur, Duration::from_secs(1));
+        assert!((stats.cpu_percent - 50.0).abs() < 1e-6);
+        assert!((stats.mem_percent - 25.0).abs() < 1e-6);
+
+        // 首次采样没有上一次数据
+        let stats = compute_stats("c1", None, &cur, Duration::from_secs(1));
+        assert_eq!(stats.cpu_percent, 0.0);
+
+        // 已停止的容器
+        let stats = compute_stats(
+            "c1",
+            Some(&prev),
+            &Sample::default(),
+            Duration::from_secs(1),
+        );
+        assert_eq!(stats.cpu_percent, 0.0);
+        assert_eq!(stats.mem_percent, 0.0);
+    }
+
+    #[test]
+    fn test_effective_memory_limit() {
+        let host = 8 * 1024 * 1024 * 1024;
+        assert_eq!(
+            effective_memory_limit(512 * 1024 * 1024, host),
+            512 * 1024 * 1024
+        );
+        assert_eq!(effective_memory_limit(9223372036854771712, host), host);
+        assert_eq!(effective_memory_limit(0, host), host);
+    }
+
+    #[test]
+    fn test_sum_blkio_bytes() {
+        let entries = [
+            ("Read", 100),
+            ("Write", 200),
+            ("Total", 300),
+            ("read", 1),
+            ("write", 2),
+            ("rios", 5),
+        ];
+        assert_eq!(sum_blkio_bytes(entries.into_iter()), (101, 202));
+    }
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_binary_size(512), "512B");
+        assert_eq!(format_binary_size(1536 * 1024), "1.50MiB");
+        assert_eq!(format_decimal_size(1_500_000), "1.50MB");
+    }
+}
@@ -5,7 +5,7 @@ use std::{fs, path::Path};
 use anyhow::{Context, Result};
 use slog::Logger;
 
-use crate::StorageCommands;
+use crate::{StorageCommands, config};
 
 /// 处理存储相关命令
 pub async fn handle_storage_command(cmd: StorageCommands, logger: &Logger) -> Result<()> {
@@ -63,8 +63,14 @@ pub async fn handle_storage_command(cmd: StorageCommands, logger: &Logger) -> Re
             slog::info!(logger, "拉取镜像"; "image" => &image, "container_id" => &container_id);
 
             // 调用镜像拉取功能
-            let rootfs_path =
-                storage::image::pull_and_extract(&image, &container_id, logger).await?;
+            let rootfs_path = storage::image::pull_and_extract(
+                &image,
+                config::image_root(),
+                &container_id,
+                logger,
+            )
+            .await?
+            .rootfs;
 
             slog::info!(logger, "镜像拉取成功"; "rootfs" => &rootfs_path);
 
@@ -87,7 +93,7 @@ pub async fn handle_storage_command(cmd: StorageCommands, logger: &Logger) -> Re
         StorageCommands::Cleanup { container_id } => {
             slog::info!(logger, "清理镜像"; "container_id" => &container_id);
 
-            storage::image::cleanup_image(&container_id, logger)?;
+            storage::image::cleanup_image(config::image_root(), &container_id, logger)?;
 
             slog::info!(logger, "清理完成"; "container_id" => &container_id);
 
@@ -192,7 +198,10 @@ async fn test_tar_image(logger: &Logger) -> Result<()> {
     let container_id = "test-tar-container";
     let image_url = format!("file://{}", tar_file);
 
-    let rootfs = storage::image::pull_and_extract(&image_url, container_id, logger).await?;
+    let rootfs =
+        storage::image::pull_and_extract(&image_url, config::image_root(), container_id, logger)
+            .await?
+            .rootfs;
 
     slog::info!(logger, "镜像解压成功"; "rootfs" => &rootfs);
 
@@ -203,7 +212,7 @@ async fn test_tar_image(logger: &Logger) -> Result<()> {
     slog::info!(logger, "内容验证成功"; "content" => &content);
 
     // 清理
-    storage::image::cleanup_image(container_id, logger)?;
+    storage::image::cleanup_image(config::image_root(), container_id, logger)?;
     fs::remove_dir_all(test_dir)?;
 
     slog::info!(logger, "清理完成");
@@ -235,7 +244,10 @@ async fn test_dir_image(logger: &Logger) -> Result<()> {
     let container_id = "test-dir-container";
     let image_url = format!("dir://{}", bundle_src);
 
-    let rootfs = storage::image::pull_and_extract(&image_url, container_id, logger).await?;
+    let rootfs =
+        storage::image::pull_and_extract(&image_url, config::image_root(), container_id, logger)
+            .await?
+            .rootfs;
 
     slog::info!(logger, "目录复制成功"; "rootfs" => &rootfs);
 
@@ -249,7 +261,7 @@ async fn test_dir_image(logger: &Logger) -> Result<()> {
     slog::info!(logger, "内容验证成功");
 
     // 清理
-    storage::image::cleanup_image(container_id, logger)?;
+    storage::image::cleanup_image(config::image_root(), container_id, logger)?;
     fs::remove_dir_all(test_dir)?;
 
     slog::info!(logger, "清理完成");
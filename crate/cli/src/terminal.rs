@@ -0,0 +1,112 @@
+//! 容器伪终端转发
+//!
+//! 容器进程使用伪终端（`-t`）时，slave 端作为其标准输入输出，
+//! CLI 持有 master 端，并在 master 与自身的标准输入输出之间转发数据。
+//! 交互模式下宿主机终端切换为 raw 模式，按键（包括 Ctrl-C）原样交给容器内的终端处理；
+//! 窗口大小变化时通过 `TIOCSWINSZ` 同步到伪终端。
+//! 后台容器的 master 由 shim 进程持有，`ctr attach` 通过 socket 转发（见 `attach` 模块）。
+
+use std::{
+    fs::File,
+    io,
+    os::unix::io::{AsRawFd, FromRawFd, RawFd},
+    thread::JoinHandle,
+};
+
+use anyhow::{Context, Result};
+use nix::{
+    errno::Errno,
+    sys::termios::{self, SetArg, Termios},
+    unistd,
+};
+
+/// 在伪终端 master 与当前进程的标准输入输出之间转发数据
+///
+/// 启动后台线程：master → stdout，`stdin` 为 true 时另有 stdin → master。
+/// 容器进程退出、slave 端关闭后 master 读到 EIO，输出线程随之结束。
+///
+/// # 参数
+/// - `master`: 伪终端 master fd（函数内部复制，调用方仍持有原 fd）
+/// - `stdin`: 是否转发标准输入（对应 `-i`）
+///
+/// # 返回
+/// 输出线程的句柄，调用方在容器进程退出后 join，确保输出全部写完
+pub fn proxy_terminal(master: RawFd, stdin: bool) -> Result<JoinHandle<()>> {
+    let output = unistd::dup(master).context("复制伪终端 master fd 失败")?;
+    // 直接写 fd 1，绕过 std::io::stdout 的行缓冲，否则不带换行的提示符不会立即显示
+    let stdout = unistd::dup(io::stdout().as_raw_fd()).context("复制标准输出失败")?;
+
+    if stdin {
+        let input = unistd::dup(master).context("复制伪终端 master fd 失败")?;
+        std::thread::spawn(move || {
+            let mut master = unsafe { File::from_raw_fd(input) };
+            let _ = io::copy(&mut io::stdin(), &mut master);
+        });
+    }
+
+    Ok(std::thread::spawn(move || {
+        let mut master = unsafe { File::from_raw_fd(output) };
+        let mut stdout = unsafe { File::from_raw_fd(stdout) };
+        let _ = io::copy(&mut master, &mut stdout);
+    }))
+}
+
+/// 将宿主机终端切换为 raw 模式，离开作用域时恢复原有设置
+///
+/// raw 模式下终端不再回显、不按行缓冲，也不把 Ctrl-C 等按键转换为信号，
+/// 这些工作交给容器内伪终端的行规程完成，`vim`、`top` 等程序才能正常工作。
+pub struct RawTerminal {
+    fd: RawFd,
+    original: Termios,
+}
+
+impl RawTerminal {
+    /// 将 `fd` 对应的终端切换为 raw 模式
+    ///
+    /// # 返回
+    /// `fd` 不是终端时（如标准输入被重定向）返回 `None`
+    pub fn enable(fd: RawFd) -> Result<Option<Self>> {
+        if !unistd::isatty(fd).unwrap_or(false) {
+            return Ok(None);
+        }
+
+        let original = termios::tcgetattr(fd).context("读取终端属性失败")?;
+        let mut raw = original.clone();
+        termios::cfmakeraw(&mut raw);
+        termios::tcsetattr(fd, SetArg::TCSANOW, &raw).context("设置终端 raw 模式失败")?;
+
+        Ok(Some(Self { fd, original }))
+    }
+}
+
+impl Drop for RawTerminal {
+    fn drop(&mut self) {
+        let _ = termios::tcsetattr(self.fd, SetArg::TCSANOW, &self.original);
+    }
+}
+
+/// 将当前终端（标准输入）的窗口大小同步到伪终端
+///
+/// 内核随后向伪终端的前台进程组发送 SIGWINCH。
+pub fn resize_pty(master: RawFd) -> Result<()> {
+    let ws = window_size(io::stdin().as_raw_fd())?;
+    set_window_size(master, &ws)
+}
+
+/// 读取终端的窗口大小
+pub fn window_size(fd: RawFd) -> Result<libc::winsize> {
+    let mut ws: libc::winsize = unsafe { std::mem::zeroed() };
+
+    let ret = unsafe { libc::ioctl(fd, libc::TIOCGWINSZ, &mut ws) };
+    Errno::result(ret).context("读取终端窗口大小失败")?;
+
+    Ok(ws)
+}
+
+/// 设置伪终端的窗口大小
+pub fn set_window_size(master: RawFd, ws: &libc::winsize) -> Result<()> {
+    let ret = unsafe { libc::ioctl(master, libc::TIOCSWINSZ, ws) };
+    Errno::result(ret).context("设置伪终端窗口大小失败")?;
+
+    Ok(())
+}
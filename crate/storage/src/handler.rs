@@ -2,7 +2,7 @@
 //!
 //! 定义不同类型存储的处理器实现。
 
-use std::{collections::HashMap, sync::Arc};
+use std::{collections::HashMap, path::Path, sync::Arc};
 
 use anyhow::{Result, anyhow};
 use async_trait::async_trait;
@@ -17,6 +17,8 @@ use crate::{StorageConfig, device::StorageDevice};
 pub struct StorageContext<'a> {
     /// 容器 ID
     pub container_id: Option<String>,
+    /// 镜像根目录
+    pub image_root: &'a Path,
     /// 日志记录器
     pub logger: &'a Logger,
 }
@@ -244,8 +246,14 @@ impl StorageHandler for ImagePullHandler {
             .ok_or_else(|| anyhow!("Container ID is required for image pull"))?;
 
         // 调用镜像拉取模块
-        let bundle_path =
-            crate::image::pull_and_extract(&storage.source, container_id, ctx.logger).await?;
+        let bundle_path = crate::image::pull_and_extract(
+            &storage.source,
+            ctx.image_root,
+            container_id,
+            ctx.logger,
+        )
+        .await?
+        .rootfs;
 
         info!(ctx.logger, "Image pulled successfully"; "bundle-path" => &bundle_path);
 
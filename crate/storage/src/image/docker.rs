@@ -0,0 +1,481 @@
+//! docker save 归档
+//!
+//! `docker save` 生成的 tar 归档包含 `manifest.json`、每层的 `layer.tar` 和镜像配置 JSON：
+//! ```json
+//! [{"Config": "<id>.json", "RepoTags": ["busybox:latest"], "Layers": ["<id>/layer.tar"]}]
+//! ```
+//! 新版本 docker 生成的归档同时也是 OCI 镜像布局，`Config` 和 `Layers` 指向
+//! `blobs/sha256/` 下的文件，两种格式都按 `manifest.json` 处理。
+//!
+//! 归档先解压到工作目录下的临时目录，`manifest.json` 中的路径都限制在该目录内解析，
+//! 镜像层按顺序解压到 rootfs 或镜像层存储。
+
+use std::{
+    fs::{self, File},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result, anyhow, bail};
+use oci_spec::image::ImageConfiguration;
+use safe_path::scoped_join;
+use serde::Deserialize;
+use sha2::{Digest as _, Sha256};
+use slog::Logger;
+use tar::Archive;
+
+use super::{
+    LayerTarget, LoadedImage, index::ImageRecord, parse_reference, unpack, with_extracted_archive,
+};
+
+/// 归档中描述镜像的文件
+const MANIFEST_FILE: &str = "manifest.json";
+
+/// `manifest.json` 中的一个镜像
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct ManifestEntry {
+    /// 镜像配置文件
+    config: String,
+    /// 镜像名称，未打标签的镜像为空
+    #[serde(default)]
+    repo_tags: Option<Vec<String>>,
+    /// 镜像层文件，从底层到顶层
+    layers: Vec<String>,
+}
+
+impl ManifestEntry {
+    fn tags(&self) -> impl Iterator<Item = &String> {
+        self.repo_tags.iter().flatten()
+    }
+}
+
+/// 判断 tar 文件是否为 docker save 归档，即根目录下是否有 `manifest.json`
+///
+/// `docker save` 通常把 `manifest.json` 放在归档末尾，因此逐条检查条目头；
+/// 遇到不可能出现在 docker save 归档中的条目（如 rootfs 的 `etc/`）时立即返回，
+/// 普通的 rootfs 归档不需要读完。
+pub fn is_docker_archive(path: &Path) -> Result<bool> {
+    let file = File::open(path).with_context(|| format!("Failed to open {:?}", path))?;
+    let (_, reader) = unpack::decompress(file)?;
+    let mut archive = Archive::new(reader);
+    for entry in archive.entries().context("Failed to read tar archive")? {
+        let entry = entry.context("Failed to read tar entry")?;
+        let path = unpack::normalize(&entry.path()?);
+        if path == Path::new(MANIFEST_FILE) {
+            return Ok(true);
+        }
+        if !is_archive_entry(&path) {
+            return Ok(false);
+        }
+    }
+    Ok(false)
+}
+
+/// 条目是否可能属于 docker save 归档
+///
+/// 旧格式的归档包含 `repositories`、`<id>.json` 和 `<id>/` 目录（`VERSION`、`json`、`layer.tar`），
+/// 新格式同时是 OCI 镜像布局，包含 `index.json`、`oci-layout` 和 `blobs/`。
+fn is_archive_entry(path: &Path) -> bool {
+    let Some(first) = path.components().next() else {
+        // 根目录本身
+        return true;
+    };
+    let Some(first) = first.as_os_str().to_str() else {
+        return false;
+    };
+    let is_id = |s: &str| s.len() == 64 && s.bytes().all(|b| b.is_ascii_hexdigit());
+    matches!(
+        first,
+        "repositories" | "index.json" | "oci-layout" | "blobs"
+    ) || is_id(first)
+        || first.strip_suffix(".json").is_some_and(is_id)
+}
+
+/// 从 docker save 归档加载镜像并解压镜像层
+///
+/// # 参数
+/// - `image`: 镜像引用 `/path/to/image.tar[:repo:tag]`，不含 `docker-archive://` 前缀，
+///   按 `parse_reference` 拆分出归档路径和镜像名称，归档中只有一个镜像时可以省略名称
+/// - `work_dir`: 临时目录所在的工作目录
+/// - `container_id`: 容器 ID，用于区分临时目录
+/// - `target`: 镜像层的解压目标
+/// - `logger`: 日志记录器
+///
+/// # 返回
+/// 加载的镜像，镜像 ID 为镜像配置文件的 sha256 摘要
+pub async fn pull_docker_archive(
+    image: &str,
+    work_dir: &Path,
+    container_id: &str,
+    target: &LayerTarget,
+    logger: &Logger,
+) -> Result<LoadedImage> {
+    let (archive, reference) = parse_reference(image);
+    let archive = PathBuf::from(archive);
+    let reference = reference.map(str::to_string);
+    let dir = scoped_join(work_dir, format!("docker-{}", container_id))?;
+    let target = target.clone();
+    let logger = logger.clone();
+
+    tokio::task::spawn_blocking(move || {
+        with_extracted_archive(&archive, &dir, &logger, |dir| {
+            load_archive(dir, reference.as_deref(), &target, &logger)
+        })
+    })
+    .await
+    .context("Docker archive task panicked")?
+}
+
+/// 从解压后的归档中选择镜像，按顺序解压镜像层
+fn load_archive(
+    dir: &Path,
+    reference: Option<&str>,
+    target: &LayerTarget,
+    logger: &Logger,
+) -> Result<LoadedImage> {
+    let manifest: Vec<ManifestEntry> = serde_json::from_reader(
+        File::open(dir.join(MANIFEST_FILE)).context("Missing manifest.json")?,
+    )
+    .context("Invalid manifest.json")?;
+    let entry = select_image(&manifest, reference)?;
+
+    let config_path = scoped_join(dir, &entry.config)?;
+    let config_blob =
+        fs::read(&config_path).with_context(|| format!("Missing image config {}", entry.config))?;
+    let config =
+        ImageConfiguration::from_reader(&config_blob[..]).context("Invalid image configuration")?;
+    let diff_ids = config.rootfs().diff_ids();
+    if diff_ids.len() != entry.layers.len() {
+        bail!(
+            "Image config lists {} layers but manifest.json has {}",
+            diff_ids.len(),
+            entry.layers.len()
+        );
+    }
+
+    if let LayerTarget::Rootfs(rootfs) = target {
+        std::fs::create_dir_all(rootfs)
+            .with_context(|| format!("Failed to create rootfs directory: {:?}", rootfs))?;
+    }
+    let mut lowers = Vec::new();
+    let mut size = 0;
+    for (layer, diff_id) in entry.layers.iter().zip(diff_ids) {
+        size += scoped_join(dir, layer)
+            .and_then(fs::metadata)
+            .map_or(0, |m| m.len());
+        debug!(logger, "Applying layer"; "layer" => layer);
+        // 重复的镜像层可能是指向其他层的符号链接，同样在归档目录内解析
+        let lower = target
+            .apply(diff_id, || Ok(scoped_join(dir, layer)?), logger)
+            .with_context(|| format!("Failed to apply layer {}", layer))?;
+        lowers.extend(lower);
+    }
+
+    info!(logger, "Loaded docker archive image";
+        "tags" => entry.tags().cloned().collect::<Vec<_>>().join(","),
+        "platform" => format!("{}/{}", config.os(), config.architecture()),
+        "layers" => entry.layers.len());
+
+    Ok(LoadedImage {
+        record: ImageRecord {
+            id: format!("sha256:{}", hex::encode(Sha256::digest(&config_blob))),
+            names: entry.tags().cloned().collect(),
+            digest: None,
+            size,
+            manifest: None,
+            config,
+        },
+        lowers,
+    })
+}
+
+/// 按镜像名称选择镜像，未指定名称时归档中只能有一个镜像
+fn select_image<'a>(
+    manifest: &'a [ManifestEntry],
+    reference: Option<&str>,
+) -> Result<&'a ManifestEntry> {
+    let available = || {
+        manifest
+            .iter()
+            .flat_map(ManifestEntry::tags)
+            .cloned()
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+
+    match (reference, manifest) {
+        (_, []) => bail!("manifest.json contains no image"),
+        (Some(reference), _) => {
+            let wanted = normalize_tag(reference);
+            manifest
+                .iter()
+                .find(|e| e.tags().any(|t| normalize_tag(t) == wanted))
+                .ok_or_else(|| {
+                    anyhow!(
+                        "Image {} not found in archive, available: [{}]",
+                        reference,
+                        available()
+                    )
+                })
+        }
+        (None, [entry]) => Ok(entry),
+        (None, _) => bail!(
+            "Archive contains multiple images, specify one of: [{}]",
+            available()
+        ),
+    }
+}
+
+/// 规范化镜像名称：去掉 Docker Hub 前缀，未指定标签时补全 `latest`
+///
+/// `docker.io/library/busybox` 和 `busybox:latest` 规范化后都是 `busybox:latest`。
+pub(super) fn normalize_tag(tag: &str) -> String {
+    let mut name = tag;
+    for prefix in ["docker.io/", "index.docker.io/"] {
+        name = name.strip_prefix(prefix).unwrap_or(name);
+    }
+    name = name.strip_prefix("library/").unwrap_or(name);
+
+    let last = name.rsplit('/').next().unwrap_or(name);
+    if last.contains(':') || last.contains('@') {
+        name.to_string()
+    } else {
+        format!("{}:latest", name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use serde_json::json;
+    use slog::Drain;
+    use tempfile::tempdir;
+
+    use super::*;
+    use crate::image::store::LayerStore;
+
+    fn test_logger() -> Logger {
+        let decorator = slog_term::PlainSyncDecorator::new(std::io::stdout());
+        let drain = slog_term::FullFormat::new(decorator).build().fuse();
+        Logger::root(drain, o!())
+    }
+
+    fn tar_of(files: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut builder = tar::Builder::new(Vec::new());
+        for (path, data) in files {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(data.len() as u64);
+            header.set_mode(0o644);
+            header.set_uid(nix::unistd::geteuid().as_raw().into());
+            header.set_gid(nix::unistd::getegid().as_raw().into());
+            builder.append_data(&mut header, path, *data).unwrap();
+        }
+        builder.into_inner().unwrap()
+    }
+
+    /// 构造包含两个镜像的 docker save 归档
+    fn write_archive(path: &Path) {
+        let lower = tar_of(&[("etc/os-release", b"lower"), ("tmp/junk", b"x")]);
+        let upper = tar_of(&[("etc/os-release", b"upper"), ("tmp/.wh.junk", b"")]);
+        let other = tar_of(&[("etc/os-release", b"other")]);
+        let config = |layers: &[&Vec<u8>]| {
+            let diff_ids: Vec<String> = layers
+                .iter()
+                .map(|data| format!("sha256:{}", hex::encode(Sha256::digest(data))))
+                .collect();
+            json!({
+                "architecture": "amd64",
+                "os": "linux",
+                "config": { "Cmd": ["/bin/sh"] },
+                "rootfs": { "type": "layers", "diff_ids": diff_ids },
+            })
+            .to_string()
+        };
+        let manifest = json!([
+            {
+                "Config": "app.json",
+                "RepoTags": ["example/app:v1"],
+                "Layers": ["lower/layer.tar", "upper/layer.tar"],
+            },
+            {
+                "Config": "busybox.json",
+                "RepoTags": ["busybox:latest"],
+                "Layers": ["other/layer.tar"],
+            },
+        ])
+        .to_string();
+        let app_config = config(&[&lower, &upper]);
+        let busybox_config = config(&[&other]);
+
+        fs::write(
+            path,
+            tar_of(&[
+                ("lower/layer.tar", &lower),
+                ("upper/layer.tar", &upper),
+                ("other/layer.tar", &other),
+                ("app.json", app_config.as_bytes()),
+                ("busybox.json", busybox_config.as_bytes()),
+                ("manifest.json", manifest.as_bytes()),
+            ]),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_normalize_tag() {
+        assert_eq!(normalize_tag("busybox"), "busybox:latest");
+        assert_eq!(normalize_tag("docker.io/library/busybox"), "busybox:latest");
+        assert_eq!(normalize_tag("example/app:v1"), "example/app:v1");
+        assert_eq!(
+            normalize_tag("registry:5000/app"),
+            "registry:5000/app:latest"
+        );
+    }
+
+    #[test]
+    fn test_is_docker_archive() {
+        let dir = tempdir().unwrap();
+        let archive = dir.path().join("image.tar");
+        write_archive(&archive);
+        assert!(is_docker_archive(&archive).unwrap());
+
+        let rootfs = dir.path().join("rootfs.tar");
+        fs::write(&rootfs, tar_of(&[("etc/os-release", b"x")])).unwrap();
+        assert!(!is_docker_archive(&rootfs).unwrap());
+
+        // 根目录的 manifest.json 之前出现了 rootfs 条目
+        let rootfs = dir.path().join("rootfs-manifest.tar");
+        fs::write(
+            &rootfs,
+            tar_of(&[("etc/os-release", b"x"), ("manifest.json", b"[]")]),
+        )
+        .unwrap();
+        assert!(!is_docker_archive(&rootfs).unwrap());
+    }
+
+    #[test]
+    fn test_is_archive_entry() {
+        let id = "0123456789abcdef".repeat(4);
+        assert!(is_archive_entry(Path::new("")));
+        assert!(is_archive_entry(Path::new("repositories")));
+        assert!(is_archive_entry(Path::new("blobs/sha256")));
+        assert!(is_archive_entry(&Path::new(&id).join("layer.tar")));
+        assert!(is_archive_entry(Path::new(&format!("{}.json", id))));
+        assert!(!is_archive_entry(Path::new("etc/passwd")));
+        assert!(!is_archive_entry(Path::new("lower/layer.tar")));
+    }
+
+    #[tokio::test]
+    async fn test_pull_docker_archive() {
+        let logger = test_logger();
+        let dir = tempdir().unwrap();
+        let archive = dir.path().join("image.tar");
+        write_archive(&archive);
+        let work_dir = dir.path().join("work");
+        fs::create_dir_all(&work_dir).unwrap();
+
+        // 多个镜像时必须指定名称
+        let rootfs = dir.path().join("rootfs");
+        let target = LayerTarget::Rootfs(rootfs.clone());
+        let image = archive.display().to_string();
+        assert!(
+            pull_docker_archive(&image, &work_dir, "c1", &target, &logger)
+                .await
+                .is_err()
+        );
+        let missing = format!("{}:example/app:v2", archive.display());
+        assert!(
+            pull_docker_archive(&missing, &work_dir, "c1", &target, &logger)
+                .await
+                .is_err()
+        );
+
+        let image = format!("{}:example/app:v1", archive.display());
+        let loaded = pull_docker_archive(&image, &work_dir, "c1", &target, &logger)
+            .await
+            .unwrap();
+        assert_eq!(loaded.record.names, vec!["example/app:v1"]);
+        let config = &loaded.record.config;
+        assert_eq!(
+            config.config().as_ref().unwrap().cmd().as_ref().unwrap(),
+            &vec!["/bin/sh".to_string()]
+        );
+        assert_eq!(
+            fs::read_to_string(rootfs.join("etc/os-release")).unwrap(),
+            "upper"
+        );
+        assert!(!rootfs.join("tmp/junk").exists());
+        assert!(!work_dir.join("docker-c1").exists());
+
+        let rootfs = dir.path().join("busybox");
+        let target = LayerTarget::Rootfs(rootfs.clone());
+        let image = format!("{}:docker.io/library/busybox", archive.display());
+        pull_docker_archive(&image, &work_dir, "c2", &target, &logger)
+            .await
+            .unwrap();
+        assert_eq!(
+            fs::read_to_string(rootfs.join("etc/os-release")).unwrap(),
+            "other"
+        );
+
+        // 解压到镜像层存储
+        let target = LayerTarget::Store(LayerStore::new(dir.path().join("layers")));
+        let image = format!("{}:example/app:v1", archive.display());
+        let loaded = pull_docker_archive(&image, &work_dir, "c3", &target, &logger)
+            .await
+            .unwrap();
+        assert_eq!(loaded.lowers.len(), 2);
+        assert_eq!(
+            fs::read_to_string(loaded.lowers[1].join("etc/os-release")).unwrap(),
+            "upper"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_pull_docker_archive_diff_id_mismatch() {
+        let logger = test_logger();
+        let dir = tempdir().unwrap();
+        let layer = tar_of(&[("etc/os-release", b"layer")]);
+        let config = json!({
+            "architecture": "amd64",
+            "os": "linux",
+            "rootfs": { "type": "layers", "diff_ids": [format!("sha256:{}", "0".repeat(64))] },
+        })
+        .to_string();
+        let manifest = json!([{
+            "Config": "config.json",
+            "RepoTags": ["example/bad:v1"],
+            "Layers": ["layer/layer.tar"],
+        }])
+        .to_string();
+        let archive = dir.path().join("bad.tar");
+        fs::write(
+            &archive,
+            tar_of(&[
+                ("layer/layer.tar", &layer),
+                ("config.json", config.as_bytes()),
+                ("manifest.json", manifest.as_bytes()),
+            ]),
+        )
+        .unwrap();
+        let work_dir = dir.path().join("work");
+        fs::create_dir_all(&work_dir).unwrap();
+        let image = archive.display().to_string();
+
+        let targets = [
+            LayerTarget::Rootfs(dir.path().join("rootfs")),
+            LayerTarget::Store(LayerStore::new(dir.path().join("layers"))),
+        ];
+        for target in &targets {
+            let err = pull_docker_archive(&image, &work_dir, "c1", target, &logger)
+                .await
+                .unwrap_err();
+            assert!(
+                format!("{:#}", err).contains("diff_id mismatch"),
+                "{:#}",
+                err
+            );
+        }
+    }
+}
@@ -0,0 +1,549 @@
+//! 镜像索引
+//!
+//! 带镜像配置的镜像（OCI 镜像和 docker save 归档）加载后记录在 `{image_root}/images/<hex>.json`，
+//! 文件名为镜像 ID（镜像配置的 sha256 摘要），记录镜像名称、manifest 摘要、manifest 和镜像配置。
+//! 镜像名称在索引中唯一，新加载的镜像使用已有名称时，该名称从旧镜像上移除。
+//!
+//! 容器 bundle 目录中的 `image.json` 记录容器使用的镜像和 overlay 下层目录：
+//! 被容器使用的镜像不能删除，被镜像或容器使用的镜像层不会被清理。
+//!
+//! 加载镜像时持有 `{image_root}/images/lock` 的共享锁，删除镜像和镜像层时持有排他锁，
+//! 刚解压、尚未记录到索引中的镜像层不会被清理。
+
+use std::{
+    collections::HashSet,
+    fs::{self, File, OpenOptions},
+    io,
+    os::unix::io::AsRawFd,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result, anyhow, bail};
+use nix::fcntl::{FlockArg, flock};
+use oci_spec::image::{ImageConfiguration, ImageManifest};
+use serde::{Deserialize, Serialize};
+use slog::Logger;
+
+use super::{container_base, docker::normalize_tag, layer_dir, store::LayerStore};
+
+/// 镜像索引目录名
+const INDEX_DIR: &str = "images";
+
+/// 索引目录中的锁文件
+const LOCK_FILE: &str = "lock";
+
+/// 容器 bundle 目录中记录镜像引用的文件
+pub const CONTAINER_IMAGE_FILE: &str = "image.json";
+
+/// 镜像索引目录 `{image_root}/images`
+pub fn index_dir(image_root: &Path) -> PathBuf {
+    image_root.join(INDEX_DIR)
+}
+
+/// 索引中的镜像
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImageRecord {
+    /// 镜像 ID，即镜像配置的摘要 `sha256:<hex>`
+    pub id: String,
+    /// 镜像名称（`name:tag`），未命名的镜像为空
+    #[serde(default)]
+    pub names: Vec<String>,
+    /// 镜像 manifest 的摘要，只有 OCI 镜像才有
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub digest: Option<String>,
+    /// 镜像层文件的大小之和（字节，压缩后）
+    pub size: u64,
+    /// 镜像 manifest，只有 OCI 镜像才有
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub manifest: Option<ImageManifest>,
+    /// 镜像配置
+    pub config: ImageConfiguration,
+}
+
+impl ImageRecord {
+    /// 镜像层的 diff_id，从底层到顶层
+    pub fn layers(&self) -> &[String] {
+        self.config.rootfs().diff_ids()
+    }
+
+    /// 镜像的创建时间（RFC 3339）
+    pub fn created(&self) -> Option<&str> {
+        self.config.created().as_deref()
+    }
+
+    /// 镜像引用是否精确匹配镜像 ID、manifest 摘要或名称
+    fn matches(&self, reference: &str) -> bool {
+        if self.id == reference || self.digest.as_deref() == Some(reference) {
+            return true;
+        }
+        let wanted = normalize_tag(reference);
+        self.names.iter().any(|name| normalize_tag(name) == wanted)
+    }
+
+    /// 镜像引用是否为镜像 ID 的前缀（可省略 `sha256:`）
+    fn matches_prefix(&self, reference: &str) -> bool {
+        let prefix = reference.strip_prefix("sha256:").unwrap_or(reference);
+        !prefix.is_empty()
+            && prefix.bytes().all(|b| b.is_ascii_hexdigit())
+            && self
+                .id
+                .strip_prefix("sha256:")
+                .is_some_and(|hex| hex.starts_with(&prefix.to_ascii_lowercase()))
+    }
+
+    /// 索引文件的路径 `{image_root}/images/<hex>.json`
+    fn path(&self, image_root: &Path) -> Result<PathBuf> {
+        let hex = self
+            .id
+            .strip_prefix("sha256:")
+            .filter(|hex| hex.len() == 64 && hex.bytes().all(|b| b.is_ascii_hexdigit()))
+            .ok_or_else(|| anyhow!("Invalid image ID: {}", self.id))?;
+        Ok(index_dir(image_root).join(format!("{}.json", hex)))
+    }
+}
+
+/// 容器使用的镜像，保存在容器 bundle 目录中
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct ContainerImage {
+    /// 镜像 ID，镜像不在索引中时为空
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image: Option<String>,
+    /// overlay 的下层目录，从底层到顶层
+    #[serde(default)]
+    pub lowers: Vec<PathBuf>,
+}
+
+/// 镜像索引的文件锁，释放时自动解锁
+#[derive(Debug)]
+pub struct IndexLock {
+    _file: File,
+}
+
+impl IndexLock {
+    /// 获取共享锁，加载镜像时使用
+    pub fn shared(image_root: &Path) -> Result<Self> {
+        Self::lock(image_root, FlockArg::LockShared)
+    }
+
+    /// 获取排他锁，删除镜像和镜像层时使用
+    pub fn exclusive(image_root: &Path) -> Result<Self> {
+        Self::lock(image_root, FlockArg::LockExclusive)
+    }
+
+    fn lock(image_root: &Path, arg: FlockArg) -> Result<Self> {
+        let dir = index_dir(image_root);
+        fs::create_dir_all(&dir)
+            .with_context(|| format!("Failed to create image index directory: {:?}", dir))?;
+        let path = dir.join(LOCK_FILE);
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&path)
+            .with_context(|| format!("Failed to open {:?}", path))?;
+        flock(file.as_raw_fd(), arg).with_context(|| format!("Failed to lock {:?}", path))?;
+        Ok(Self { _file: file })
+    }
+}
+
+/// 清理结果
+#[derive(Debug, Default)]
+pub struct PruneReport {
+    /// 删除的镜像 ID
+    pub images: Vec<String>,
+    /// 删除的镜像层数
+    pub layers: usize,
+}
+
+/// 将镜像记录到索引中
+///
+/// 同一镜像再次加载时保留原有的名称；其他镜像使用的同名名称被移除。
+///
+/// # 参数
+/// - `image_root`: 镜像根目录
+/// - `record`: 镜像记录
+/// - `logger`: 日志记录器
+pub fn save_image(image_root: &Path, record: &ImageRecord, logger: &Logger) -> Result<()> {
+    let mut record = record.clone();
+    let wanted: HashSet<String> = record.names.iter().map(|n| normalize_tag(n)).collect();
+
+    for mut other in list_images(image_root)? {
+        if other.id == record.id {
+            for name in other.names {
+                if !wanted.contains(&normalize_tag(&name)) {
+                    record.names.push(name);
+                }
+            }
+            continue;
+        }
+        let before = other.names.len();
+        other
+            .names
+            .retain(|name| !wanted.contains(&normalize_tag(name)));
+        if other.names.len() != before {
+            debug!(logger, "Name moved to new image"; "image" => &other.id);
+            write_record(image_root, &other)?;
+        }
+    }
+
+    write_record(image_root, &record)?;
+    debug!(logger, "Image saved to index";
+        "id" => &record.id, "names" => record.names.join(","));
+    Ok(())
+}
+
+/// 列出索引中的全部镜像
+pub fn list_images(image_root: &Path) -> Result<Vec<ImageRecord>> {
+    let dir = index_dir(image_root);
+    let entries = match fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {:?}", dir)),
+    };
+
+    let mut images = Vec::new();
+    for entry in entries {
+        let path = entry?.path();
+        if path.extension().is_none_or(|ext| ext != "json") {
+            continue;
+        }
+        let record: ImageRecord = serde_json::from_reader(
+            File::open(&path).with_context(|| format!("Failed to open {:?}", path))?,
+        )
+        .with_context(|| format!("Invalid image record {:?}", path))?;
+        images.push(record);
+    }
+    images.sort_by(|a, b| a.id.cmp(&b.id));
+    Ok(images)
+}
+
+/// 按镜像 ID、ID 前缀、manifest 摘要或名称查找镜像
+///
+/// # 参数
+/// - `image_root`: 镜像根目录
+/// - `reference`: 镜像引用
+pub fn find_image(image_root: &Path, reference: &str) -> Result<ImageRecord> {
+    resolve(list_images(image_root)?, reference)
+}
+
+/// 删除镜像，并清理不再被使用的镜像层
+///
+/// # 参数
+/// - `image_root`: 镜像根目录
+/// - `reference`: 镜像引用
+/// - `logger`: 日志记录器
+///
+/// # 返回
+/// 被删除的镜像
+pub fn remove_image(image_root: &Path, reference: &str, logger: &Logger) -> Result<ImageRecord> {
+    let _lock = IndexLock::exclusive(image_root)?;
+    let record = find_image(image_root, reference)?;
+
+    let users: Vec<String> = container_images(image_root)?
+        .into_iter()
+        .filter(|(_, c)| c.image.as_deref() == Some(record.id.as_str()))
+        .map(|(id, _)| id)
+        .collect();
+    if !users.is_empty() {
+        bail!(
+            "Image {} is in use by container(s): {}",
+            reference,
+            users.join(", ")
+        );
+    }
+
+    remove_record(image_root, &record)?;
+    info!(logger, "Image removed"; "id" => &record.id);
+    remove_unused_layers(image_root, logger)?;
+    Ok(record)
+}
+
+/// 删除没有被容器使用的镜像，并清理不再被使用的镜像层
+///
+/// # 参数
+/// - `image_root`: 镜像根目录
+/// - `all`: 删除所有未使用的镜像，否则只删除未命名的镜像
+/// - `logger`: 日志记录器
+pub fn prune_images(image_root: &Path, all: bool, logger: &Logger) -> Result<PruneReport> {
+    let _lock = IndexLock::exclusive(image_root)?;
+    let used: HashSet<String> = container_images(image_root)?
+        .into_iter()
+        .filter_map(|(_, c)| c.image)
+        .collect();
+
+    let mut report = PruneReport::default();
+    for record in list_images(image_root)? {
+        if used.contains(&record.id) || (!all && !record.names.is_empty()) {
+            continue;
+        }
+        remove_record(image_root, &record)?;
+        info!(logger, "Image removed"; "id" => &record.id);
+        report.images.push(record.id);
+    }
+    report.layers = remove_unused_layers(image_root, logger)?;
+    Ok(report)
+}
+
+/// 记录容器使用的镜像
+///
+/// # 参数
+/// - `bundle_path`: 容器 bundle 目录
+/// - `image`: 容器使用的镜像
+pub fn save_container_image(bundle_path: &Path, image: &ContainerImage) -> Result<()> {
+    let path = bundle_path.join(CONTAINER_IMAGE_FILE);
+    fs::write(&path, serde_json::to_vec_pretty(image)?)
+        .with_context(|| format!("Failed to write {:?}", path))
+}
+
+/// 所有容器使用的镜像
+///
+/// # 返回
+/// 容器 ID 和容器使用的镜像
+fn container_images(image_root: &Path) -> Result<Vec<(String, ContainerImage)>> {
+    let base = container_base(image_root);
+    let entries = match fs::read_dir(&base) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {:?}", base)),
+    };
+
+    let mut containers = Vec::new();
+    for entry in entries {
+        let entry = entry?;
+        let path = entry.path().join(CONTAINER_IMAGE_FILE);
+        let image = match File::open(&path) {
+            Ok(file) => serde_json::from_reader(file)
+                .with_context(|| format!("Invalid container image record {:?}", path))?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e).with_context(|| format!("Failed to open {:?}", path)),
+        };
+        containers.push((entry.file_name().to_string_lossy().to_string(), image));
+    }
+    Ok(containers)
+}
+
+/// 删除不被任何镜像或容器使用的镜像层，调用方需持有排他锁
+///
+/// # 返回
+/// 删除的镜像层数
+fn remove_unused_layers(image_root: &Path, logger: &Logger) -> Result<usize> {
+    let store = LayerStore::new(layer_dir(image_root));
+    let mut used: HashSet<PathBuf> = container_images(image_root)?
+        .into_iter()
+        .flat_map(|(_, c)| c.lowers)
+        .collect();
+    for record in list_images(image_root)? {
+        for diff_id in record.layers() {
+            used.insert(store.layer_path(diff_id)?);
+        }
+    }
+
+    let mut removed = 0;
+    for (diff_id, path) in store.layers()? {
+        if !used.contains(&path) {
+            store.remove(&diff_id, logger)?;
+            removed += 1;
+        }
+    }
+    // 持有排他锁时没有正在进行的解压，临时目录都是中断后残留的
+    let tmp = store.remove_tmp()?;
+    if tmp > 0 {
+        warn!(logger, "Removed stale temporary layer directories"; "count" => tmp);
+    }
+    Ok(removed)
+}
+
+/// 从镜像列表中解析镜像引用
+///
+/// 精确匹配镜像 ID、manifest 摘要或名称优先，其次匹配镜像 ID 前缀，匹配到多个镜像时报错。
+fn resolve(images: Vec<ImageRecord>, reference: &str) -> Result<ImageRecord> {
+    let (exact, others): (Vec<_>, Vec<_>) = images.into_iter().partition(|i| i.matches(reference));
+    let mut candidates = if exact.is_empty() {
+        others
+            .into_iter()
+            .filter(|i| i.matches_prefix(reference))
+            .collect()
+    } else {
+        exact
+    };
+
+    match candidates.len() {
+        0 => bail!("Image {} not found", reference),
+        1 => Ok(candidates.remove(0)),
+        _ => bail!(
+            "Image reference {} is ambiguous: {}",
+            reference,
+            candidates
+                .iter()
+                .map(|i| i.id.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    }
+}
+
+/// 写入镜像记录，先写临时文件再重命名
+fn write_record(image_root: &Path, record: &ImageRecord) -> Result<()> {
+    let path = record.path(image_root)?;
+    let tmp = path.with_extension(format!("json.{}", std::process::id()));
+    fs::write(&tmp, serde_json::to_vec_pretty(record)?)
+        .with_context(|| format!("Failed to write {:?}", tmp))?;
+    fs::rename(&tmp, &path).with_context(|| format!("Failed to rename {:?} to {:?}", tmp, path))
+}
+
+/// 删除镜像记录
+fn remove_record(image_root: &Path, record: &ImageRecord) -> Result<()> {
+    let path = record.path(image_root)?;
+    fs::remove_file(&path).with_context(|| format!("Failed to remove {:?}", path))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use slog::Drain;
+    use tempfile::tempdir;
+
+    use super::*;
+
+    fn test_logger() -> Logger {
+        let decorator = slog_term::PlainSyncDecorator::new(std::io::stdout());
+        let drain = slog_term::FullFormat::new(decorator).build().fuse();
+        Logger::root(drain, o!())
+    }
+
+    fn record(id: char, names: &[&str], layers: &[char]) -> ImageRecord {
+        let diff_ids: Vec<String> = layers
+            .iter()
+            .map(|c| format!("sha256:{}", c.to_string().repeat(64)))
+            .collect();
+        let config = json!({
+            "architecture": "amd64",
+            "os": "linux",
+            "rootfs": { "type": "layers", "diff_ids": diff_ids },
+        });
+        ImageRecord {
+            id: format!("sha256:{}", id.to_string().repeat(64)),
+            names: names.iter().map(|n| n.to_string()).collect(),
+            digest: None,
+            size: 0,
+            manifest: None,
+            config: serde_json::from_value(config).unwrap(),
+        }
+    }
+
+    /// 在镜像层存储中创建镜像层
+    fn add_layer(image_root: &Path, layer: char) -> PathBuf {
+        let store = LayerStore::new(layer_dir(image_root));
+        let path = store
+            .layer_path(&format!("sha256:{}", layer.to_string().repeat(64)))
+            .unwrap();
+        fs::create_dir_all(&path).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_save_and_find() {
+        let logger = test_logger();
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+
+        save_image(root, &record('a', &["busybox:latest"], &['1']), &logger).unwrap();
+        save_image(root, &record('b', &["example/app:v1"], &['1']), &logger).unwrap();
+
+        assert_eq!(
+            find_image(root, "busybox").unwrap().id,
+            record('a', &[], &[]).id
+        );
+        assert_eq!(
+            find_image(root, "docker.io/library/busybox:latest")
+                .unwrap()
+                .names,
+            vec!["busybox:latest"]
+        );
+        assert_eq!(
+            find_image(root, "bbbb").unwrap().names,
+            vec!["example/app:v1"]
+        );
+        assert!(find_image(root, "cccc").is_err());
+
+        // 新镜像使用已有名称时，名称从旧镜像上移除
+        save_image(root, &record('c', &["busybox"], &['2']), &logger).unwrap();
+        assert_eq!(
+            find_image(root, "busybox").unwrap().id,
+            record('c', &[], &[]).id
+        );
+        assert!(find_image(root, "aaaa").unwrap().names.is_empty());
+
+        // 再次加载同一镜像时保留原有名称
+        save_image(root, &record('b', &["app:v2"], &['1']), &logger).unwrap();
+        let names = find_image(root, "bbbb").unwrap().names;
+        assert_eq!(names, vec!["app:v2", "example/app:v1"]);
+        assert_eq!(list_images(root).unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_remove_in_use() {
+        let logger = test_logger();
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        let shared = add_layer(root, '1');
+        let own = add_layer(root, '2');
+        save_image(root, &record('a', &["app:v1"], &['1', '2']), &logger).unwrap();
+        save_image(root, &record('b', &["app:v2"], &['1']), &logger).unwrap();
+
+        let bundle = container_base(root).join("c1");
+        fs::create_dir_all(&bundle).unwrap();
+        let image = ContainerImage {
+            image: Some(record('a', &[], &[]).id),
+            lowers: vec![shared.clone(), own.clone()],
+        };
+        save_container_image(&bundle, &image).unwrap();
+
+        let err = remove_image(root, "app:v1", &logger).unwrap_err();
+        assert!(err.to_string().contains("c1"));
+
+        // 被其他镜像使用的镜像层保留
+        fs::remove_dir_all(&bundle).unwrap();
+        remove_image(root, "app:v1", &logger).unwrap();
+        assert!(shared.exists());
+        assert!(!own.exists());
+        assert!(find_image(root, "app:v1").is_err());
+    }
+
+    #[test]
+    fn test_prune() {
+        let logger = test_logger();
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        let named = add_layer(root, '1');
+        let dangling = add_layer(root, '2');
+        let used = add_layer(root, '3');
+        // 不在索引中的镜像（如本地 tar 文件）的镜像层
+        let plain = add_layer(root, '4');
+        let orphan = add_layer(root, '5');
+        save_image(root, &record('a', &["app:v1"], &['1']), &logger).unwrap();
+        save_image(root, &record('b', &[], &['2']), &logger).unwrap();
+        save_image(root, &record('c', &[], &['3']), &logger).unwrap();
+
+        for (id, image) in [("c1", Some(record('c', &[], &[]).id)), ("c2", None)] {
+            let bundle = container_base(root).join(id);
+            fs::create_dir_all(&bundle).unwrap();
+            let lowers = match image {
+                Some(_) => vec![used.clone()],
+                None => vec![plain.clone()],
+            };
+            save_container_image(&bundle, &ContainerImage { image, lowers }).unwrap();
+        }
+
+        let report = prune_images(root, false, &logger).unwrap();
+        assert_eq!(report.images, vec![record('b', &[], &[]).id]);
+        assert_eq!(report.layers, 2);
+        assert!(named.exists() && used.exists() && plain.exists());
+        assert!(!dangling.exists() && !orphan.exists());
+
+        let report = prune_images(root, true, &logger).unwrap();
+        assert_eq!(report.images, vec![record('a', &[], &[]).id]);
+        assert!(!named.exists());
+        assert_eq!(list_images(root).unwrap().len(), 1);
+    }
+}
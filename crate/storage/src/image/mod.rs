@@ -1,51 +1,165 @@
 //! # 容器镜像管理
 //!
 //! 提供容器镜像的拉取、解压和管理功能。
+//!
+//! 以 root 运行时，镜像层解压到共享的镜像层存储（见 [`store`]），容器 rootfs 为 overlay 挂载，
+//! 同一镜像的多个容器不再各自复制一份完整的 rootfs。
 
 pub mod cdh;
-
-use std::{fs, path::Path};
-
-use anyhow::{Context, Result, bail};
+pub mod docker;
+pub mod index;
+pub mod oci;
+pub mod store;
+pub mod unpack;
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result, anyhow, bail};
+use nix::{
+    sched::{CloneFlags, unshare},
+    unistd::{SysconfVar, chdir, geteuid, sysconf},
+};
+use oci_spec::image::ImageConfiguration;
 use safe_path::scoped_join;
 use slog::Logger;
 
-/// 镜像工作目录
+use self::{
+    index::{ContainerImage, ImageRecord, IndexLock},
+    store::LayerStore,
+};
+use crate::mount;
+
+/// 默认的镜像根目录（root 用户）
+///
+/// 镜像和容器 rootfs 需要跨重启保留，与 `/run` 下的运行时状态分开存放。
+pub const DEFAULT_IMAGE_ROOT: &str = "/var/lib/runcell";
+
+/// 镜像工作目录 `{image_root}/image`
 ///
 /// 用于存储镜像层和解压后的内容。
-pub const IMAGE_WORK_DIR: &str = "/tmp/runcell/image/";
+pub fn image_work_dir(image_root: &Path) -> PathBuf {
+    image_root.join("image")
+}
 
-/// 容器基础��录
+/// 容器基础目录 `{image_root}/containers`
 ///
 /// 用于存储容器 bundle。
-pub const CONTAINER_BASE: &str = "/tmp/runcell/containers/";
+pub fn container_base(image_root: &Path) -> PathBuf {
+    image_root.join("containers")
+}
+
+/// 镜像层存储目录 `{image_root}/layers`
+///
+/// 按 diff_id 存放解压后的镜像层，由所有容器共享。
+pub fn layer_dir(image_root: &Path) -> PathBuf {
+    image_root.join("layers")
+}
+
+/// 拉取的镜像
+#[derive(Debug)]
+pub struct PulledImage {
+    /// rootfs 路径
+    pub rootfs: String,
+    /// 镜像配置，只有 OCI 镜像和 docker save 归档才有
+    pub config: Option<ImageConfiguration>,
+}
+
+/// 从镜像源加载的镜像
+#[derive(Debug)]
+pub struct LoadedImage {
+    /// 镜像索引中的记录
+    pub record: ImageRecord,
+    /// 镜像层在存储中的目录，从底层到顶层，解压到 rootfs 时为空
+    pub lowers: Vec<PathBuf>,
+}
+
+/// 镜像层的解压目标
+#[derive(Debug, Clone)]
+pub enum LayerTarget {
+    /// 按顺序解压到同一个 rootfs 目录，whiteout 直接删除下层的内容
+    Rootfs(PathBuf),
+    /// 解压到共享的镜像层存储，rootfs 由各层目录组成 overlay 挂载
+    Store(LayerStore),
+}
+
+impl LayerTarget {
+    /// 应用一个镜像层
+    ///
+    /// # 参数
+    /// - `diff_id`: 镜像配置中该层的 diff_id，解压后校验
+    /// - `layer`: 返回镜像层文件的路径，镜像层已在存储中时不会调用
+    /// - `logger`: 日志记录器
+    ///
+    /// # 返回
+    /// 解压到存储时返回该层在存储中的目录
+    fn apply(
+        &self,
+        diff_id: &str,
+        layer: impl FnOnce() -> Result<PathBuf>,
+        logger: &Logger,
+    ) -> Result<Option<PathBuf>> {
+        match self {
+            LayerTarget::Rootfs(rootfs) => {
+                let actual = unpack::unpack_layer_file(&layer()?, rootfs, logger)?;
+                if !actual.eq_ignore_ascii_case(diff_id) {
+                    bail!(
+                        "Layer diff_id mismatch: expected {}, got {}",
+                        diff_id,
+                        actual
+                    );
+                }
+                Ok(None)
+            }
+            LayerTarget::Store(store) if store.contains(diff_id) => {
+                debug!(logger, "Layer already unpacked"; "diff_id" => diff_id);
+                store.layer_path(diff_id).map(Some)
+            }
+            LayerTarget::Store(store) => store.unpack(diff_id, &layer()?, logger).map(Some),
+        }
+    }
+}
 
 /// 拉取并解压镜像
 ///
 /// 从镜像源拉取容器镜像,并解压到指定的 bundle 目录。
+/// 以 root 运行时镜像层解压到共享的镜像层存储，`{bundle}/rootfs` 为 overlay 挂载，
+/// 容器的修改写入 `{bundle}/upper`；否则镜像层依次解压到 `{bundle}/rootfs`。
 ///
 /// # 参数
 /// - `image`: 镜像名称或路径
+/// - `image_root`: 镜像根目录，bundle 位于 `{image_root}/containers/{container_id}`
 /// - `container_id`: 容器 ID
 /// - `logger`: 日志记录器
 ///
 /// # 返回
-/// 返回 rootfs 路径
+/// 返回 rootfs 路径和镜像配置
 ///
 /// # 工作流程
 /// 1. 验证容器 ID
 /// 2. 创建 bundle 目录
 /// 3. 拉取或加载镜像
-/// 4. 解压镜像到 rootfs
-/// 5. 返回 rootfs 路径
+/// 4. 解压镜像层到镜像层存储或 rootfs
+/// 5. 挂载 overlay rootfs
+/// 6. 返回 rootfs 路径
 ///
 /// # 镜像格式
 /// 支持以下格式:
-/// - `file:///path/to/image.tar`: 本地 tar 镜像
-/// - `dir:///path/to/bundle`: 本地 bundle 目录
+/// - `file:///path/to/image.tar`: 本地 tar 镜像，`docker save` 归档会被自动识别
+/// - `docker-archive:///path/to/image.tar[:repo:tag]`: `docker save` 归档
+/// - `oci:///path/to/layout[:tag]`: OCI 镜像布局目录
+/// - `oci-archive:///path/to/image.tar[:tag]`: OCI 镜像布局的 tar 归档
+/// - `dir:///path/to/bundle`: 本地 bundle 目录，复制到容器的 bundle 中
 /// - `docker://registry/image:tag`: Docker 镜像 (需要 CDH 支持)
 /// - 其他: 视为本地路径
-pub async fn pull_and_extract(image: &str, container_id: &str, logger: &Logger) -> Result<String> {
+pub async fn pull_and_extract(
+    image: &str,
+    image_root: &Path,
+    container_id: &str,
+    logger: &Logger,
+) -> Result<PulledImage> {
     info!(logger, "Pulling and extracting image"; "image" => image, "container_id" => container_id);
 
     // 验证容器 ID (基本检查)
@@ -59,17 +173,17 @@ pub async fn pull_and_extract(image: &str, container_id: &str, logger: &Logger)
 
     // 创建 bundle 目录
     // 确保容器基础目录存在
-    let container_base = Path::new(CONTAINER_BASE);
+    let container_base = container_base(image_root);
     if !container_base.exists() {
-        fs::create_dir_all(container_base).with_context(|| {
+        fs::create_dir_all(&container_base).with_context(|| {
             format!(
-                "Failed to create container base directory: {}",
-                CONTAINER_BASE
+                "Failed to create container base directory: {:?}",
+                container_base
             )
         })?;
     }
 
-    let bundle_path = scoped_join(CONTAINER_BASE, container_id).with_context(|| {
+    let bundle_path = scoped_join(&container_base, container_id).with_context(|| {
         format!(
             "Failed to create bundle path for container {}",
             container_id
@@ -79,85 +193,325 @@ pub async fn pull_and_extract(image: &str, container_id: &str, logger: &Logger)
     fs::create_dir_all(&bundle_path)
         .with_context(|| format!("Failed to create bundle directory: {:?}", bundle_path))?;
 
-    // 根据镜像格式选择处理方式
-    if image.starts_with("file://") {
-        // 本地 tar 文件
+    let rootfs = scoped_join(&bundle_path, "rootfs")
+        .with_context(|| "Failed to create rootfs path".to_string())?;
+    if is_rootfs_mounted(&rootfs)? {
+        bail!("Rootfs of container {} is still mounted", container_id);
+    }
+
+    // 持有共享锁直到镜像记录到索引中，期间解压的镜像层不会被清理
+    let _lock = IndexLock::shared(image_root)?;
+
+    // 挂载 overlayfs 需要 root 权限，非 root 用户将镜像层依次解压到 rootfs
+    let target = if geteuid().is_root() {
+        LayerTarget::Store(LayerStore::new(layer_dir(image_root)))
+    } else {
+        LayerTarget::Rootfs(rootfs.clone())
+    };
+
+    // 根据镜像格式选择处理方式，得到镜像记录和 overlay 的下层目录（从底层到顶层）
+    let (record, lowers) = if image.starts_with("file://") {
         let tar_path = image.trim_start_matches("file://");
-        extract_tar_image(tar_path, &bundle_path, logger).await?;
+        if Path::new(tar_path).is_file() && docker::is_docker_archive(Path::new(tar_path))? {
+            // docker save 归档
+            let work_dir = create_work_dir(image_root)?;
+            let loaded =
+                docker::pull_docker_archive(tar_path, &work_dir, container_id, &target, logger)
+                    .await?;
+            (Some(loaded.record), loaded.lowers)
+        } else {
+            // 本地 tar 文件
+            (None, extract_tar_image(tar_path, &target, logger).await?)
+        }
+    } else if image.starts_with("docker-archive://") {
+        // docker save 归档
+        let reference = image.trim_start_matches("docker-archive://");
+        let work_dir = create_work_dir(image_root)?;
+        let loaded =
+            docker::pull_docker_archive(reference, &work_dir, container_id, &target, logger)
+                .await?;
+        (Some(loaded.record), loaded.lowers)
+    } else if image.starts_with("oci://") {
+        // OCI 镜像布局目录
+        let reference = image.trim_start_matches("oci://");
+        let loaded = oci::pull_oci_layout(reference, &target, logger).await?;
+        (Some(loaded.record), loaded.lowers)
+    } else if image.starts_with("oci-archive://") {
+        // OCI 镜像布局的 tar 归档
+        let reference = image.trim_start_matches("oci-archive://");
+        let work_dir = create_work_dir(image_root)?;
+        let loaded =
+            oci::pull_oci_archive(reference, &work_dir, container_id, &target, logger).await?;
+        (Some(loaded.record), loaded.lowers)
     } else if image.starts_with("dir://") {
         // 本地目录
         let dir_path = image.trim_start_matches("dir://");
         copy_local_bundle(dir_path, &bundle_path, logger).await?;
+        (None, Vec::new())
     } else if image.starts_with("docker://") || image.contains('/') {
         // Docker 镜像或远程镜像 - 需要 CDH 支持
         #[cfg(feature = "cdh")]
-        {
+        let lowers = {
             cdh::pull_image_via_cdh(image, &bundle_path, logger).await?;
-        }
+            Vec::new()
+        };
         #[cfg(not(feature = "cdh"))]
-        {
+        let lowers = {
             warn!(logger, "CDH support not enabled, treating as local path"; "image" => image);
             // 尝试作为本地路径处理
             if Path::new(image).exists() {
                 copy_local_bundle(image, &bundle_path, logger).await?;
+                Vec::new()
             } else {
                 bail!("Image {} not found and CDH support is not enabled", image);
             }
-        }
+        };
+        (None, lowers)
     } else {
         // 默认作为本地路径处理
         if Path::new(image).exists() {
             copy_local_bundle(image, &bundle_path, logger).await?;
+            (None, Vec::new())
         } else {
             bail!("Image path does not exist: {}", image);
         }
+    };
+
+    match &target {
+        LayerTarget::Store(store) if !lowers.is_empty() => {
+            mount_rootfs(store, &lowers, &bundle_path, &rootfs, logger)?;
+        }
+        _ => {
+            // 解压到 rootfs，或镜像没有任何镜像层
+            fs::create_dir_all(&rootfs)
+                .with_context(|| format!("Failed to create rootfs directory: {:?}", rootfs))?;
+        }
     }
 
-    // 返回 rootfs 路径
-    let rootfs_path = scoped_join(&bundle_path, "rootfs")
-        .with_context(|| "Failed to create rootfs path".to_string())?;
+    // 只有带镜像配置的镜像才记录到索引中，容器同时记录使用的镜像和下层目录
+    if let Some(record) = &record {
+        index::save_image(image_root, record, logger)?;
+    }
+    let image_ref = ContainerImage {
+        image: record.as_ref().map(|r| r.id.clone()),
+        lowers,
+    };
+    index::save_container_image(&bundle_path, &image_ref)?;
+
+    info!(logger, "Image extracted successfully"; "rootfs" => rootfs.display().to_string());
+
+    Ok(PulledImage {
+        rootfs: rootfs.display().to_string(),
+        config: record.map(|r| r.config),
+    })
+}
+
+/// 创建镜像工作目录
+fn create_work_dir(image_root: &Path) -> Result<PathBuf> {
+    let work_dir = image_work_dir(image_root);
+    fs::create_dir_all(&work_dir)
+        .with_context(|| format!("Failed to create image work directory: {:?}", work_dir))?;
+    Ok(work_dir)
+}
+
+/// 将 tar 归档解压到临时目录，在其中执行 `f`，完成后删除临时目录
+///
+/// # 参数
+/// - `archive`: tar 归档路径
+/// - `dir`: 临时目录，已存在时先删除
+/// - `logger`: 日志记录器
+/// - `f`: 使用解压后目录的操作
+fn with_extracted_archive<T>(
+    archive: &Path,
+    dir: &Path,
+    logger: &Logger,
+    f: impl FnOnce(&Path) -> Result<T>,
+) -> Result<T> {
+    if dir.exists() {
+        fs::remove_dir_all(dir)
+            .with_context(|| format!("Failed to remove stale directory: {:?}", dir))?;
+    }
+    fs::create_dir_all(dir).with_context(|| format!("Failed to create directory: {:?}", dir))?;
 
-    info!(logger, "Image extracted successfully"; "rootfs" => rootfs_path.display().to_string());
+    let result = unpack::unpack_layer_file(archive, dir, logger).and_then(|_| f(dir));
 
-    Ok(rootfs_path.display().to_string())
+    if let Err(e) = fs::remove_dir_all(dir) {
+        warn!(logger, "Failed to remove temporary directory";
+            "path" => dir.display().to_string(), "error" => e.to_string());
+    }
+    result
+}
+
+/// 拆分本地镜像引用 `path[:name]` 或 `path@digest`
+///
+/// 路径中可能含有 `:`，镜像名称中也可能含有 `:` 和 `/`，因此在每个 `:` 和 `@` 处尝试拆分，
+/// 取文件系统中存在的最长前缀作为路径。整个引用就是已存在的路径，或者没有任何前缀存在时，
+/// 整个引用都视为路径，由调用方报告路径不存在。
+pub fn parse_reference(image: &str) -> (&str, Option<&str>) {
+    if Path::new(image).exists() {
+        return (image, None);
+    }
+    image
+        .match_indices([':', '@'])
+        .rev()
+        .map(|(i, _)| (&image[..i], &image[i + 1..]))
+        .find(|(path, reference)| {
+            !path.is_empty() && !reference.is_empty() && Path::new(path).exists()
+        })
+        .map_or((image, None), |(path, reference)| (path, Some(reference)))
+}
+
+/// 以 overlay 挂载容器 rootfs
+///
+/// 镜像层为只读下层，upper 和 work 目录位于 bundle 目录中，容器的修改只写入 upper 目录。
+/// 挂载选项不能超过一页，下层使用镜像层存储中的短链接，在存储目录中挂载。
+///
+/// # 参数
+/// - `store`: 镜像层存储
+/// - `lowers`: 下层目录，从底层到顶层，都在 `store` 中
+/// - `bundle_path`: bundle 目录
+/// - `rootfs`: 挂载点
+/// - `logger`: 日志记录器
+fn mount_rootfs(
+    store: &LayerStore,
+    lowers: &[PathBuf],
+    bundle_path: &Path,
+    rootfs: &Path,
+    logger: &Logger,
+) -> Result<()> {
+    // 挂载时工作目录为存储目录，其他路径都使用绝对路径
+    let bundle_path = std::path::absolute(bundle_path)
+        .with_context(|| format!("Invalid bundle path: {:?}", bundle_path))?;
+    let rootfs = std::path::absolute(rootfs)
+        .with_context(|| format!("Invalid rootfs path: {:?}", rootfs))?;
+    let upper = bundle_path.join("upper");
+    let work = bundle_path.join("work");
+    // 同名容器残留的修改不应带入新容器
+    for dir in [&upper, &work] {
+        if dir.exists() {
+            fs::remove_dir_all(dir)
+                .with_context(|| format!("Failed to remove stale directory: {:?}", dir))?;
+        }
+    }
+
+    let lower = overlay_lowerdir(store, lowers)?;
+    let (upper, work, target) = (
+        overlay_option(&upper)?,
+        overlay_option(&work)?,
+        overlay_option(&rootfs)?,
+    );
+
+    // 与 mount::mount_overlay 拼接的挂载选项一致，超过一页时无法挂载
+    let data_len = format!("lowerdir={},upperdir={},workdir={}", lower, upper, work).len();
+    let page_size = sysconf(SysconfVar::PAGE_SIZE)
+        .context("Failed to get page size")?
+        .unwrap_or(4096) as usize;
+    if data_len >= page_size {
+        bail!(
+            "Too many layers to mount: overlay options of {} layers exceed {} bytes",
+            lowers.len(),
+            page_size
+        );
+    }
+
+    with_cwd(store.root(), || {
+        mount::mount_overlay(&lower, &upper, &work, &target, &[])
+    })?;
+
+    info!(logger, "Mounted overlay rootfs";
+        "rootfs" => rootfs.display().to_string(), "layers" => lowers.len());
+
+    Ok(())
+}
+
+/// overlay 的 lowerdir 选项：各层在存储中的短链接，最上层在前，相对于存储目录
+fn overlay_lowerdir(store: &LayerStore, lowers: &[PathBuf]) -> Result<String> {
+    Ok(lowers
+        .iter()
+        .rev()
+        .map(|layer| store.link(layer).and_then(|link| overlay_option(&link)))
+        .collect::<Result<Vec<_>>>()?
+        .join(":"))
+}
+
+/// 转换为 overlay 挂载选项中的路径，`:` 和 `,` 是选项的分隔符
+fn overlay_option(path: &Path) -> Result<String> {
+    path.to_str()
+        .filter(|p| !p.contains([':', ',']))
+        .map(str::to_string)
+        .ok_or_else(|| anyhow!("Path cannot be used as overlay option: {:?}", path))
+}
+
+/// 以 `dir` 为工作目录执行 `f`
+///
+/// 工作目录由进程的所有线程共享，这里在单独的线程中 `unshare(CLONE_FS)` 后再切换，
+/// 不影响其他线程。
+fn with_cwd<T: Send>(dir: &Path, f: impl FnOnce() -> Result<T> + Send) -> Result<T> {
+    std::thread::scope(|s| {
+        s.spawn(|| {
+            unshare(CloneFlags::CLONE_FS).context("Failed to unshare fs attributes")?;
+            chdir(dir).with_context(|| format!("Failed to change directory to {:?}", dir))?;
+            f()
+        })
+        .join()
+        .map_err(|_| anyhow!("Thread panicked in {:?}", dir))?
+    })
+}
+
+/// rootfs 是否为挂载点
+fn is_rootfs_mounted(rootfs: &Path) -> Result<bool> {
+    match rootfs.canonicalize() {
+        Ok(path) => mount::is_mounted(&path.to_string_lossy()),
+        Err(_) => Ok(false),
+    }
 }
 
 /// 从 tar 文件提取镜像
 ///
 /// # 参数
 /// - `tar_path`: tar 文件路径
-/// - `bundle_path`: bundle 目录
+/// - `target`: 镜像层的解压目标
 /// - `logger`: 日志记录器
-async fn extract_tar_image(tar_path: &str, bundle_path: &Path, logger: &Logger) -> Result<()> {
-    info!(logger, "Extracting tar image"; "tar" => tar_path, "bundle" => bundle_path.display().to_string());
+///
+/// # 返回
+/// 解压到镜像层存储时返回该层的目录
+async fn extract_tar_image(
+    tar_path: &str,
+    target: &LayerTarget,
+    logger: &Logger,
+) -> Result<Vec<PathBuf>> {
+    info!(logger, "Extracting tar image"; "tar" => tar_path);
 
     let tar_file = Path::new(tar_path);
     if !tar_file.exists() {
         bail!("Tar file does not exist: {}", tar_path);
     }
 
-    // 创建 rootfs 目录
-    let rootfs = scoped_join(bundle_path, "rootfs")?;
-    fs::create_dir_all(&rootfs)
-        .with_context(|| format!("Failed to create rootfs directory: {:?}", rootfs))?;
-
-    // 使用 tar 命令解压
-    let status = tokio::process::Command::new("tar")
-        .arg("-xf")
-        .arg(tar_path)
-        .arg("-C")
-        .arg(&rootfs)
-        .status()
-        .await
-        .context("Failed to execute tar command")?;
-
-    if !status.success() {
-        bail!("Failed to extract tar file: {}", tar_path);
-    }
+    // 在进程内解压，所有条目都限制在目标目录内创建
+    let tar_file = tar_file.to_path_buf();
+    let target = target.clone();
+    let task_logger = logger.clone();
+    let lowers = tokio::task::spawn_blocking(move || -> Result<Vec<PathBuf>> {
+        match &target {
+            LayerTarget::Rootfs(rootfs) => {
+                fs::create_dir_all(rootfs)
+                    .with_context(|| format!("Failed to create rootfs directory: {:?}", rootfs))?;
+                unpack::unpack_layer_file(&tar_file, rootfs, &task_logger)?;
+                Ok(Vec::new())
+            }
+            LayerTarget::Store(store) => {
+                // tar 文件没有镜像配置，diff_id 由文件内容计算
+                let diff_id = unpack::diff_id(&tar_file)?;
+                Ok(vec![store.unpack(&diff_id, &tar_file, &task_logger)?])
+            }
+        }
+    })
+    .await
+    .context("Tar extraction task panicked")??;
 
-    info!(logger, "Tar extraction completed"; "rootfs" => rootfs.display().to_string());
+    info!(logger, "Tar extraction completed");
 
-    Ok(())
+    Ok(lowers)
 }
 
 /// 复制本地 bundle
@@ -253,15 +607,23 @@ fn copy_dir_recursive(src: &Path, dest: &Path) -> Result<()> {
 
 /// 清理容器镜像
 ///
-/// 删除容器的 bundle 目录。
+/// 卸载 overlay rootfs 后删除容器的 bundle 目录，其中包括容器的 upper 和 work 目录，
+/// 镜像层存储中的共享镜像层保留。
 ///
 /// # 参数
+/// - `image_root`: 镜像根目录
 /// - `container_id`: 容器 ID
 /// - `logger`: 日志记录器
-pub fn cleanup_image(container_id: &str, logger: &Logger) -> Result<()> {
+pub fn cleanup_image(image_root: &Path, container_id: &str, logger: &Logger) -> Result<()> {
     info!(logger, "Cleaning up image"; "container_id" => container_id);
 
-    let bundle_path = scoped_join(CONTAINER_BASE, container_id)?;
+    let bundle_path = scoped_join(container_base(image_root), container_id)?;
+
+    let rootfs = bundle_path.join("rootfs");
+    if is_rootfs_mounted(&rootfs)? {
+        mount::unmount(&rootfs.to_string_lossy())?;
+        info!(logger, "Overlay rootfs unmounted"; "rootfs" => rootfs.display().to_string());
+    }
 
     if bundle_path.exists() {
         fs::remove_dir_all(&bundle_path)
@@ -286,6 +648,67 @@ mod tests {
         Logger::root(drain, o!())
     }
 
+    #[test]
+    fn test_parse_reference() {
+        let dir = tempdir().unwrap();
+        let archive = dir.path().join("a:b").join("image.tar");
+        fs::create_dir_all(archive.parent().unwrap()).unwrap();
+        fs::write(&archive, b"").unwrap();
+        let archive = archive.to_str().unwrap();
+
+        assert_eq!(parse_reference(archive), (archive, None));
+        assert_eq!(
+            parse_reference(&format!("{}:example/app:v1", archive)),
+            (archive, Some("example/app:v1"))
+        );
+        assert_eq!(
+            parse_reference(&format!("{}@sha256:abc", archive)),
+            (archive, Some("sha256:abc"))
+        );
+
+        // 路径不存在时整个引用都是路径
+        let missing = dir.path().join("missing:v1");
+        let missing = missing.to_str().unwrap();
+        assert_eq!(parse_reference(missing), (missing, None));
+    }
+
+    #[test]
+    fn test_overlay_lowerdir() {
+        use sha2::{Digest as _, Sha256};
+
+        let dir = tempdir().unwrap();
+        // 存储目录的长度不影响 lowerdir
+        let store = LayerStore::new(dir.path().join("x".repeat(200)).join("layers"));
+        let lowers: Vec<PathBuf> = (0..200)
+            .map(|i: u32| {
+                let hex = hex::encode(Sha256::digest(i.to_le_bytes()));
+                let layer = store.layer_path(&format!("sha256:{}", hex)).unwrap();
+                fs::create_dir_all(&layer).unwrap();
+                fs::write(layer.join("index"), i.to_string()).unwrap();
+                layer
+            })
+            .collect();
+
+        let lower = overlay_lowerdir(&store, &lowers).unwrap();
+        // 200 层加上 upper 和 work 仍在一页之内
+        assert!(lower.len() < 3200, "{}", lower.len());
+        let links: Vec<&str> = lower.split(':').collect();
+        assert_eq!(links.len(), lowers.len());
+        // 最上层在前，链接在存储目录中解析到镜像层
+        assert_eq!(
+            fs::read_to_string(store.root().join(links[0]).join("index")).unwrap(),
+            "199"
+        );
+        let content = with_cwd(store.root(), || {
+            Ok(fs::read_to_string(Path::new(links[199]).join("index"))?)
+        })
+        .unwrap();
+        assert_eq!(content, "0");
+        assert_ne!(std::env::current_dir().unwrap(), store.root());
+
+        assert!(overlay_lowerdir(&store, &[dir.path().to_path_buf()]).is_err());
+    }
+
     #[tokio::test]
     async fn test_copy_local_bundle() {
         let logger = test_logger();
@@ -0,0 +1,732 @@
+//! OCI 镜像布局
+//!
+//! 从 OCI image layout 目录（`oci:///path/to/layout:tag`）或其 tar 归档
+//! （`oci-archive:///path/to/image.tar:tag`）加载镜像：
+//! `index.json` → manifest（镜像索引按当前平台选择）→ config 和镜像层。
+//! 每个 blob 在使用前都会校验 sha256 摘要和大小，镜像层按顺序解压到 rootfs 或镜像层存储，
+//! 已在镜像层存储中的镜像层不再读取。
+
+use std::{
+    fs::{self, File},
+    io::{self, Read},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result, anyhow, bail};
+use oci_spec::image::{Descriptor, ImageConfiguration, ImageIndex, ImageManifest, Platform};
+use sha2::{Digest as _, Sha256};
+use slog::Logger;
+
+use super::{
+    LayerTarget, LoadedImage, index::ImageRecord, parse_reference, with_extracted_archive,
+};
+
+/// 镜像布局中记录镜像名称的注解
+pub const ANNOTATION_REF_NAME: &str = "org.opencontainers.image.ref.name";
+
+/// 镜像索引的媒体类型
+const INDEX_MEDIA_TYPES: &[&str] = &[
+    "application/vnd.oci.image.index.v1+json",
+    "application/vnd.docker.distribution.manifest.list.v2+json",
+];
+
+/// 镜像 manifest 的媒体类型
+const MANIFEST_MEDIA_TYPES: &[&str] = &[
+    "application/vnd.oci.image.manifest.v1+json",
+    "application/vnd.docker.distribution.manifest.v2+json",
+];
+
+/// 支持的镜像层媒体类型，压缩格式在解压时根据文件头识别
+const LAYER_MEDIA_TYPES: &[&str] = &[
+    "application/vnd.oci.image.layer.v1.tar",
+    "application/vnd.oci.image.layer.v1.tar+gzip",
+    "application/vnd.oci.image.layer.v1.tar+zstd",
+    "application/vnd.oci.image.layer.nondistributable.v1.tar",
+    "application/vnd.oci.image.layer.nondistributable.v1.tar+gzip",
+    "application/vnd.oci.image.layer.nondistributable.v1.tar+zstd",
+    "application/vnd.docker.image.rootfs.diff.tar.gzip",
+    "application/vnd.docker.image.rootfs.foreign.diff.tar.gzip",
+];
+
+/// JSON blob（索引、manifest、config）的大小上限
+const MAX_JSON_BLOB_SIZE: u64 = 4 * 1024 * 1024;
+
+/// 从镜像布局中解析出的镜像
+#[derive(Debug)]
+pub struct OciImage {
+    /// 镜像布局目录
+    pub layout: PathBuf,
+    /// 镜像名称（`org.opencontainers.image.ref.name`）
+    pub name: Option<String>,
+    /// `index.json` 中镜像描述符的摘要，多平台镜像为镜像索引的摘要
+    pub digest: String,
+    /// 镜像 manifest
+    pub manifest: ImageManifest,
+    /// 镜像配置
+    pub config: ImageConfiguration,
+}
+
+impl OciImage {
+    /// 转换为镜像索引中的记录
+    pub fn into_record(self) -> ImageRecord {
+        ImageRecord {
+            id: self.manifest.config().digest().to_string(),
+            names: self.name.into_iter().collect(),
+            digest: Some(self.digest),
+            size: self.manifest.layers().iter().map(Descriptor::size).sum(),
+            manifest: Some(self.manifest),
+            config: self.config,
+        }
+    }
+}
+
+/// 从镜像布局目录加载镜像并解压镜像层
+///
+/// # 参数
+/// - `image`: 镜像引用 `/path/to/layout[:tag]`，不含 `oci://` 前缀
+/// - `target`: 镜像层的解压目标
+/// - `logger`: 日志记录器
+///
+/// # 返回
+/// 加载的镜像
+pub async fn pull_oci_layout(
+    image: &str,
+    target: &LayerTarget,
+    logger: &Logger,
+) -> Result<LoadedImage> {
+    let (layout, reference) = parse_reference(image);
+    let layout = PathBuf::from(layout);
+    let reference = reference.map(str::to_string);
+    let target = target.clone();
+    let logger = logger.clone();
+
+    tokio::task::spawn_blocking(move || {
+        let image = load_image(&layout, reference.as_deref(), &logger)?;
+        let lowers = unpack_image(&image, &target, &logger)?;
+        Ok(LoadedImage {
+            record: image.into_record(),
+            lowers,
+        })
+    })
+    .await
+    .context("OCI layout task panicked")?
+}
+
+/// 从镜像布局的 tar 归档加载镜像并解压镜像层
+///
+/// 归档先解压到 `work_dir` 下的临时目录，加载完成后删除。
+///
+/// # 参数
+/// - `image`: 镜像引用 `/path/to/image.tar[:tag]`，不含 `oci-archive://` 前缀
+/// - `work_dir`: 临时目录所在的工作目录
+/// - `container_id`: 容器 ID，用于区分临时目录
+/// - `target`: 镜像层的解压目标
+/// - `logger`: 日志记录器
+///
+/// # 返回
+/// 加载的镜像
+pub async fn pull_oci_archive(
+    image: &str,
+    work_dir: &Path,
+    container_id: &str,
+    target: &LayerTarget,
+    logger: &Logger,
+) -> Result<LoadedImage> {
+    let (archive, reference) = parse_reference(image);
+    let archive = PathBuf::from(archive);
+    let reference = reference.map(str::to_string);
+    let layout = safe_path::scoped_join(work_dir, format!("oci-{}", container_id))?;
+    let target = target.clone();
+    let logger = logger.clone();
+
+    tokio::task::spawn_blocking(move || {
+        with_extracted_archive(&archive, &layout, &logger, |layout| {
+            let image = load_image(layout, reference.as_deref(), &logger)?;
+            let lowers = unpack_image(&image, &target, &logger)?;
+            Ok(LoadedImage {
+                record: image.into_record(),
+                lowers,
+            })
+        })
+    })
+    .await
+    .context("OCI archive task panicked")?
+}
+
+/// 解析镜像布局，得到 manifest 和镜像配置
+///
+/// # 参数
+/// - `layout`: 镜像布局目录
+/// - `reference`: 标签（`org.opencontainers.image.ref.name`）或 manifest 摘要，
+///   未指定时布局中只能有一个镜像
+/// - `logger`: 日志记录器
+pub fn load_image(layout: &Path, reference: Option<&str>, logger: &Logger) -> Result<OciImage> {
+    if !layout.join("oci-layout").is_file() {
+        bail!("Not an OCI image layout: {:?}", layout);
+    }
+    let index = ImageIndex::from_reader(
+        File::open(layout.join("index.json"))
+            .with_context(|| format!("Failed to open index.json in {:?}", layout))?,
+    )
+    .context("Invalid index.json")?;
+
+    let candidates: Vec<&Descriptor> = match reference {
+        Some(reference) => index
+            .manifests()
+            .iter()
+            .filter(|d| ref_name(d) == Some(reference) || d.digest().to_string() == reference)
+            .collect(),
+        None => index.manifests().iter().collect(),
+    };
+    let descriptor = match candidates.as_slice() {
+        [] => bail!(
+            "Image {} not found in {:?}",
+            reference.unwrap_or("<default>"),
+            layout
+        ),
+        [descriptor] => *descriptor,
+        _ => select_platform(&candidates)?,
+    };
+
+    let manifest = resolve_manifest(layout, descriptor)?;
+    let config_blob = read_json_blob(layout, manifest.config())?;
+    let config =
+        ImageConfiguration::from_reader(&config_blob[..]).context("Invalid image configuration")?;
+
+    info!(logger, "Loaded OCI image";
+        "layout" => layout.display().to_string(),
+        "manifest" => descriptor.digest().to_string(),
+        "platform" => format!("{}/{}", config.os(), config.architecture()),
+        "layers" => manifest.layers().len());
+
+    Ok(OciImage {
+        layout: layout.to_path_buf(),
+        name: ref_name(descriptor).map(str::to_string),
+        digest: descriptor.digest().to_string(),
+        manifest,
+        config,
+    })
+}
+
+/// 校验并按顺序解压镜像层
+///
+/// # 参数
+/// - `image`: 已解析的镜像
+/// - `target`: 镜像层的解压目标，rootfs 目录不存在时创建
+/// - `logger`: 日志记录器
+///
+/// # 返回
+/// 镜像层在存储中的目录（从底层到顶层，解压到 rootfs 时为空）
+pub fn unpack_image(
+    image: &OciImage,
+    target: &LayerTarget,
+    logger: &Logger,
+) -> Result<Vec<PathBuf>> {
+    if let LayerTarget::Rootfs(rootfs) = target {
+        fs::create_dir_all(rootfs)
+            .with_context(|| format!("Failed to create rootfs directory: {:?}", rootfs))?;
+    }
+
+    let layers = image.manifest.layers();
+    let diff_ids = image.config.rootfs().diff_ids();
+    if diff_ids.len() != layers.len() {
+        bail!(
+            "Image config lists {} layers but manifest has {}",
+            diff_ids.len(),
+            layers.len()
+        );
+    }
+
+    let mut lowers = Vec::new();
+    for (i, (layer, diff_id)) in layers.iter().zip(diff_ids).enumerate() {
+        let media_type = layer.media_type().to_string();
+        if !LAYER_MEDIA_TYPES.contains(&media_type.as_str()) {
+            bail!("Unsupported layer media type: {}", media_type);
+        }
+        debug!(logger, "Applying layer";
+            "index" => i, "digest" => layer.digest().to_string());
+        let lower = target
+            .apply(diff_id, || verify_blob(&image.layout, layer), logger)
+            .with_context(|| format!("Failed to apply layer {}", layer.digest()))?;
+        lowers.extend(lower);
+    }
+
+    Ok(lowers)
+}
+
+/// 镜像描述符上的名称注解
+fn ref_name(descriptor: &Descriptor) -> Option<&str> {
+    descriptor
+        .annotations()
+        .as_ref()
+        .and_then(|a| a.get(ANNOTATION_REF_NAME))
+        .map(String::as_str)
+}
+
+/// 解析描述符指向的 manifest，镜像索引按当前平台选择
+fn resolve_manifest(layout: &Path, descriptor: &Descriptor) -> Result<ImageManifest> {
+    let media_type = descriptor.media_type().to_string();
+    let blob = read_json_blob(layout, descriptor)?;
+
+    if INDEX_MEDIA_TYPES.contains(&media_type.as_str()) {
+        let index = ImageIndex::from_reader(&blob[..]).context("Invalid image index")?;
+        let manifests: Vec<&Descriptor> = index.manifests().iter().collect();
+        let descriptor = select_platform(&manifests)?;
+        if !MANIFEST_MEDIA_TYPES.contains(&descriptor.media_type().to_string().as_str()) {
+            bail!(
+                "Unsupported manifest media type: {}",
+                descriptor.media_type()
+            );
+        }
+        return resolve_manifest(layout, descriptor);
+    }
+    if !MANIFEST_MEDIA_TYPES.contains(&media_type.as_str()) {
+        bail!("Unsupported manifest media type: {}", media_type);
+    }
+
+    ImageManifest::from_reader(&blob[..]).context("Invalid image manifest")
+}
+
+/// 平台的 OCI 名称
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct HostPlatform {
+    os: &'static str,
+    architecture: &'static str,
+    /// CPU 变体，如 arm 的 `v7`、arm64 的 `v8`
+    variant: Option<&'static str>,
+}
+
+impl std::fmt::Display for HostPlatform {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.os, self.architecture)?;
+        if let Some(variant) = self.variant {
+            write!(f, "/{}", variant)?;
+        }
+        Ok(())
+    }
+}
+
+/// 当前平台的 OCI 名称
+fn current_platform() -> HostPlatform {
+    let little_endian = cfg!(target_endian = "little");
+    let architecture = match std::env::consts::ARCH {
+        "x86_64" => "amd64",
+        "x86" => "386",
+        "aarch64" => "arm64",
+        "powerpc64" if little_endian => "ppc64le",
+        "powerpc64" => "ppc64",
+        "mips64" if little_endian => "mips64le",
+        "mips" if little_endian => "mipsle",
+        "loongarch64" => "loong64",
+        arch => arch,
+    };
+    let variant = match architecture {
+        "arm64" => Some("v8"),
+        "arm" if cfg!(target_feature = "v7") => Some("v7"),
+        "arm" if cfg!(target_feature = "v6") => Some("v6"),
+        "arm" => Some("v5"),
+        _ => None,
+    };
+    HostPlatform {
+        os: std::env::consts::OS,
+        architecture,
+        variant,
+    }
+}
+
+/// manifest 的平台与 `host` 的匹配程度，数值越小越优先，不能运行时返回 `None`
+///
+/// 变体相同（arm64 未标注变体视为 `v8`）的最优先，其次是未标注变体的；
+/// arm 可以运行较低版本变体的镜像（v7 可运行 v6、v5），版本越接近越优先。
+fn platform_rank(platform: &Platform, host: &HostPlatform) -> Option<u32> {
+    let architecture = platform.architecture().to_string();
+    if platform.os().to_string() != host.os || architecture != host.architecture {
+        return None;
+    }
+
+    let variant = match platform.variant().as_deref() {
+        None if architecture == "arm64" => Some("v8"),
+        variant => variant,
+    };
+    if variant == host.variant {
+        return Some(0);
+    }
+    let Some(variant) = variant else {
+        return Some(1);
+    };
+
+    let version = |v: &str| v.strip_prefix('v').and_then(|n| n.parse::<u32>().ok());
+    match (version(variant), host.variant.and_then(version)) {
+        (Some(theirs), Some(ours)) if architecture == "arm" && theirs <= ours => {
+            Some(1 + ours - theirs)
+        }
+        _ => None,
+    }
+}
+
+/// 从多个 manifest 中选择与当前平台最匹配的一个
+fn select_platform<'a>(manifests: &[&'a Descriptor]) -> Result<&'a Descriptor> {
+    select_platform_for(manifests, &current_platform())
+}
+
+/// 从多个 manifest 中选择与 `host` 最匹配的一个，匹配程度相同时取第一个
+fn select_platform_for<'a>(
+    manifests: &[&'a Descriptor],
+    host: &HostPlatform,
+) -> Result<&'a Descriptor> {
+    manifests
+        .iter()
+        .filter_map(|d| {
+            let rank = platform_rank(d.platform().as_ref()?, host)?;
+            Some((rank, *d))
+        })
+        .min_by_key(|(rank, _)| *rank)
+        .map(|(_, d)| d)
+        .ok_or_else(|| {
+            let available: Vec<String> = manifests
+                .iter()
+                .filter_map(|d| d.platform().as_ref())
+                .map(|p| match p.variant() {
+                    Some(variant) => format!("{}/{}/{}", p.os(), p.architecture(), variant),
+                    None => format!("{}/{}", p.os(), p.architecture()),
+                })
+                .collect();
+            anyhow!(
+                "No manifest for platform {}, available: [{}]",
+                host,
+                available.join(", ")
+            )
+        })
+}
+
+/// blob 在布局中的路径 `blobs/<algorithm>/<encoded>`
+fn blob_path(layout: &Path, descriptor: &Descriptor) -> Result<PathBuf> {
+    let digest = descriptor.digest().to_string();
+    let (algorithm, encoded) = digest
+        .split_once(':')
+        .ok_or_else(|| anyhow!("Invalid digest: {}", digest))?;
+    if algorithm != "sha256" {
+        bail!("Unsupported digest algorithm: {}", algorithm);
+    }
+    if encoded.len() != 64 || !encoded.bytes().all(|b| b.is_ascii_hexdigit()) {
+        bail!("Invalid digest: {}", digest);
+    }
+    Ok(layout.join("blobs").join(algorithm).join(encoded))
+}
+
+/// 校验 blob 的大小和 sha256 摘要
+///
+/// # 返回
+/// 校验通过的 blob 路径
+fn verify_blob(layout: &Path, descriptor: &Descriptor) -> Result<PathBuf> {
+    let path = blob_path(layout, descriptor)?;
+    let mut file =
+        File::open(&path).with_context(|| format!("Missing blob {}", descriptor.digest()))?;
+    let mut hasher = Sha256::new();
+    let size = io::copy(&mut file, &mut hasher)
+        .with_context(|| format!("Failed to read blob {}", descriptor.digest()))?;
+    check_blob(descriptor, size, &hasher.finalize())?;
+    Ok(path)
+}
+
+/// 读取并校验 JSON blob
+fn read_json_blob(layout: &Path, descriptor: &Descriptor) -> Result<Vec<u8>> {
+    if descriptor.size() > MAX_JSON_BLOB_SIZE {
+        bail!(
+            "Blob {} is too large: {} bytes",
+            descriptor.digest(),
+            descriptor.size()
+        );
+    }
+    let path = blob_path(layout, descriptor)?;
+    let mut data = Vec::new();
+    File::open(&path)
+        .with_context(|| format!("Missing blob {}", descriptor.digest()))?
+        .take(MAX_JSON_BLOB_SIZE + 1)
+        .read_to_end(&mut data)
+        .with_context(|| format!("Failed to read blob {}", descriptor.digest()))?;
+    check_blob(descriptor, data.len() as u64, &Sha256::digest(&data))?;
+    Ok(data)
+}
+
+fn check_blob(descriptor: &Descriptor, size: u64, hash: &[u8]) -> Result<()> {
+    if size != descriptor.size() {
+        bail!(
+            "Blob {} size mismatch: expected {}, got {}",
+            descriptor.digest(),
+            descriptor.size(),
+            size
+        );
+    }
+    let actual = format!("sha256:{}", hex::encode(hash));
+    if actual != descriptor.digest().to_string() {
+        bail!(
+            "Blob digest mismatch: expected {}, got {}",
+            descriptor.digest(),
+            actual
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{Value, json};
+    use slog::Drain;
+    use tempfile::tempdir;
+
+    use super::*;
+
+    fn test_logger() -> Logger {
+        let decorator = slog_term::PlainSyncDecorator::new(std::io::stdout());
+        let drain = slog_term::FullFormat::new(decorator).build().fuse();
+        Logger::root(drain, o!())
+    }
+
+    /// 写入 blob，返回描述符
+    fn write_blob(layout: &Path, media_type: &str, data: &[u8]) -> Value {
+        let encoded = hex::encode(Sha256::digest(data));
+        let dir = layout.join("blobs/sha256");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join(&encoded), data).unwrap();
+        json!({
+            "mediaType": media_type,
+            "digest": format!("sha256:{}", encoded),
+            "size": data.len(),
+        })
+    }
+
+    fn layer(files: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut builder = tar::Builder::new(Vec::new());
+        for (path, data) in files {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(data.len() as u64);
+            header.set_mode(0o644);
+            header.set_uid(nix::unistd::geteuid().as_raw().into());
+            header.set_gid(nix::unistd::getegid().as_raw().into());
+            builder.append_data(&mut header, path, *data).unwrap();
+        }
+        builder.into_inner().unwrap()
+    }
+
+    /// 构造包含两层的镜像，返回 manifest 描述符
+    fn write_image(layout: &Path, arch: &str) -> Value {
+        let lower = layer(&[("etc/os-release", b"lower"), ("tmp/junk", b"x")]);
+        let upper = layer(&[("etc/os-release", b"upper"), ("tmp/.wh.junk", b"")]);
+        let mut layers = Vec::new();
+        let mut diff_ids = Vec::new();
+        for data in [&lower, &upper] {
+            let desc = write_blob(layout, "application/vnd.oci.image.layer.v1.tar", data);
+            diff_ids.push(desc["digest"].clone());
+            layers.push(desc);
+        }
+        let config = json!({
+            "architecture": arch,
+            "os": "linux",
+            "config": { "Cmd": ["/bin/sh"] },
+            "rootfs": { "type": "layers", "diff_ids": diff_ids },
+        });
+        let config = write_blob(
+            layout,
+            "application/vnd.oci.image.config.v1+json",
+            config.to_string().as_bytes(),
+        );
+        let manifest = json!({
+            "schemaVersion": 2,
+            "mediaType": "application/vnd.oci.image.manifest.v1+json",
+            "config": config,
+            "layers": layers,
+        });
+        write_blob(
+            layout,
+            "application/vnd.oci.image.manifest.v1+json",
+            manifest.to_string().as_bytes(),
+        )
+    }
+
+    fn write_layout(layout: &Path, manifests: Vec<Value>) {
+        fs::create_dir_all(layout).unwrap();
+        fs::write(
+            layout.join("oci-layout"),
+            r#"{"imageLayoutVersion":"1.0.0"}"#,
+        )
+        .unwrap();
+        let index = json!({ "schemaVersion": 2, "manifests": manifests });
+        fs::write(layout.join("index.json"), index.to_string()).unwrap();
+    }
+
+    #[test]
+    fn test_load_and_unpack() {
+        let logger = test_logger();
+        let dir = tempdir().unwrap();
+        let layout = dir.path().join("layout");
+        fs::create_dir_all(&layout).unwrap();
+        let arch = current_platform().architecture;
+
+        let mut manifest = write_image(&layout, arch);
+        manifest["annotations"] = json!({ ANNOTATION_REF_NAME: "v1" });
+        write_layout(&layout, vec![manifest]);
+
+        assert!(load_image(&layout, Some("v2"), &logger).is_err());
+        let image = load_image(&layout, Some("v1"), &logger).unwrap();
+        assert_eq!(image.manifest.layers().len(), 2);
+
+        let rootfs = dir.path().join("rootfs");
+        unpack_image(&image, &LayerTarget::Rootfs(rootfs.clone()), &logger).unwrap();
+        assert_eq!(
+            fs::read_to_string(rootfs.join("etc/os-release")).unwrap(),
+            "upper"
+        );
+        assert!(!rootfs.join("tmp/junk").exists());
+    }
+
+    #[test]
+    fn test_select_platform() {
+        let logger = test_logger();
+        let dir = tempdir().unwrap();
+        let layout = dir.path().join("layout");
+        fs::create_dir_all(&layout).unwrap();
+        let HostPlatform {
+            os,
+            architecture: arch,
+            ..
+        } = current_platform();
+
+        // 镜像索引中包含另一个平台和当前平台的镜像
+        let mut other = write_image(&layout, "s390x");
+        other["platform"] = json!({ "os": os, "architecture": "s390x" });
+        let mut native = write_image(&layout, arch);
+        native["platform"] = json!({ "os": os, "architecture": arch });
+        let index = json!({ "schemaVersion": 2, "manifests": [other, native] });
+        let index = write_blob(
+            &layout,
+            "application/vnd.oci.image.index.v1+json",
+            index.to_string().as_bytes(),
+        );
+        write_layout(&layout, vec![index]);
+
+        let image = load_image(&layout, None, &logger).unwrap();
+        assert_eq!(image.config.architecture().to_string(), arch);
+    }
+
+    #[test]
+    fn test_select_platform_variant() {
+        let descriptor = |architecture: &str, variant: Option<&str>| -> Descriptor {
+            let mut platform = json!({ "os": "linux", "architecture": architecture });
+            if let Some(variant) = variant {
+                platform["variant"] = json!(variant);
+            }
+            serde_json::from_value(json!({
+                "mediaType": "application/vnd.oci.image.manifest.v1+json",
+                "digest": format!("sha256:{}", "0".repeat(64)),
+                "size": 0,
+                "platform": platform,
+            }))
+            .unwrap()
+        };
+        let host = |architecture, variant| HostPlatform {
+            os: "linux",
+            architecture,
+            variant,
+        };
+        let select = |manifests: &[Descriptor], host: &HostPlatform| {
+            let manifests: Vec<&Descriptor> = manifests.iter().collect();
+            select_platform_for(&manifests, host)
+                .ok()
+                .and_then(|d| d.platform().clone())
+                .and_then(|p| p.variant().clone())
+        };
+
+        // arm64：v8 优先于其他变体，未标注变体视为 v8
+        let arm64 = [
+            descriptor("arm64", Some("v9")),
+            descriptor("arm64", Some("v8")),
+        ];
+        assert_eq!(
+            select(&arm64, &host("arm64", Some("v8"))).as_deref(),
+            Some("v8")
+        );
+        let arm64 = [descriptor("arm64", Some("v9"))];
+        assert!(select_platform_for(&[&arm64[0]], &host("arm64", Some("v8"))).is_err());
+
+        // arm：精确匹配优先，否则选择最接近的较低版本，不选择较高版本
+        let arm = [
+            descriptor("arm", Some("v5")),
+            descriptor("arm", Some("v7")),
+            descriptor("arm", Some("v6")),
+        ];
+        assert_eq!(
+            select(&arm, &host("arm", Some("v7"))).as_deref(),
+            Some("v7")
+        );
+        assert_eq!(
+            select(&arm[..1], &host("arm", Some("v7"))).as_deref(),
+            Some("v5")
+        );
+        assert_eq!(
+            select(&[arm[0].clone(), arm[2].clone()], &host("arm", Some("v7"))).as_deref(),
+            Some("v6")
+        );
+        assert!(select_platform_for(&[&arm[1]], &host("arm", Some("v6"))).is_err());
+
+        // 大端 ppc64 不匹配 ppc64le
+        let ppc = [descriptor("ppc64le", None)];
+        assert!(select_platform_for(&[&ppc[0]], &host("ppc64", None)).is_err());
+    }
+
+    #[test]
+    fn test_verify_digest() {
+        let logger = test_logger();
+        let dir = tempdir().unwrap();
+        let layout = dir.path().join("layout");
+        fs::create_dir_all(&layout).unwrap();
+        let arch = current_platform().architecture;
+        write_layout(&layout, vec![write_image(&layout, arch)]);
+
+        // 篡改第一个镜像层
+        let image = load_image(&layout, None, &logger).unwrap();
+        let blob = blob_path(&layout, &image.manifest.layers()[0]).unwrap();
+        let mut data = fs::read(&blob).unwrap();
+        data[600] ^= 0xff;
+        fs::write(&blob, data).unwrap();
+
+        let rootfs = LayerTarget::Rootfs(dir.path().join("rootfs"));
+        let err = unpack_image(&image, &rootfs, &logger).unwrap_err();
+        assert!(format!("{:#}", err).contains("digest mismatch"));
+    }
+
+    #[tokio::test]
+    async fn test_pull_oci_archive() {
+        let logger = test_logger();
+        let dir = tempdir().unwrap();
+        let layout = dir.path().join("layout");
+        fs::create_dir_all(&layout).unwrap();
+        let arch = current_platform().architecture;
+        let mut manifest = write_image(&layout, arch);
+        manifest["annotations"] = json!({ ANNOTATION_REF_NAME: "latest" });
+        write_layout(&layout, vec![manifest]);
+
+        let archive = dir.path().join("image.tar");
+        let mut builder = tar::Builder::new(File::create(&archive).unwrap());
+        builder.append_dir_all(".", &layout).unwrap();
+        builder.finish().unwrap();
+
+        let work_dir = dir.path().join("work");
+        fs::create_dir_all(&work_dir).unwrap();
+        let rootfs = dir.path().join("rootfs");
+        let image = format!("{}:latest", archive.display());
+        let target = LayerTarget::Rootfs(rootfs.clone());
+        let loaded = pull_oci_archive(&image, &work_dir, "c1", &target, &logger)
+            .await
+            .unwrap();
+        assert!(loaded.lowers.is_empty());
+        assert_eq!(loaded.record.names, vec!["latest"]);
+        assert_eq!(loaded.record.layers().len(), 2);
+
+        assert_eq!(
+            fs::read_to_string(rootfs.join("etc/os-release")).unwrap(),
+            "upper"
+        );
+        assert!(!work_dir.join("oci-c1").exists());
+    }
+}
@@ -0,0 +1,393 @@
+//! 镜像层存储
+//!
+//! 镜像层按 diff_id（解压后 tar 流的 sha256 摘要）存放在 `{image_root}/layers/sha256/<hex>`，
+//! 每层只解压一次，whiteout 转换为 overlayfs 的格式，由同一镜像创建的所有容器共享。
+//! 容器的 rootfs 是以这些目录为只读下层、容器自己的 upper 和 work 目录为上层的 overlay 挂载。
+//!
+//! 镜像层先解压到 `tmp/` 下的临时目录，校验摘要后再重命名为最终目录：
+//! 解压中断不会留下不完整的镜像层，并发解压同一层时只保留先完成的一份。
+//! 删除镜像层时同样先重命名到 `tmp/` 下再删除。
+//!
+//! 与 docker 的 overlay2 相同，`l/` 下为每层保存一个短链接，挂载 overlay 时作为下层路径，
+//! 使几百层的镜像也不会超出挂载选项一页的长度限制。
+
+use std::{
+    fs::{self, File},
+    io,
+    os::unix::fs::symlink,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use anyhow::{Context, Result, anyhow, bail};
+use slog::Logger;
+
+use super::unpack;
+
+/// 同一进程中临时目录的序号
+static TMP_SEQ: AtomicUsize = AtomicUsize::new(0);
+
+/// 短链接名称的长度，取 diff_id 十六进制的前缀
+const LINK_LEN: usize = 12;
+
+/// 镜像层存储
+#[derive(Debug, Clone)]
+pub struct LayerStore {
+    root: PathBuf,
+}
+
+impl LayerStore {
+    /// 创建镜像层存储
+    ///
+    /// # 参数
+    /// - `root`: 存储目录，使用时创建
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// 镜像层在存储中的目录 `{root}/sha256/<hex>`
+    ///
+    /// # 参数
+    /// - `diff_id`: 镜像层的 diff_id `sha256:<hex>`
+    pub fn layer_path(&self, diff_id: &str) -> Result<PathBuf> {
+        let (algorithm, encoded) = diff_id
+            .split_once(':')
+            .ok_or_else(|| anyhow!("Invalid diff_id: {}", diff_id))?;
+        if algorithm != "sha256" {
+            bail!("Unsupported digest algorithm: {}", algorithm);
+        }
+        if encoded.len() != 64 || !encoded.bytes().all(|b| b.is_ascii_hexdigit()) {
+            bail!("Invalid diff_id: {}", diff_id);
+        }
+        Ok(self.root.join(algorithm).join(encoded.to_ascii_lowercase()))
+    }
+
+    /// 存储目录
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// 镜像层的短链接 `l/<前 12 位>`，相对于存储目录，不存在时创建
+    ///
+    /// 链接指向 `../sha256/<hex>`，以存储目录为工作目录时可以代替镜像层目录使用。
+    ///
+    /// # 参数
+    /// - `layer`: 镜像层在存储中的目录
+    pub fn link(&self, layer: &Path) -> Result<PathBuf> {
+        let (link, target) = self.link_of(layer)?;
+        let path = self.root.join(&link);
+        match fs::read_link(&path) {
+            Ok(existing) if existing == target => return Ok(link),
+            Ok(existing) => bail!("Layer link {:?} already points to {:?}", path, existing),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e).with_context(|| format!("Failed to read link {:?}", path)),
+        }
+
+        let dir = self.root.join("l");
+        fs::create_dir_all(&dir)
+            .with_context(|| format!("Failed to create directory: {:?}", dir))?;
+        match symlink(&target, &path) {
+            Ok(()) => Ok(link),
+            // 其他进程同时创建了同一个链接
+            Err(e)
+                if e.kind() == io::ErrorKind::AlreadyExists
+                    && fs::read_link(&path).is_ok_and(|existing| existing == target) =>
+            {
+                Ok(link)
+            }
+            Err(e) => Err(e).with_context(|| format!("Failed to create link {:?}", path)),
+        }
+    }
+
+    /// 镜像层的短链接路径（相对于存储目录）和链接目标
+    fn link_of(&self, layer: &Path) -> Result<(PathBuf, PathBuf)> {
+        let hex = layer
+            .strip_prefix(self.root.join("sha256"))
+            .ok()
+            .and_then(Path::to_str)
+            .filter(|hex| self.layer_path(&format!("sha256:{}", hex)).is_ok())
+            .ok_or_else(|| anyhow!("Layer is not in the store: {:?}", layer))?;
+        Ok((
+            Path::new("l").join(&hex[..LINK_LEN]),
+            Path::new("../sha256").join(hex),
+        ))
+    }
+
+    /// 镜像层是否已在存储中
+    pub fn contains(&self, diff_id: &str) -> bool {
+        self.layer_path(diff_id).is_ok_and(|path| path.is_dir())
+    }
+
+    /// 存储中的全部镜像层
+    ///
+    /// # 返回
+    /// 各层的 diff_id 和目录
+    pub fn layers(&self) -> Result<Vec<(String, PathBuf)>> {
+        let dir = self.root.join("sha256");
+        let entries = match fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {:?}", dir)),
+        };
+
+        let mut layers = Vec::new();
+        for entry in entries {
+            let entry = entry?;
+            let name = entry.file_name();
+            let Some(diff_id) = name.to_str().map(|hex| format!("sha256:{}", hex)) else {
+                continue;
+            };
+            if self.layer_path(&diff_id).is_ok() {
+                layers.push((diff_id, entry.path()));
+            }
+        }
+        layers.sort();
+        Ok(layers)
+    }
+
+    /// 删除镜像层
+    ///
+    /// 镜像层先重命名到临时目录再删除，删除中断时不会留下不完整的镜像层。
+    pub fn remove(&self, diff_id: &str, logger: &Logger) -> Result<()> {
+        let path = self.layer_path(diff_id)?;
+        if !path.exists() {
+            return Ok(());
+        }
+        let (link, target) = self.link_of(&path)?;
+        let link = self.root.join(link);
+        if fs::read_link(&link).is_ok_and(|existing| existing == target) {
+            fs::remove_file(&link).with_context(|| format!("Failed to remove {:?}", link))?;
+        }
+        let tmp = self.tmp_path(&path);
+        if let Some(parent) = tmp.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create directory: {:?}", parent))?;
+        }
+        fs::rename(&path, &tmp)
+            .with_context(|| format!("Failed to rename {:?} to {:?}", path, tmp))?;
+        fs::remove_dir_all(&tmp).with_context(|| format!("Failed to remove {:?}", tmp))?;
+
+        info!(logger, "Layer removed"; "diff_id" => diff_id);
+        Ok(())
+    }
+
+    /// 删除中断的解压或删除留下的临时目录
+    ///
+    /// 调用方需保证没有正在进行的解压。
+    ///
+    /// # 返回
+    /// 删除的临时目录数
+    pub fn remove_tmp(&self) -> Result<usize> {
+        let dir = self.root.join("tmp");
+        let entries = match fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(0),
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {:?}", dir)),
+        };
+
+        let mut count = 0;
+        for entry in entries {
+            let path = entry?.path();
+            fs::remove_dir_all(&path).with_context(|| format!("Failed to remove {:?}", path))?;
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    /// 将镜像层解压到存储中，已存在时直接返回
+    ///
+    /// # 参数
+    /// - `diff_id`: 镜像配置中该层的 diff_id，解压后校验
+    /// - `layer`: tar 文件路径，可以是未压缩、gzip 或 zstd 格式
+    /// - `logger`: 日志记录器
+    ///
+    /// # 返回
+    /// 镜像层在存储中的目录
+    pub fn unpack(&self, diff_id: &str, layer: &Path, logger: &Logger) -> Result<PathBuf> {
+        let path = self.layer_path(diff_id)?;
+        if path.is_dir() {
+            debug!(logger, "Layer already unpacked"; "diff_id" => diff_id);
+            return Ok(path);
+        }
+
+        let tmp = self.tmp_path(&path);
+        fs::create_dir_all(&tmp)
+            .with_context(|| format!("Failed to create directory: {:?}", tmp))?;
+
+        let result = self.commit(diff_id, layer, &tmp, &path, logger);
+        if tmp.exists()
+            && let Err(e) = fs::remove_dir_all(&tmp)
+        {
+            warn!(logger, "Failed to remove temporary directory";
+                "path" => tmp.display().to_string(), "error" => e.to_string());
+        }
+        result.map(|_| path)
+    }
+
+    /// 镜像层对应的临时目录 `{root}/tmp/<hex>-<pid>-<seq>`
+    fn tmp_path(&self, path: &Path) -> PathBuf {
+        self.root.join("tmp").join(format!(
+            "{}-{}-{}",
+            path.file_name().unwrap_or_default().to_string_lossy(),
+            std::process::id(),
+            TMP_SEQ.fetch_add(1, Ordering::Relaxed)
+        ))
+    }
+
+    /// 解压到临时目录，校验摘要后重命名为最终目录
+    fn commit(
+        &self,
+        diff_id: &str,
+        layer: &Path,
+        tmp: &Path,
+        path: &Path,
+        logger: &Logger,
+    ) -> Result<()> {
+        let file =
+            File::open(layer).with_context(|| format!("Failed to open layer: {:?}", layer))?;
+        let actual = unpack::unpack_layer_diff(file, tmp, logger)
+            .with_context(|| format!("Failed to unpack {:?}", layer))?;
+        if !actual.eq_ignore_ascii_case(diff_id) {
+            bail!(
+                "Layer diff_id mismatch: expected {}, got {}",
+                diff_id,
+                actual
+            );
+        }
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create directory: {:?}", parent))?;
+        }
+        match fs::rename(tmp, path) {
+            Ok(()) => {
+                info!(logger, "Layer unpacked"; "diff_id" => diff_id);
+                Ok(())
+            }
+            // 其他进程已经解压了同一层
+            Err(_) if path.is_dir() => {
+                debug!(logger, "Layer unpacked concurrently"; "diff_id" => diff_id);
+                Ok(())
+            }
+            Err(e) => Err(e).with_context(|| format!("Failed to rename {:?} to {:?}", tmp, path)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::os::unix::fs::FileTypeExt;
+
+    use slog::Drain;
+    use tempfile::tempdir;
+
+    use super::*;
+
+    fn test_logger() -> Logger {
+        let decorator = slog_term::PlainSyncDecorator::new(std::io::stdout());
+        let drain = slog_term::FullFormat::new(decorator).build().fuse();
+        Logger::root(drain, o!())
+    }
+
+    fn write_layer(path: &Path, files: &[(&str, &[u8])]) -> String {
+        let mut builder = tar::Builder::new(Vec::new());
+        for (name, data) in files {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(data.len() as u64);
+            header.set_mode(0o644);
+            header.set_uid(nix::unistd::geteuid().as_raw().into());
+            header.set_gid(nix::unistd::getegid().as_raw().into());
+            builder.append_data(&mut header, name, *data).unwrap();
+        }
+        fs::write(path, builder.into_inner().unwrap()).unwrap();
+        unpack::diff_id(path).unwrap()
+    }
+
+    #[test]
+    fn test_layer_path() {
+        let store = LayerStore::new("/store");
+        let hex = "a".repeat(64);
+        assert_eq!(
+            store.layer_path(&format!("sha256:{}", hex)).unwrap(),
+            Path::new("/store/sha256").join(&hex)
+        );
+        assert!(store.layer_path("sha256:../../etc").is_err());
+        assert!(store.layer_path(&format!("sha512:{}", hex)).is_err());
+        assert!(store.layer_path(&hex).is_err());
+    }
+
+    #[test]
+    fn test_unpack_once() {
+        let logger = test_logger();
+        let dir = tempdir().unwrap();
+        let store = LayerStore::new(dir.path().join("layers"));
+        let layer = dir.path().join("layer.tar");
+        let diff_id = write_layer(&layer, &[("etc/os-release", b"layer")]);
+
+        // 摘要不符时不写入存储
+        let wrong = format!("sha256:{}", "0".repeat(64));
+        assert!(store.unpack(&wrong, &layer, &logger).is_err());
+        assert!(!store.contains(&wrong));
+
+        let path = store.unpack(&diff_id, &layer, &logger).unwrap();
+        assert!(store.contains(&diff_id));
+        assert_eq!(
+            fs::read_to_string(path.join("etc/os-release")).unwrap(),
+            "layer"
+        );
+
+        // 已存在的镜像层不再读取文件
+        fs::remove_file(&layer).unwrap();
+        assert_eq!(store.unpack(&diff_id, &layer, &logger).unwrap(), path);
+        assert_eq!(
+            fs::read_dir(dir.path().join("layers/tmp")).unwrap().count(),
+            0
+        );
+
+        assert_eq!(
+            store.layers().unwrap(),
+            vec![(diff_id.clone(), path.clone())]
+        );
+        let link = store.link(&path).unwrap();
+        assert_eq!(link, Path::new("l").join(&diff_id[7..7 + LINK_LEN]));
+        assert_eq!(store.link(&path).unwrap(), link);
+        assert_eq!(
+            fs::read_to_string(store.root().join(&link).join("etc/os-release")).unwrap(),
+            "layer"
+        );
+        assert!(store.link(&dir.path().join("layer.tar")).is_err());
+
+        store.remove(&diff_id, &logger).unwrap();
+        assert!(fs::symlink_metadata(store.root().join(&link)).is_err());
+        assert!(!store.contains(&diff_id));
+        assert!(store.layers().unwrap().is_empty());
+        assert_eq!(store.remove_tmp().unwrap(), 0);
+    }
+
+    #[test]
+    fn test_unpack_whiteout() {
+        // 创建 whiteout 设备和 trusted.* 扩展属性需要 root 权限
+        if !nix::unistd::geteuid().is_root() {
+            return;
+        }
+        let logger = test_logger();
+        let dir = tempdir().unwrap();
+        let store = LayerStore::new(dir.path().join("layers"));
+        let layer = dir.path().join("layer.tar");
+        let diff_id = write_layer(
+            &layer,
+            &[
+                ("tmp/.wh.junk", b""),
+                ("opaque/.wh..wh..opq", b""),
+                ("opaque/kept", b"x"),
+            ],
+        );
+
+        let path = store.unpack(&diff_id, &layer, &logger).unwrap();
+        let junk = fs::symlink_metadata(path.join("tmp/junk")).unwrap();
+        assert!(junk.file_type().is_char_device());
+        assert!(!path.join("tmp/.wh.junk").exists());
+        assert!(!path.join("opaque/.wh..wh..opq").exists());
+        assert!(path.join("opaque/kept").is_file());
+    }
+}
@@ -0,0 +1,847 @@
+//! 镜像层解压
+//!
+//! 在进程内解压 tar 格式的镜像层，根据文件头自动识别未压缩、gzip 和 zstd 格式。
+//!
+//! 所有条目都通过 `safe_path` 在 rootfs 内创建：父目录由 `ScopedDirBuilder` 创建并固定，
+//! 条目本身通过 `*at` 系统调用相对父目录创建，`..`、绝对路径以及指向 rootfs 之外的
+//! 符号链接都被限制在 rootfs 内解析，不可信的镜像无法写到 rootfs 之外。
+//!
+//! 条目的属主、权限、修改时间、扩展属性、硬链接和设备节点都会保留。
+//! 非 root 用户解压时，无权限设置的属主、设备节点和扩展属性会被跳过。
+//!
+//! 按 OCI 镜像层规范处理 whiteout：`.wh.<name>` 删除下层的 `<name>`，
+//! `.wh..wh..opq` 清空下层在该目录中的内容，本层中的条目不受影响。
+//! 镜像层单独解压、作为 overlayfs 下层使用时，whiteout 转换为 overlayfs 的格式：
+//! 删除标记为设备号 0/0 的字符设备，不透明目录带有 `trusted.overlay.opaque=y` 扩展属性。
+
+use std::{
+    collections::HashSet,
+    ffi::{CString, OsStr, OsString},
+    fs::{self, File},
+    io::{self, BufRead, BufReader, Read},
+    os::unix::{
+        ffi::OsStrExt,
+        fs::PermissionsExt,
+        io::{FromRawFd, RawFd},
+    },
+    path::{Component, Path, PathBuf},
+};
+
+use anyhow::{Context, Result, anyhow, bail};
+use flate2::read::MultiGzDecoder;
+use nix::{
+    errno::Errno,
+    fcntl::{AtFlags, OFlag, openat},
+    sys::{
+        stat::{
+            FchmodatFlags, Mode, SFlag, UtimensatFlags, fchmodat, fstatat, makedev, mkdirat,
+            mknodat, utimensat,
+        },
+        time::TimeSpec,
+    },
+    unistd::{
+        FchownatFlags, Gid, LinkatFlags, Uid, UnlinkatFlags, fchownat, geteuid, linkat, symlinkat,
+        unlinkat,
+    },
+};
+use safe_path::{PinnedPathBuf, ScopedDirBuilder, scoped_join};
+use sha2::{Digest as _, Sha256};
+use slog::Logger;
+use tar::{Archive, Entry, EntryType};
+
+/// gzip 文件头魔数
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+
+/// zstd 文件头魔数
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+
+/// whiteout 文件名前缀
+const WHITEOUT_PREFIX: &str = ".wh.";
+
+/// 不透明目录标记
+const WHITEOUT_OPAQUE: &str = ".wh..wh..opq";
+
+/// PAX 扩展头中扩展属性的前缀
+const PAX_XATTR_PREFIX: &str = "SCHILY.xattr.";
+
+/// overlayfs 使用的扩展属性前缀，镜像层中的同名属性会被忽略
+const OVERLAY_XATTR_PREFIX: &str = "trusted.overlay.";
+
+/// overlayfs 不透明目录的扩展属性
+const OVERLAY_OPAQUE_XATTR: &str = "trusted.overlay.opaque";
+
+/// 镜像层的压缩格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    None,
+    Gzip,
+    Zstd,
+}
+
+impl Compression {
+    /// 根据文件头的魔数识别压缩格式
+    pub fn detect(header: &[u8]) -> Self {
+        if header.starts_with(GZIP_MAGIC) {
+            Compression::Gzip
+        } else if header.starts_with(ZSTD_MAGIC) {
+            Compression::Zstd
+        } else {
+            Compression::None
+        }
+    }
+}
+
+/// 解压 tar 文件到 rootfs
+///
+/// # 参数
+/// - `layer`: tar 文件路径，可以是未压缩、gzip 或 zstd 格式
+/// - `rootfs`: 目标 rootfs 目录，必须已存在
+/// - `logger`: 日志记录器
+///
+/// # 返回
+/// 解压后 tar 流的 sha256 摘要，见 `unpack_layer`
+pub fn unpack_layer_file(layer: &Path, rootfs: &Path, logger: &Logger) -> Result<String> {
+    let file = File::open(layer).with_context(|| format!("Failed to open layer: {:?}", layer))?;
+    unpack_layer(file, rootfs, logger).with_context(|| format!("Failed to unpack {:?}", layer))
+}
+
+/// 解压 tar 流到 rootfs
+///
+/// # 参数
+/// - `reader`: tar 数据流，可以是未压缩、gzip 或 zstd 格式
+/// - `rootfs`: 目标 rootfs 目录，必须已存在
+/// - `logger`: 日志记录器
+///
+/// # 返回
+/// 解压后 tar 流的 sha256 摘要 `sha256:<hex>`，即镜像配置中的 diff_id；
+/// 任一条目解压失败时返回错误，错误信息包含条目路径
+pub fn unpack_layer<R: Read>(reader: R, rootfs: &Path, logger: &Logger) -> Result<String> {
+    let (compression, reader) = decompress(reader)?;
+    debug!(logger, "Unpacking layer";
+        "compression" => format!("{:?}", compression),
+        "rootfs" => rootfs.display().to_string());
+
+    let mut reader = DigestReader::new(reader);
+    unpack_tar(&mut reader, Unpacker::new(rootfs, false, logger)?)?;
+    io::copy(&mut reader, &mut io::sink()).context("Failed to read layer")?;
+    Ok(reader.digest())
+}
+
+/// 将镜像层单独解压到目录，供 overlayfs 作为下层使用
+///
+/// whiteout 转换为 overlayfs 的格式，创建字符设备和设置 `trusted.*` 扩展属性需要 root 权限。
+///
+/// # 参数
+/// - `reader`: tar 数据流，可以是未压缩、gzip 或 zstd 格式
+/// - `dir`: 目标目录，必须已存在且为空
+/// - `logger`: 日志记录器
+///
+/// # 返回
+/// 解压后 tar 流的 sha256 摘要 `sha256:<hex>`，即镜像配置中的 diff_id
+pub fn unpack_layer_diff<R: Read>(reader: R, dir: &Path, logger: &Logger) -> Result<String> {
+    let (compression, reader) = decompress(reader)?;
+    debug!(logger, "Unpacking layer for overlay";
+        "compression" => format!("{:?}", compression),
+        "dir" => dir.display().to_string());
+
+    let mut reader = DigestReader::new(reader);
+    unpack_tar(&mut reader, Unpacker::new(dir, true, logger)?)?;
+    // tar 结束标记之后可能还有填充数据，同样计入摘要
+    io::copy(&mut reader, &mut io::sink()).context("Failed to read layer")?;
+    Ok(reader.digest())
+}
+
+/// 计算 tar 文件解压后的 sha256 摘要，不写入任何文件
+///
+/// # 返回
+/// 摘要 `sha256:<hex>`，即镜像配置中的 diff_id
+pub fn diff_id(layer: &Path) -> Result<String> {
+    let file = File::open(layer).with_context(|| format!("Failed to open layer: {:?}", layer))?;
+    let (_, reader) = decompress(file)?;
+    let mut reader = DigestReader::new(reader);
+    io::copy(&mut reader, &mut io::sink())
+        .with_context(|| format!("Failed to read layer: {:?}", layer))?;
+    Ok(reader.digest())
+}
+
+/// 逐条解压 tar 流中的条目
+fn unpack_tar<R: Read>(reader: R, mut unpacker: Unpacker<'_>) -> Result<()> {
+    let mut archive = Archive::new(reader);
+    for entry in archive.entries().context("Failed to read tar archive")? {
+        let mut entry = entry.context("Failed to read tar entry")?;
+        let path = entry
+            .path()
+            .map(|p| p.into_owned())
+            .context("Invalid tar entry path")?;
+        unpacker
+            .unpack_entry(&mut entry)
+            .with_context(|| format!("Failed to unpack entry {:?}", path))?;
+    }
+    unpacker.finish()
+}
+
+/// 根据文件头识别压缩格式并返回解压后的数据流
+///
+/// # 返回
+/// 识别出的压缩格式和解压后的 tar 数据流
+pub fn decompress<'a, R: Read + 'a>(reader: R) -> Result<(Compression, Box<dyn Read + 'a>)> {
+    let mut reader = BufReader::new(reader);
+    let compression = Compression::detect(reader.fill_buf().context("Failed to read layer")?);
+
+    let reader: Box<dyn Read + 'a> = match compression {
+        Compression::None => Box::new(reader),
+        Compression::Gzip => Box::new(MultiGzDecoder::new(reader)),
+        Compression::Zstd => Box::new(
+            zstd::stream::read::Decoder::with_buffer(reader)
+                .context("Failed to create zstd decoder")?,
+        ),
+    };
+    Ok((compression, reader))
+}
+
+/// 读取数据的同时计算 sha256 摘要
+struct DigestReader<R> {
+    inner: R,
+    hasher: Sha256,
+}
+
+impl<R: Read> DigestReader<R> {
+    fn new(inner: R) -> Self {
+        Self {
+            inner,
+            hasher: Sha256::new(),
+        }
+    }
+
+    /// 已读取数据的摘要 `sha256:<hex>`
+    fn digest(self) -> String {
+        format!("sha256:{}", hex::encode(self.hasher.finalize()))
+    }
+}
+
+impl<R: Read> Read for DigestReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.hasher.update(&buf[..n]);
+        Ok(n)
+    }
+}
+
+/// 条目的元数据
+struct EntryMeta {
+    mode: u32,
+    uid: u64,
+    gid: u64,
+    mtime: i64,
+    xattrs: Vec<(OsString, Vec<u8>)>,
+}
+
+impl EntryMeta {
+    /// 读取条目头和 PAX 扩展头中的元数据，PAX 中的 uid/gid 优先
+    fn read<R: Read>(entry: &mut Entry<R>) -> Result<Self> {
+        let header = entry.header();
+        let mut meta = EntryMeta {
+            mode: header.mode()? & 0o7777,
+            uid: header.uid()?,
+            gid: header.gid()?,
+            mtime: header.mtime()? as i64,
+            xattrs: Vec::new(),
+        };
+
+        if let Some(extensions) = entry.pax_extensions()? {
+            for ext in extensions {
+                let ext = ext?;
+                let Ok(key) = ext.key() else {
+                    continue;
+                };
+                match key {
+                    "uid" => meta.uid = parse_pax_number(ext.value_bytes())?,
+                    "gid" => meta.gid = parse_pax_number(ext.value_bytes())?,
+                    _ => {
+                        if let Some(name) = key.strip_prefix(PAX_XATTR_PREFIX) {
+                            meta.xattrs.push((name.into(), ext.value_bytes().to_vec()));
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(meta)
+    }
+}
+
+fn parse_pax_number(value: &[u8]) -> Result<u64> {
+    std::str::from_utf8(value)
+        .ok()
+        .and_then(|v| v.trim().parse().ok())
+        .ok_or_else(|| anyhow!("Invalid PAX number: {:?}", String::from_utf8_lossy(value)))
+}
+
+/// 逐条解压 tar 条目
+struct Unpacker<'a> {
+    rootfs: PathBuf,
+    builder: ScopedDirBuilder,
+    /// 非 root 用户解压时跳过无权限的操作
+    rootless: bool,
+    /// 将 whiteout 转换为 overlayfs 的格式，而不是删除下层的内容
+    overlay: bool,
+    /// 跳过的操作数
+    skipped: usize,
+    /// 目录的权限和修改时间在全部条目解压后再设置，
+    /// 避免只读目录阻止写入子条目、写入子条目又改变目录的修改时间
+    dirs: Vec<(PathBuf, u32, i64)>,
+    /// 本层解压的条目，whiteout 只删除下层的内容
+    created: HashSet<PathBuf>,
+    logger: &'a Logger,
+}
+
+impl<'a> Unpacker<'a> {
+    fn new(rootfs: &Path, overlay: bool, logger: &'a Logger) -> Result<Self> {
+        let rootfs = rootfs
+            .canonicalize()
+            .with_context(|| format!("Invalid rootfs: {:?}", rootfs))?;
+        let mut builder = ScopedDirBuilder::new(&rootfs)?;
+        builder.recursive(true).mode(0o755);
+
+        Ok(Self {
+            rootfs,
+            builder,
+            rootless: !geteuid().is_root(),
+            overlay,
+            skipped: 0,
+            dirs: Vec::new(),
+            created: HashSet::new(),
+            logger,
+        })
+    }
+
+    fn unpack_entry<R: Read>(&mut self, entry: &mut Entry<R>) -> Result<()> {
+        let path = normalize(&entry.path()?);
+        // rootfs 本身（如 `./`）保持不变
+        let Some(name) = path.file_name().map(OsStr::to_os_string) else {
+            return Ok(());
+        };
+        if let Some(target) = name.to_str().and_then(|n| n.strip_prefix(WHITEOUT_PREFIX)) {
+            if self.overlay {
+                return self.convert_whiteout(&path, target);
+            }
+            return self.apply_whiteout(&path, target);
+        }
+        self.created.insert(path.clone());
+        let kind = entry.header().entry_type();
+        let meta = EntryMeta::read(entry)?;
+
+        let parent_path = path.parent().unwrap_or(Path::new(""));
+        let parent = self
+            .builder
+            .create(parent_path)
+            .with_context(|| format!("Failed to create parent directory {:?}", parent_path))?;
+        let dirfd = parent.path_fd();
+        let name = name.as_os_str();
+
+        match kind {
+            EntryType::Directory => {
+                match fstatat(dirfd, name, AtFlags::AT_SYMLINK_NOFOLLOW) {
+                    Ok(stat) if is_dir(stat.st_mode) => {}
+                    Ok(_) => {
+                        remove_existing(&parent, name)?;
+                        mkdirat(dirfd, name, Mode::from_bits_truncate(0o755))?;
+                    }
+                    Err(Errno::ENOENT) => {
+                        mkdirat(dirfd, name, Mode::from_bits_truncate(0o755))?;
+                    }
+                    Err(e) => return Err(e.into()),
+                }
+                self.dirs.push((path.clone(), meta.mode, meta.mtime));
+            }
+            EntryType::Regular | EntryType::Continuous => {
+                remove_existing(&parent, name)?;
+                let fd = openat(
+                    dirfd,
+                    name,
+                    OFlag::O_CREAT
+                        | OFlag::O_EXCL
+                        | OFlag::O_WRONLY
+                        | OFlag::O_NOFOLLOW
+                        | OFlag::O_CLOEXEC,
+                    Mode::from_bits_truncate(0o600),
+                )?;
+                // SAFETY: fd 由 openat 新建，所有权转移给 File
+                let mut file = unsafe { File::from_raw_fd(fd) };
+                io::copy(entry, &mut file)?;
+            }
+            EntryType::Symlink => {
+                let target = entry
+                    .link_name()?
+                    .ok_or_else(|| anyhow!("Symlink without target"))?;
+                remove_existing(&parent, name)?;
+                // 符号链接的目标原样保留，之后解析时同样限制在 rootfs 内
+                symlinkat(&*target, Some(dirfd), name)?;
+            }
+            EntryType::Link => {
+                let target = entry
+                    .link_name()?
+                    .ok_or_else(|| anyhow!("Hardlink without target"))?;
+                let target = normalize(&target);
+                let target_name = target
+                    .file_name()
+                    .ok_or_else(|| anyhow!("Invalid hardlink target {:?}", target))?;
+                let target_parent =
+                    PinnedPathBuf::new(&self.rootfs, target.parent().unwrap_or(Path::new("")))
+                        .with_context(|| format!("Invalid hardlink target {:?}", target))?;
+                remove_existing(&parent, name)?;
+                linkat(
+                    Some(target_parent.path_fd()),
+                    target_name,
+                    Some(dirfd),
+                    name,
+                    LinkatFlags::NoSymlinkFollow,
+                )
+                .with_context(|| format!("Failed to link to {:?}", target))?;
+                // 硬链接与目标共享 inode，不再设置元数据
+                return Ok(());
+            }
+            EntryType::Char | EntryType::Block | EntryType::Fifo => {
+                let (sflag, dev) = match kind {
+                    EntryType::Fifo => (SFlag::S_IFIFO, 0),
+                    _ => {
+                        let header = entry.header();
+                        let major = header.device_major()?.unwrap_or(0);
+                        let minor = header.device_minor()?.unwrap_or(0);
+                        let sflag = if kind == EntryType::Char {
+                            SFlag::S_IFCHR
+                        } else {
+                            SFlag::S_IFBLK
+                        };
+                        (sflag, makedev(major.into(), minor.into()))
+                    }
+                };
+                remove_existing(&parent, name)?;
+                match mknodat(dirfd, name, sflag, Mode::from_bits_truncate(0o600), dev) {
+                    Err(Errno::EPERM) if self.rootless && sflag != SFlag::S_IFIFO => {
+                        warn!(self.logger, "Skipping device node in rootless mode";
+                            "path" => path.display().to_string());
+                        self.skipped += 1;
+                        return Ok(());
+                    }
+                    res => res?,
+                }
+            }
+            kind => {
+                warn!(self.logger, "Skipping unsupported tar entry";
+                    "path" => path.display().to_string(),
+                    "type" => format!("{:?}", kind));
+                return Ok(());
+            }
+        }
+
+        self.set_owner(dirfd, name, &meta)?;
+        // chown 会清除 setuid/setgid 位，权限需要在之后设置；符号链接没有权限，目录稍后设置
+        if kind != EntryType::Symlink && kind != EntryType::Directory {
+            fchmodat(
+                Some(dirfd),
+                name,
+                Mode::from_bits_truncate(meta.mode),
+                FchmodatFlags::FollowSymlink,
+            )?;
+        }
+        self.set_xattrs(&parent, name, &meta)?;
+        if kind != EntryType::Directory {
+            set_mtime(Some(dirfd), name, meta.mtime)?;
+        }
+
+        Ok(())
+    }
+
+    /// 处理 whiteout 条目
+    ///
+    /// # 参数
+    /// - `path`: whiteout 条目的路径
+    /// - `target`: 去掉 `.wh.` 前缀后的文件名，不透明目录标记为 `.wh..opq`
+    fn apply_whiteout(&mut self, path: &Path, target: &str) -> Result<()> {
+        let parent_path = path.parent().unwrap_or(Path::new(""));
+        // 父目录在下层中不存在时无需删除
+        let parent = match PinnedPathBuf::new(&self.rootfs, parent_path) {
+            Ok(parent) if parent.metadata()?.is_dir() => parent,
+            Ok(_) => return Ok(()),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e.into()),
+        };
+
+        if path.file_name() == Some(OsStr::new(WHITEOUT_OPAQUE)) {
+            for child in fs::read_dir(parent.as_path())? {
+                let child = child?.file_name();
+                if !self.created.contains(&parent_path.join(&child)) {
+                    remove_existing(&parent, &child)?;
+                }
+            }
+            return Ok(());
+        }
+
+        if target.is_empty() || target == "." || target == ".." {
+            bail!("Invalid whiteout {:?}", path);
+        }
+        if !self.created.contains(&parent_path.join(target)) {
+            remove_existing(&parent, OsStr::new(target))?;
+        }
+        Ok(())
+    }
+
+    /// 将 whiteout 条目转换为 overlayfs 的格式
+    ///
+    /// # 参数
+    /// - `path`: whiteout 条目的路径
+    /// - `target`: 去掉 `.wh.` 前缀后的文件名，不透明目录标记为 `.wh..opq`
+    fn convert_whiteout(&mut self, path: &Path, target: &str) -> Result<()> {
+        let parent_path = path.parent().unwrap_or(Path::new(""));
+        let parent = self
+            .builder
+            .create(parent_path)
+            .with_context(|| format!("Failed to create parent directory {:?}", parent_path))?;
+
+        if path.file_name() == Some(OsStr::new(WHITEOUT_OPAQUE)) {
+            return set_xattr(parent.as_path(), OVERLAY_OPAQUE_XATTR, b"y")
+                .context("Failed to mark directory as opaque");
+        }
+
+        if target.is_empty() || target == "." || target == ".." {
+            bail!("Invalid whiteout {:?}", path);
+        }
+        // 本层中的同名条目优先于 whiteout
+        if self.created.contains(&parent_path.join(target)) {
+            return Ok(());
+        }
+        let name = OsStr::new(target);
+        remove_existing(&parent, name)?;
+        mknodat(
+            parent.path_fd(),
+            name,
+            SFlag::S_IFCHR,
+            Mode::empty(),
+            makedev(0, 0),
+        )
+        .context("Failed to create whiteout device")?;
+        Ok(())
+    }
+
+    /// 设置属主，非 root 用户无权限时跳过
+    fn set_owner(&mut self, dirfd: RawFd, name: &OsStr, meta: &EntryMeta) -> Result<()> {
+        let uid = Uid::from_raw(meta.uid.try_into()?);
+        let gid = Gid::from_raw(meta.gid.try_into()?);
+        match fchownat(
+            Some(dirfd),
+            name,
+            Some(uid),
+            Some(gid),
+            FchownatFlags::NoFollowSymlink,
+        ) {
+            Err(Errno::EPERM | Errno::EINVAL) if self.rootless => {
+                self.skipped += 1;
+                Ok(())
+            }
+            res => res.with_context(|| format!("Failed to chown to {}:{}", uid, gid)),
+        }
+    }
+
+    /// 设置扩展属性，文件系统不支持或非 root 用户无权限时跳过
+    fn set_xattrs(&mut self, parent: &PinnedPathBuf, name: &OsStr, meta: &EntryMeta) -> Result<()> {
+        if meta.xattrs.is_empty() {
+            return Ok(());
+        }
+        // 父目录已固定，`/proc/self/fd/N/name` 不会被替换到 rootfs 之外
+        let path = parent.as_path().join(name);
+        for (key, value) in &meta.xattrs {
+            // overlayfs 的属性由 whiteout 转换生成，不能由镜像层指定
+            if self.overlay && key.as_bytes().starts_with(OVERLAY_XATTR_PREFIX.as_bytes()) {
+                warn!(self.logger, "Skipping overlay xattr in layer";
+                    "xattr" => key.to_string_lossy().to_string());
+                self.skipped += 1;
+                continue;
+            }
+            match set_xattr(&path, key, value) {
+                Ok(_) => {}
+                Err(Errno::ENOTSUP) => {
+                    warn!(self.logger, "Filesystem does not support xattr";
+                        "xattr" => key.to_string_lossy().to_string());
+                    self.skipped += 1;
+                }
+                Err(Errno::EPERM) if self.rootless => self.skipped += 1,
+                Err(e) => {
+                    return Err(e).with_context(|| format!("Failed to set xattr {:?}", key));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// 设置目录的权限和修改时间，由深到浅处理
+    fn finish(mut self) -> Result<()> {
+        self.dirs
+            .sort_by_key(|(path, _, _)| std::cmp::Reverse(path.components().count()));
+        for (path, mode, mtime) in &self.dirs {
+            let dir = scoped_join(&self.rootfs, path)?;
+            // 目录可能被之后的条目替换
+            if !fs::symlink_metadata(&dir).is_ok_and(|m| m.is_dir()) {
+                continue;
+            }
+            fs::set_permissions(&dir, fs::Permissions::from_mode(*mode))
+                .with_context(|| format!("Failed to set permissions of {:?}", path))?;
+            set_mtime(None, dir.as_os_str(), *mtime)
+                .with_context(|| format!("Failed to set mtime of {:?}", path))?;
+        }
+
+        if self.skipped > 0 {
+            warn!(self.logger, "Some metadata was not preserved";
+                "skipped" => self.skipped, "rootless" => self.rootless);
+        }
+        Ok(())
+    }
+}
+
+/// 将条目路径规范化为 rootfs 内的相对路径
+///
+/// 去掉开头的 `/` 和 `.`，`..` 最多回退到 rootfs。
+pub(super) fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(c) => normalized.push(c),
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            Component::RootDir | Component::CurDir | Component::Prefix(_) => {}
+        }
+    }
+    normalized
+}
+
+fn is_dir(mode: libc::mode_t) -> bool {
+    mode & SFlag::S_IFMT.bits() == SFlag::S_IFDIR.bits()
+}
+
+/// 删除已存在的同名条目，不跟随符号链接
+fn remove_existing(parent: &PinnedPathBuf, name: &OsStr) -> Result<()> {
+    let dirfd = parent.path_fd();
+    match fstatat(dirfd, name, AtFlags::AT_SYMLINK_NOFOLLOW) {
+        Err(Errno::ENOENT) => Ok(()),
+        Err(e) => Err(e.into()),
+        Ok(stat) if is_dir(stat.st_mode) => {
+            fs::remove_dir_all(parent.as_path().join(name))?;
+            Ok(())
+        }
+        Ok(_) => {
+            unlinkat(Some(dirfd), name, UnlinkatFlags::NoRemoveDir)?;
+            Ok(())
+        }
+    }
+}
+
+/// 设置扩展属性，不跟随符号链接
+fn set_xattr(path: &Path, key: impl AsRef<OsStr>, value: &[u8]) -> nix::Result<()> {
+    let path = CString::new(path.as_os_str().as_bytes()).map_err(|_| Errno::EINVAL)?;
+    let key = CString::new(key.as_ref().as_bytes()).map_err(|_| Errno::EINVAL)?;
+    // SAFETY: 参数均为有效的 C 字符串和缓冲区
+    let res = unsafe {
+        libc::lsetxattr(
+            path.as_ptr(),
+            key.as_ptr(),
+            value.as_ptr() as *const libc::c_void,
+            value.len(),
+            0,
+        )
+    };
+    Errno::result(res).map(drop)
+}
+
+/// 设置访问时间和修改时间，不跟随符号链接
+fn set_mtime(dirfd: Option<RawFd>, name: &OsStr, mtime: i64) -> nix::Result<()> {
+    let time = TimeSpec::new(mtime, 0);
+    utimensat(dirfd, name, &time, &time, UtimensatFlags::NoFollowSymlink)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io::Write, os::unix::fs::MetadataExt};
+
+    use slog::Drain;
+    use tar::{Builder, Header};
+    use tempfile::tempdir;
+
+    use super::*;
+
+    fn test_logger() -> Logger {
+        let decorator = slog_term::PlainSyncDecorator::new(std::io::stdout());
+        let drain = slog_term::FullFormat::new(decorator).build().fuse();
+        Logger::root(drain, o!())
+    }
+
+    /// 构造条目，路径直接写入头部，不经过 tar 库的路径检查
+    fn header(path: &str, kind: EntryType, mode: u32, size: u64) -> Header {
+        let mut header = Header::new_gnu();
+        header.as_old_mut().name[..path.len()].copy_from_slice(path.as_bytes());
+        header.set_entry_type(kind);
+        header.set_mode(mode);
+        header.set_size(size);
+        header.set_mtime(1_600_000_000);
+        header.set_uid(geteuid().as_raw().into());
+        header.set_gid(nix::unistd::getegid().as_raw().into());
+        header
+    }
+
+    fn append_file(builder: &mut Builder<Vec<u8>>, path: &str, data: &[u8]) {
+        let mut h = header(path, EntryType::Regular, 0o644, data.len() as u64);
+        h.set_cksum();
+        builder.append(&h, data).unwrap();
+    }
+
+    fn append_link(builder: &mut Builder<Vec<u8>>, path: &str, kind: EntryType, target: &str) {
+        let mut h = header(path, kind, 0o777, 0);
+        h.as_old_mut().linkname[..target.len()].copy_from_slice(target.as_bytes());
+        h.set_cksum();
+        builder.append(&h, io::empty()).unwrap();
+    }
+
+    fn sample_layer() -> Vec<u8> {
+        let mut builder = Builder::new(Vec::new());
+        let mut h = header("etc/", EntryType::Directory, 0o750, 0);
+        h.set_cksum();
+        builder.append(&h, io::empty()).unwrap();
+        append_file(&mut builder, "etc/hostname", b"runcell\n");
+        let mut h = header("bin/tool", EntryType::Regular, 0o755, 4);
+        h.set_cksum();
+        builder.append(&h, &b"tool"[..]).unwrap();
+        append_link(&mut builder, "bin/alias", EntryType::Symlink, "tool");
+        append_link(&mut builder, "etc/name", EntryType::Link, "etc/hostname");
+        builder.into_inner().unwrap()
+    }
+
+    fn check_sample(rootfs: &Path) {
+        assert_eq!(
+            fs::read_to_string(rootfs.join("etc/hostname")).unwrap(),
+            "runcell\n"
+        );
+        let etc = fs::metadata(rootfs.join("etc")).unwrap();
+        assert_eq!(etc.mode() & 0o7777, 0o750);
+        assert_eq!(etc.mtime(), 1_600_000_000);
+
+        let tool = fs::metadata(rootfs.join("bin/tool")).unwrap();
+        assert_eq!(tool.mode() & 0o7777, 0o755);
+        assert_eq!(tool.mtime(), 1_600_000_000);
+        assert_eq!(
+            fs::read_link(rootfs.join("bin/alias")).unwrap(),
+            Path::new("tool")
+        );
+        let name = fs::metadata(rootfs.join("etc/name")).unwrap();
+        let hostname = fs::metadata(rootfs.join("etc/hostname")).unwrap();
+        assert_eq!(name.ino(), hostname.ino());
+    }
+
+    #[test]
+    fn test_detect_compression() {
+        assert_eq!(Compression::detect(&[0x1f, 0x8b, 0x08]), Compression::Gzip);
+        assert_eq!(
+            Compression::detect(&[0x28, 0xb5, 0x2f, 0xfd, 0x00]),
+            Compression::Zstd
+        );
+        assert_eq!(Compression::detect(b"etc/"), Compression::None);
+        assert_eq!(Compression::detect(&[]), Compression::None);
+    }
+
+    #[test]
+    fn test_unpack_compressed() {
+        let logger = test_logger();
+        let layer = sample_layer();
+
+        let mut gzip = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::fast());
+        gzip.write_all(&layer).unwrap();
+        let gzip = gzip.finish().unwrap();
+        let zstd = zstd::stream::encode_all(&layer[..], 0).unwrap();
+
+        for data in [layer, gzip, zstd] {
+            let dir = tempdir().unwrap();
+            unpack_layer(&data[..], dir.path(), &logger).unwrap();
+            check_sample(dir.path());
+        }
+    }
+
+    #[test]
+    fn test_unpack_overwrite() {
+        let logger = test_logger();
+        let dir = tempdir().unwrap();
+        unpack_layer(&sample_layer()[..], dir.path(), &logger).unwrap();
+
+        // 后一层的普通文件替换前一层的符号链接，不应写入链接目标
+        let mut builder = Builder::new(Vec::new());
+        append_file(&mut builder, "bin/alias", b"new");
+        unpack_layer(&builder.into_inner().unwrap()[..], dir.path(), &logger).unwrap();
+
+        assert_eq!(fs::read(dir.path().join("bin/alias")).unwrap(), b"new");
+        assert_eq!(fs::read(dir.path().join("bin/tool")).unwrap(), b"tool");
+    }
+
+    #[test]
+    fn test_unpack_whiteout() {
+        let logger = test_logger();
+        let dir = tempdir().unwrap();
+
+        let mut builder = Builder::new(Vec::new());
+        append_file(&mut builder, "opaque/lower", b"x");
+        append_file(&mut builder, "removed", b"x");
+        append_file(&mut builder, "kept", b"x");
+        unpack_layer(&builder.into_inner().unwrap()[..], dir.path(), &logger).unwrap();
+
+        // 本层写入的条目在不透明标记之前，不应被清空
+        let mut builder = Builder::new(Vec::new());
+        append_file(&mut builder, "opaque/upper", b"x");
+        append_file(&mut builder, "opaque/.wh..wh..opq", b"");
+        append_file(&mut builder, ".wh.removed", b"");
+        append_file(&mut builder, "missing/.wh.file", b"");
+        unpack_layer(&builder.into_inner().unwrap()[..], dir.path(), &logger).unwrap();
+
+        assert!(!dir.path().join("opaque/lower").exists());
+        assert!(dir.path().join("opaque/upper").is_file());
+        assert!(!dir.path().join("opaque/.wh..wh..opq").exists());
+        assert!(!dir.path().join("removed").exists());
+        assert!(!dir.path().join(".wh.removed").exists());
+        assert!(!dir.path().join("missing").exists());
+        assert!(dir.path().join("kept").is_file());
+    }
+
+    #[test]
+    fn test_unpack_path_traversal() {
+        let logger = test_logger();
+        let dir = tempdir().unwrap();
+        let rootfs = dir.path().join("rootfs");
+        fs::create_dir(&rootfs).unwrap();
+
+        let mut builder = Builder::new(Vec::new());
+        append_file(&mut builder, "../dotdot", b"x");
+        append_file(&mut builder, "/absolute", b"x");
+        append_link(&mut builder, "up", EntryType::Symlink, "../..");
+        append_file(&mut builder, "up/via-relative", b"x");
+        let outside = dir.path().display().to_string();
+        append_link(&mut builder, "abs", EntryType::Symlink, &outside);
+        append_file(&mut builder, "abs/via-absolute", b"x");
+        unpack_layer(&builder.into_inner().unwrap()[..], &rootfs, &logger).unwrap();
+
+        assert!(rootfs.join("dotdot").is_file());
+        assert!(rootfs.join("absolute").is_file());
+        assert!(rootfs.join("via-relative").is_file());
+        let scoped = scoped_join(&rootfs, outside.trim_start_matches('/')).unwrap();
+        assert!(scoped.join("via-absolute").is_file());
+        assert!(!dir.path().join("dotdot").exists());
+        assert!(!dir.path().join("via-absolute").exists());
+
+        // 硬链接目标被限制在 rootfs 内，指向宿主机文件时报错
+        let mut builder = Builder::new(Vec::new());
+        append_link(
+            &mut builder,
+            "passwd",
+            EntryType::Link,
+            "../../../../etc/passwd",
+        );
+        let err = unpack_layer(&builder.into_inner().unwrap()[..], &rootfs, &logger).unwrap_err();
+        assert!(format!("{:#}", err).contains("passwd"));
+        assert!(!rootfs.join("passwd").exists());
+    }
+}
@@ -36,6 +36,8 @@ pub mod handler;
 pub mod image;
 pub mod mount;
 
+use std::path::Path;
+
 use anyhow::{Result, anyhow};
 pub use device::{StorageDevice, StorageDeviceGeneric};
 pub use handler::{
@@ -52,6 +54,7 @@ use slog::Logger;
 /// - `logger`: 日志记录器
 /// - `storages`: 存储配置列表
 /// - `container_id`: 容器 ID
+/// - `image_root`: 镜像根目录（镜像拉取存储使用）
 ///
 /// # 返回
 /// 挂载点路径列表
@@ -65,6 +68,7 @@ pub async fn add_storages(
     logger: &Logger,
     storages: Vec<StorageConfig>,
     container_id: &str,
+    image_root: &Path,
 ) -> Result<Vec<String>> {
     let mut mount_list = Vec::new();
 
@@ -81,6 +85,7 @@ pub async fn add_storages(
 
         let mut ctx = StorageContext {
             container_id: Some(container_id.to_string()),
+            image_root,
             logger: &logger,
         };
 